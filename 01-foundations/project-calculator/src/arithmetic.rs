@@ -0,0 +1,145 @@
+// Arithmetic - the basic float operations behind the default mode
+//
+// `calculate` applies one operator to two f64 values and turns results
+// that are not numbers (NaN, infinity) into calculator errors. The exact
+// modes in number.rs fall back to it when no exact answer exists.
+
+use crate::error::CalcError;
+
+pub fn parse_number(s: &str) -> Result<f64, CalcError> {
+    let n: f64 = s.parse().map_err(|_| CalcError::InvalidNumber(s.to_string(), None))?;
+    // "1e999" parses, but only to infinity
+    if n.is_infinite() {
+        return Err(CalcError::Overflow);
+    }
+    Ok(n)
+}
+
+pub fn calculate(left: f64, operator: &str, right: f64) -> Result<f64, CalcError> {
+    let result = match operator {
+        "+" => add(left, right),
+        "-" => subtract(left, right),
+        "*" => multiply(left, right),
+        "/" | "//" | "%" if right == 0.0 => return Err(CalcError::DivisionByZero),
+        "/" => divide(left, right),
+        "//" => int_divide(left, right),
+        "%" => modulo(left, right),
+        "^" if left == 0.0 && right < 0.0 => return Err(CalcError::DivisionByZero),
+        "^" => power(left, right),
+        _ => return Err(CalcError::UnknownOperator(operator.to_string(), None)),
+    };
+
+    if result.is_nan() {
+        return Err(CalcError::Domain(format!("{} {} {} is undefined", left, operator, right)));
+    }
+    if result.is_infinite() {
+        return Err(CalcError::Overflow);
+    }
+    Ok(result)
+}
+
+fn add(a: f64, b: f64) -> f64 {
+    a + b
+}
+
+fn subtract(a: f64, b: f64) -> f64 {
+    a - b
+}
+
+fn multiply(a: f64, b: f64) -> f64 {
+    a * b
+}
+
+fn divide(a: f64, b: f64) -> f64 {
+    a / b
+}
+
+// Rounds down, so -7 // 2 is -4
+fn int_divide(a: f64, b: f64) -> f64 {
+    (a / b).floor()
+}
+
+// The remainder takes the sign of the divisor, so -7 % 3 is 2
+fn modulo(a: f64, b: f64) -> f64 {
+    let remainder = a % b;
+    if remainder != 0.0 && (remainder < 0.0) != (b < 0.0) {
+        remainder + b
+    } else {
+        remainder
+    }
+}
+
+fn power(a: f64, b: f64) -> f64 {
+    a.powf(b)
+}
+
+// Tests to verify your implementation
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_addition() {
+        let result = calculate(5.0, "+", 3.0);
+        assert_eq!(result, Ok(8.0));
+    }
+
+    #[test]
+    fn test_subtraction() {
+        let result = calculate(10.0, "-", 4.0);
+        assert_eq!(result, Ok(6.0));
+    }
+
+    #[test]
+    fn test_multiplication() {
+        let result = calculate(6.0, "*", 7.0);
+        assert_eq!(result, Ok(42.0));
+    }
+
+    #[test]
+    fn test_division() {
+        let result = calculate(15.0, "/", 3.0);
+        assert_eq!(result, Ok(5.0));
+    }
+
+    #[test]
+    #[allow(clippy::approx_constant)] // 3.14 is just a number here, not pi
+    fn test_parse_number() {
+        assert_eq!(parse_number("42"), Ok(42.0));
+        assert_eq!(parse_number("3.14"), Ok(3.14));
+    }
+
+    #[test]
+    fn test_parse_number_errors() {
+        assert_eq!(parse_number("1.2.3"), Err(CalcError::InvalidNumber("1.2.3".to_string(), None)));
+        assert_eq!(parse_number("abc"), Err(CalcError::InvalidNumber("abc".to_string(), None)));
+        assert_eq!(parse_number("1e999"), Err(CalcError::Overflow));
+    }
+
+    #[test]
+    fn test_calculate_errors() {
+        assert_eq!(calculate(1.0, "/", 0.0), Err(CalcError::DivisionByZero));
+        assert_eq!(calculate(5.0, "@", 3.0), Err(CalcError::UnknownOperator("@".to_string(), None)));
+        assert_eq!(calculate(5.0, "%", 0.0), Err(CalcError::DivisionByZero));
+        assert_eq!(calculate(0.0, "^", -1.0), Err(CalcError::DivisionByZero));
+        assert_eq!(calculate(10.0, "^", 400.0), Err(CalcError::Overflow));
+        assert!(matches!(calculate(-8.0, "^", 0.5), Err(CalcError::Domain(_))));
+        assert_eq!(calculate(f64::MAX, "*", 2.0), Err(CalcError::Overflow));
+        assert!(matches!(
+            calculate(f64::INFINITY, "-", f64::INFINITY),
+            Err(CalcError::Domain(_))
+        ));
+    }
+
+    #[test]
+    fn test_helper_functions() {
+        assert_eq!(add(2.0, 3.0), 5.0);
+        assert_eq!(subtract(10.0, 4.0), 6.0);
+        assert_eq!(multiply(3.0, 4.0), 12.0);
+        assert_eq!(divide(8.0, 2.0), 4.0);
+        assert_eq!(int_divide(-7.0, 2.0), -4.0);
+        assert_eq!(modulo(-7.0, 3.0), 2.0);
+        assert_eq!(modulo(7.0, -3.0), -2.0);
+        assert_eq!(power(2.0, 10.0), 1024.0);
+    }
+}
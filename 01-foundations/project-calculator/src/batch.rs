@@ -0,0 +1,224 @@
+// Batch mode - one expression per line from stdin or a file
+//
+//   printf 'x = 2\nx ^ 10\n' | cargo run -- --batch
+//   cargo run -- --file=sums.txt --format=csv
+//
+// Lines share variables and `ans` just like an interactive session. Blank
+// lines and lines starting with # are skipped. Results go to stdout and
+// errors to stderr with their line number, so a pipeline can keep the two
+// apart. A failed line does not stop the run unless --fail-fast is given.
+
+use std::io::{self, BufRead, Write};
+
+use crate::error::CalcError;
+use crate::value::Value;
+use crate::{evaluate, parse, Env};
+
+/// How results are written to stdout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// Just the result, one per line
+    #[default]
+    Plain,
+    /// `line,expression,result` with a header row
+    Csv,
+    /// One JSON object per line: {"line":1,"expression":"1 + 2","result":"3"}
+    Json,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "plain" => Some(Format::Plain),
+            "csv" => Some(Format::Csv),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Options {
+    pub format: Format,
+    pub fail_fast: bool,
+}
+
+/// Evaluates every line of `input`. Returns the first error, if any line
+/// failed; reading or writing problems are returned as `io::Error`.
+pub fn run(
+    input: impl BufRead,
+    out: &mut impl Write,
+    err: &mut impl Write,
+    mut env: Env,
+    options: Options,
+) -> io::Result<Option<CalcError>> {
+    let mut first_error = None;
+
+    if options.format == Format::Csv {
+        writeln!(out, "line,expression,result")?;
+    }
+
+    for (index, line) in input.lines().enumerate() {
+        let line = line?;
+        let number = index + 1;
+        let expression = line.trim();
+        if expression.is_empty() || expression.starts_with('#') {
+            continue;
+        }
+
+        match parse(expression).and_then(|ast| evaluate(&ast, &mut env)) {
+            Ok(result) => {
+                write_result(out, options.format, number, expression, &result, &env)?
+            }
+            Err(e) => {
+                writeln!(err, "line {}: {}", number, e.render(expression))?;
+                first_error.get_or_insert(e);
+                if options.fail_fast {
+                    break;
+                }
+            }
+        }
+    }
+
+    out.flush()?;
+    Ok(first_error)
+}
+
+fn write_result(
+    out: &mut impl Write,
+    format: Format,
+    line: usize,
+    expression: &str,
+    result: &Value,
+    env: &Env,
+) -> io::Result<()> {
+    let result = env.show(result);
+    match format {
+        Format::Plain => writeln!(out, "{}", result),
+        Format::Csv => writeln!(
+            out,
+            "{},{},{}",
+            line,
+            csv_field(expression),
+            csv_field(&result)
+        ),
+        Format::Json => writeln!(
+            out,
+            "{{\"line\":{},\"expression\":{},\"result\":{}}}",
+            line,
+            json_string(expression),
+            json_string(&result)
+        ),
+    }
+}
+
+// Fields with commas or quotes are quoted, and quotes inside are doubled
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Runs `input` and returns (stdout, stderr, first error)
+    fn batch(input: &str, options: Options) -> (String, String, Option<CalcError>) {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let first_error = run(
+            input.as_bytes(),
+            &mut out,
+            &mut err,
+            Env::default(),
+            options,
+        )
+        .unwrap();
+        (
+            String::from_utf8(out).unwrap(),
+            String::from_utf8(err).unwrap(),
+            first_error,
+        )
+    }
+
+    fn with_format(format: Format) -> Options {
+        Options {
+            format,
+            ..Options::default()
+        }
+    }
+
+    #[test]
+    fn test_plain_output_shares_variables() {
+        let (out, err, error) = batch(
+            "x = 2\n\n# powers of two\nx ^ 10\nans / 4\n",
+            Options::default(),
+        );
+        assert_eq!(out, "2\n1024\n256\n");
+        assert_eq!(err, "");
+        assert_eq!(error, None);
+    }
+
+    #[test]
+    fn test_errors_are_numbered_and_skipped() {
+        let (out, err, error) = batch("1 / 0\n2 +\n3 * 3\n", Options::default());
+        assert_eq!(out, "9\n");
+        assert!(
+            err.starts_with("line 1: Error: division by zero\nline 2: Error: expected a number")
+        );
+        assert_eq!(error, Some(CalcError::DivisionByZero));
+    }
+
+    #[test]
+    fn test_fail_fast_stops_at_the_first_error() {
+        let options = Options {
+            fail_fast: true,
+            ..Options::default()
+        };
+        let (out, err, error) = batch("1 + 1\nnope\n3 * 3\n", options);
+        assert_eq!(out, "2\n");
+        assert_eq!(err, "line 2: Error: unknown variable 'nope'\n");
+        assert_eq!(error, Some(CalcError::UnknownVariable("nope".to_string())));
+    }
+
+    #[test]
+    fn test_csv_output() {
+        let (out, _, _) = batch("max(1, 2)\n\n512 MiB / 2 s\n", with_format(Format::Csv));
+        assert_eq!(
+            out,
+            "line,expression,result\n1,\"max(1, 2)\",2\n3,512 MiB / 2 s,256 MiB/s\n"
+        );
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn test_json_output() {
+        let (out, _, _) = batch("x = 1 / 4\n", with_format(Format::Json));
+        assert_eq!(
+            out,
+            "{\"line\":1,\"expression\":\"x = 1 / 4\",\"result\":\"0.25\"}\n"
+        );
+        assert_eq!(json_string("a\"b\\c\u{1}"), "\"a\\\"b\\\\c\\u0001\"");
+    }
+}
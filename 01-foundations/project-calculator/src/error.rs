@@ -0,0 +1,121 @@
+// CalcError - everything that can go wrong between reading the input and
+// printing a result
+//
+// Each kind of error has its own exit code, so scripts calling the
+// calculator can tell a typo from a division by zero.
+
+use std::fmt;
+
+use crate::parser::ParseError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalcError {
+    /// The input is not a well-formed expression
+    Syntax(ParseError),
+    /// Text that starts like a number but is not one, e.g. `1.2.3` or `12abc`,
+    /// and the column where it stops being a number if it came from the input
+    InvalidNumber(String, Option<usize>),
+    /// An operator or function that does not exist, and its column if known
+    UnknownOperator(String, Option<usize>),
+    UnknownVariable(String),
+    DivisionByZero,
+    /// A number or result too large to represent
+    Overflow,
+    /// An operation that is undefined for its operands
+    Domain(String),
+    /// Units that do not fit together, e.g. MiB + s
+    Unit(String),
+}
+
+impl CalcError {
+    /// Exit status of the command line calculator for this error
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CalcError::Syntax(_) => 2,
+            CalcError::InvalidNumber(..) => 3,
+            CalcError::UnknownOperator(..) => 4,
+            CalcError::UnknownVariable(_) => 5,
+            CalcError::DivisionByZero => 6,
+            CalcError::Overflow => 7,
+            CalcError::Domain(_) => 8,
+            CalcError::Unit(_) => 9,
+        }
+    }
+
+    /// The message to show the user; errors with a column also point at it
+    pub fn render(&self, input: &str) -> String {
+        match self {
+            CalcError::Syntax(e) => e.render(input),
+            CalcError::InvalidNumber(_, Some(column)) | CalcError::UnknownOperator(_, Some(column)) => {
+                format!("Error: {} at column {}\n  {}\n  {}^", self, column, input, " ".repeat(column - 1))
+            }
+            _ => format!("Error: {}", self),
+        }
+    }
+}
+
+impl fmt::Display for CalcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CalcError::Syntax(e) => write!(f, "{}", e),
+            CalcError::InvalidNumber(text, _) => write!(f, "invalid number '{}'", text),
+            CalcError::UnknownOperator(op, _) => write!(f, "unknown operator '{}'", op),
+            CalcError::UnknownVariable(name) => write!(f, "unknown variable '{}'", name),
+            CalcError::DivisionByZero => write!(f, "division by zero"),
+            CalcError::Overflow => write!(f, "number too large"),
+            CalcError::Domain(message) => write!(f, "domain error: {}", message),
+            CalcError::Unit(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for CalcError {}
+
+impl From<ParseError> for CalcError {
+    fn from(e: ParseError) -> Self {
+        CalcError::Syntax(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes_are_distinct() {
+        let errors = [
+            CalcError::Syntax(ParseError::new("expected a number", 1)),
+            CalcError::InvalidNumber("1.2.3".to_string(), Some(4)),
+            CalcError::UnknownOperator("^".to_string(), None),
+            CalcError::UnknownVariable("x".to_string()),
+            CalcError::DivisionByZero,
+            CalcError::Overflow,
+            CalcError::Domain("inf - inf is undefined".to_string()),
+            CalcError::Unit("incompatible units: MiB + s".to_string()),
+        ];
+
+        let mut codes: Vec<i32> = errors.iter().map(CalcError::exit_code).collect();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), errors.len());
+        // 1 is left for failures that are not about the expression
+        assert!(!codes.contains(&0) && !codes.contains(&1));
+    }
+
+    #[test]
+    fn test_render() {
+        assert_eq!(CalcError::DivisionByZero.render("1 / 0"), "Error: division by zero");
+        assert_eq!(
+            CalcError::Syntax(ParseError::new("expected a number, found end of input", 4)).render("5 +"),
+            "Error: expected a number, found end of input at column 4\n  5 +\n     ^"
+        );
+        assert_eq!(
+            CalcError::UnknownOperator("@".to_string(), Some(3)).render("5 @ 3"),
+            "Error: unknown operator '@' at column 3\n  5 @ 3\n    ^"
+        );
+        assert_eq!(
+            CalcError::InvalidNumber("1.2.3".to_string(), None).render("1.2.3"),
+            "Error: invalid number '1.2.3'"
+        );
+    }
+}
@@ -0,0 +1,201 @@
+// Evaluation - walks an expression tree and computes its value
+//
+// Each operation is handed to value.rs, which checks units and passes the
+// numbers on to number.rs in the current mode. An `Env` carries everything
+// a line can depend on or change: variables, angle mode, number mode and
+// the base results are shown in.
+
+use std::collections::BTreeMap;
+
+use crate::error::CalcError;
+use crate::functions::{self, AngleMode};
+use crate::number::{Number, NumberMode};
+use crate::parser::{Expr, Statement};
+use crate::value::{self, Value};
+use crate::Ast;
+
+/// What an expression is evaluated against: the user's variables, whether
+/// trigonometry works in degrees or radians, and which arithmetic to use.
+/// `base` is the base whole-number results are shown in.
+#[derive(Debug)]
+pub struct Env {
+    pub variables: BTreeMap<String, Value>,
+    pub angles: AngleMode,
+    pub mode: NumberMode,
+    pub base: u32,
+}
+
+impl Default for Env {
+    fn default() -> Self {
+        Env {
+            variables: BTreeMap::new(),
+            angles: AngleMode::default(),
+            mode: NumberMode::default(),
+            base: 10,
+        }
+    }
+}
+
+impl Env {
+    /// A result as the user sees it, in the current mode and base
+    pub fn show(&self, value: &Value) -> String {
+        value.format_in_base(self.mode, self.base)
+    }
+}
+
+/// Evaluates a parsed line. An assignment (`x = 3.5`) stores the variable,
+/// and every result is also stored as `ans`.
+pub fn evaluate(ast: &Ast, env: &mut Env) -> Result<Value, CalcError> {
+    let (name, expr) = match ast {
+        Statement::Assign { name, value } => (Some(name), value),
+        Statement::Expr(expr) => (None, expr),
+    };
+
+    let result = evaluate_expr(expr, env)?;
+    env.variables.insert("ans".to_string(), result.clone());
+    if let Some(name) = name {
+        env.variables.insert(name.clone(), result.clone());
+    }
+    Ok(result)
+}
+
+fn evaluate_expr(expr: &Expr, env: &Env) -> Result<Value, CalcError> {
+    match expr {
+        Expr::Number(text) => Ok(Value::plain(Number::parse(text, env.mode)?)),
+        Expr::Quantity { number, unit } => Ok(Value {
+            number: Number::parse(number, env.mode)?,
+            unit: unit.clone(),
+        }),
+        Expr::Variable(name) => functions::constant(name)
+            .map(|value| Value::plain(env.mode.inexact(value)))
+            .or_else(|| env.variables.get(name).cloned())
+            .ok_or_else(|| CalcError::UnknownVariable(name.clone())),
+        Expr::Negate(inner) => Ok(evaluate_expr(inner, env)?.negate()),
+        Expr::Not(inner) => value::not(evaluate_expr(inner, env)?, env.mode),
+        Expr::Binary { op, left, right } => {
+            let left = evaluate_expr(left, env)?;
+            let right = evaluate_expr(right, env)?;
+            value::binary(left, *op, right, env.mode)
+        }
+        Expr::Call { name, args } => {
+            let args = args
+                .iter()
+                .map(|arg| evaluate_expr(arg, env))
+                .collect::<Result<Vec<Value>, CalcError>>()?;
+            value::call(name, args, env.angles, env.mode)
+        }
+        Expr::Convert { value, unit } => value::convert(evaluate_expr(value, env)?, unit, env.mode),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::number::Word;
+    use crate::parser;
+
+    #[test]
+    fn test_evaluate_expressions() {
+        let eval = |input: &str| evaluate_expr(&parser::parse(input).unwrap(), &Env::default()).map(|v| v.number.to_f64());
+
+        assert_eq!(eval("2 * (3 + 4) / 7"), Ok(2.0));
+        assert_eq!(eval("1 + 2 * 3"), Ok(7.0));
+        assert_eq!(eval("(1 + 2) * 3"), Ok(9.0));
+        assert_eq!(eval("8 - 3 - 2"), Ok(3.0));
+        assert_eq!(eval("16 / 4 / 2"), Ok(2.0));
+        assert_eq!(eval("-3 * -(2 + 1)"), Ok(9.0));
+        assert_eq!(eval("1 / (2 - 2)"), Err(CalcError::DivisionByZero));
+        assert_eq!(eval("2 * r"), Err(CalcError::UnknownVariable("r".to_string())));
+
+        assert_eq!(eval("2 ^ 3 ^ 2"), Ok(512.0));
+        assert_eq!(eval("-2 ^ 2"), Ok(-4.0));
+        assert_eq!(eval("17 // 5 * 5 + 17 % 5"), Ok(17.0));
+        assert_eq!(eval("max(sqrt(16), abs(-5), floor(e))"), Ok(5.0));
+        assert_eq!(eval("round(pi * 1000)"), Ok(3142.0));
+        assert!(matches!(eval("ln(1 - 1)"), Err(CalcError::Domain(_))));
+    }
+
+    #[test]
+    fn test_evaluate_in_degrees() {
+        let env = Env {
+            angles: AngleMode::Degrees,
+            ..Env::default()
+        };
+        let eval = |input: &str| evaluate_expr(&parser::parse(input).unwrap(), &env).map(|v| v.number.to_f64());
+
+        assert_eq!(eval("sin(90) + cos(180)"), Ok(0.0));
+        assert_eq!(eval("acos(0)"), Ok(90.0));
+        assert!(matches!(eval("tan(90)"), Err(CalcError::Domain(_))));
+    }
+
+    #[test]
+    fn test_evaluate_exact_modes() {
+        let eval = |input: &str, mode: NumberMode| {
+            let env = Env {
+                mode,
+                ..Env::default()
+            };
+            evaluate_expr(&parser::parse(input).unwrap(), &env).map(|v| env.show(&v))
+        };
+
+        assert_eq!(eval("0.1 + 0.2", NumberMode::Float), Ok("0.30000000000000004".to_string()));
+        assert_eq!(eval("0.1 + 0.2", NumberMode::Rational), Ok("3/10".to_string()));
+        assert_eq!(eval("0.1 + 0.2", NumberMode::Decimal(20)), Ok("0.3".to_string()));
+        assert_eq!(eval("-(1/3) + 1/6", NumberMode::Rational), Ok("-1/6".to_string()));
+        assert_eq!(
+            eval("12345678901234567890 * 10 + 1", NumberMode::Rational),
+            Ok("123456789012345678901".to_string())
+        );
+        assert_eq!(eval("pi", NumberMode::Decimal(5)), Ok("3.14159".to_string()));
+        assert_eq!(eval("1e999 / 1e998", NumberMode::Rational), Ok("10".to_string()));
+        assert_eq!(eval("1 / 0", NumberMode::Decimal(20)), Err(CalcError::DivisionByZero));
+    }
+
+    #[test]
+    fn test_evaluate_units() {
+        let eval = |input: &str, mode: NumberMode| {
+            let env = Env {
+                mode,
+                ..Env::default()
+            };
+            evaluate_expr(&parser::parse(input).unwrap(), &env).map(|v| env.show(&v))
+        };
+        let float = |input: &str| eval(input, NumberMode::Float);
+
+        assert_eq!(float("512 MiB / 2 s"), Ok("256 MiB/s".to_string()));
+        assert_eq!(float("3 h in min"), Ok("180 min".to_string()));
+        assert_eq!(float("1 h + 30 min to min"), Ok("90 min".to_string()));
+        assert_eq!(float("-2 km * 3"), Ok("-6 km".to_string()));
+        assert_eq!(float("100 MB / (10 MB/s) in min"), Ok("0.16666666666666666 min".to_string()));
+        assert_eq!(float("(2 km in m) * 3"), Ok("6000 m".to_string()));
+        assert_eq!(eval("1 GB to GiB", NumberMode::Rational), Ok("1953125/2097152 GiB".to_string()));
+        assert_eq!(
+            float("1 MiB + 1 s"),
+            Err(CalcError::Unit("incompatible units: MiB + s".to_string()))
+        );
+        assert_eq!(float("3 kg in lb").map(|s| s[..4].to_string()), Ok("6.61".to_string()));
+    }
+
+    #[test]
+    fn test_evaluate_programmer_mode() {
+        let show = |input: &str, mode: NumberMode, base: u32| {
+            let env = Env {
+                mode,
+                base,
+                ..Env::default()
+            };
+            evaluate_expr(&parser::parse(input).unwrap(), &env).map(|v| env.show(&v))
+        };
+        let u8 = NumberMode::Integer(Word { bits: 8, signed: false });
+        let i32 = NumberMode::Integer(Word { bits: 32, signed: true });
+
+        assert_eq!(show("0xff & ~0x0f | 1 << 2", NumberMode::Float, 10), Ok("244".to_string()));
+        assert_eq!(show("0xff & ~0x0f | 1 << 2", u8, 16), Ok("0xf4".to_string()));
+        assert_eq!(show("0b1010 xor 0b0110", u8, 2), Ok("0b1100".to_string()));
+        assert_eq!(show("0x7fffffff + 1", i32, 10), Ok("-2147483648".to_string()));
+        assert_eq!(show("-1", i32, 16), Ok("0xffffffff".to_string()));
+        assert_eq!(show("255 + 1", u8, 10), Ok("0".to_string()));
+        assert_eq!(show("4 KiB", NumberMode::Float, 16), Ok("0x4 KiB".to_string()));
+        assert!(matches!(show("1.5 & 1", NumberMode::Float, 10), Err(CalcError::Domain(_))));
+    }
+}
@@ -0,0 +1,195 @@
+// Functions and constants - sqrt(2), max(1, x, 3), pi, e
+//
+// The parser checks names and argument counts with `arity`, so by the time
+// `call` runs the arguments are known to fit. Results that would come out
+// as NaN or infinity are turned into calculator errors instead.
+
+use std::f64::consts;
+
+use crate::error::CalcError;
+
+/// How trigonometric functions read and return angles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AngleMode {
+    #[default]
+    Radians,
+    Degrees,
+}
+
+impl AngleMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AngleMode::Radians => "radians",
+            AngleMode::Degrees => "degrees",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arity {
+    Exactly(usize),
+    AtLeast(usize),
+}
+
+impl Arity {
+    pub fn accepts(&self, count: usize) -> bool {
+        match *self {
+            Arity::Exactly(n) => count == n,
+            Arity::AtLeast(n) => count >= n,
+        }
+    }
+
+    /// "1 argument", "at least 1 argument", ...
+    pub fn describe(&self) -> String {
+        let (prefix, n) = match *self {
+            Arity::Exactly(n) => ("", n),
+            Arity::AtLeast(n) => ("at least ", n),
+        };
+        format!("{}{} argument{}", prefix, n, if n == 1 { "" } else { "s" })
+    }
+}
+
+pub fn arity(name: &str) -> Option<Arity> {
+    match name {
+        "sqrt" | "abs" | "ln" | "log10" | "exp" | "sin" | "cos" | "tan" | "asin" | "acos" | "atan" | "floor"
+        | "ceil" | "round" => Some(Arity::Exactly(1)),
+        "min" | "max" => Some(Arity::AtLeast(1)),
+        _ => None,
+    }
+}
+
+pub fn constant(name: &str) -> Option<f64> {
+    match name {
+        "pi" => Some(consts::PI),
+        "e" => Some(consts::E),
+        _ => None,
+    }
+}
+
+pub fn call(name: &str, args: &[f64], angles: AngleMode) -> Result<f64, CalcError> {
+    let x = args.first().copied().unwrap_or_default();
+
+    let result = match name {
+        "sqrt" if x < 0.0 => return Err(domain("sqrt is not defined for negative numbers")),
+        "sqrt" => x.sqrt(),
+        "abs" => x.abs(),
+        "ln" | "log10" if x <= 0.0 => return Err(domain(&format!("{} is only defined for positive numbers", name))),
+        "ln" => x.ln(),
+        "log10" => x.log10(),
+        "exp" => x.exp(),
+        "sin" | "cos" | "tan" => trig(name, x, angles)?,
+        "asin" | "acos" if !(-1.0..=1.0).contains(&x) => {
+            return Err(domain(&format!("{} is only defined from -1 to 1", name)))
+        }
+        "asin" => from_radians(x.asin(), angles),
+        "acos" => from_radians(x.acos(), angles),
+        "atan" => from_radians(x.atan(), angles),
+        "floor" => x.floor(),
+        "ceil" => x.ceil(),
+        "round" => x.round(),
+        "min" => args.iter().copied().fold(f64::INFINITY, f64::min),
+        "max" => args.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        _ => return Err(CalcError::UnknownOperator(name.to_string(), None)),
+    };
+
+    if result.is_nan() {
+        return Err(domain(&format!("{} is undefined here", name)));
+    }
+    if result.is_infinite() {
+        return Err(CalcError::Overflow);
+    }
+    Ok(result)
+}
+
+fn trig(name: &str, angle: f64, angles: AngleMode) -> Result<f64, CalcError> {
+    // Whole multiples of 90 degrees get exact answers, so sin(180) is 0
+    // rather than 1.2e-16 and tan(90) is an error rather than 1.6e16
+    if angles == AngleMode::Degrees && angle % 90.0 == 0.0 {
+        let quarter = (angle / 90.0).rem_euclid(4.0) as u8;
+        let (sin, cos) = [(0.0, 1.0), (1.0, 0.0), (0.0, -1.0), (-1.0, 0.0)][quarter as usize];
+        return match name {
+            "sin" => Ok(sin),
+            "cos" => Ok(cos),
+            _ if cos == 0.0 => Err(domain(&format!("tan is undefined at {} degrees", angle))),
+            _ => Ok(sin / cos),
+        };
+    }
+
+    let radians = match angles {
+        AngleMode::Radians => angle,
+        AngleMode::Degrees => angle.to_radians(),
+    };
+    Ok(match name {
+        "sin" => radians.sin(),
+        "cos" => radians.cos(),
+        _ => radians.tan(),
+    })
+}
+
+fn from_radians(radians: f64, angles: AngleMode) -> f64 {
+    match angles {
+        AngleMode::Radians => radians,
+        AngleMode::Degrees => radians.to_degrees(),
+    }
+}
+
+fn domain(message: &str) -> CalcError {
+    CalcError::Domain(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call_rad(name: &str, args: &[f64]) -> Result<f64, CalcError> {
+        call(name, args, AngleMode::Radians)
+    }
+
+    #[test]
+    fn test_functions() {
+        assert_eq!(call_rad("sqrt", &[16.0]), Ok(4.0));
+        assert_eq!(call_rad("abs", &[-2.5]), Ok(2.5));
+        assert_eq!(call_rad("log10", &[1000.0]), Ok(3.0));
+        assert_eq!(call_rad("ln", &[consts::E]), Ok(1.0));
+        assert_eq!(call_rad("exp", &[0.0]), Ok(1.0));
+        assert_eq!(call_rad("floor", &[-1.5]), Ok(-2.0));
+        assert_eq!(call_rad("ceil", &[1.2]), Ok(2.0));
+        assert_eq!(call_rad("round", &[2.5]), Ok(3.0));
+        assert_eq!(call_rad("min", &[3.0, -1.0, 2.0]), Ok(-1.0));
+        assert_eq!(call_rad("max", &[3.0]), Ok(3.0));
+    }
+
+    #[test]
+    fn test_angle_modes() {
+        let deg = |name: &str, x: f64| call(name, &[x], AngleMode::Degrees);
+
+        assert_eq!(deg("sin", 180.0), Ok(0.0));
+        assert_eq!(deg("cos", -90.0), Ok(0.0));
+        assert_eq!(deg("sin", 450.0), Ok(1.0));
+        assert_eq!(deg("tan", 45.0).map(|r| (r * 1e12).round()), Ok(1e12));
+        assert_eq!(deg("asin", 1.0), Ok(90.0));
+        assert_eq!(call_rad("asin", &[1.0]), Ok(consts::FRAC_PI_2));
+        assert_eq!(call_rad("cos", &[consts::PI]), Ok(-1.0));
+    }
+
+    #[test]
+    fn test_domain_errors() {
+        let is_domain = |result: Result<f64, CalcError>| matches!(result, Err(CalcError::Domain(_)));
+
+        assert!(is_domain(call_rad("sqrt", &[-1.0])));
+        assert!(is_domain(call_rad("ln", &[0.0])));
+        assert!(is_domain(call_rad("log10", &[-10.0])));
+        assert!(is_domain(call_rad("acos", &[1.5])));
+        assert!(is_domain(call("tan", &[270.0], AngleMode::Degrees)));
+        assert_eq!(call_rad("exp", &[1000.0]), Err(CalcError::Overflow));
+    }
+
+    #[test]
+    fn test_arity() {
+        assert_eq!(arity("sqrt"), Some(Arity::Exactly(1)));
+        assert!(arity("max").unwrap().accepts(3));
+        assert!(!arity("max").unwrap().accepts(0));
+        assert_eq!(arity("nope"), None);
+        assert_eq!(Arity::AtLeast(2).describe(), "at least 2 arguments");
+    }
+}
@@ -0,0 +1,313 @@
+// Lexer - turns "2 * (3 + 4)" into a list of tokens
+//
+// Every token remembers the column (1-based) where it starts, so the
+// parser can point at the exact spot when something is wrong.
+
+use crate::error::CalcError;
+use crate::parser::ParseError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    /// The literal as written, so exact modes see every digit
+    Number(String),
+    Identifier(String),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    DoubleSlash,
+    Percent,
+    Caret,
+    Ampersand,
+    Pipe,
+    Tilde,
+    ShiftLeft,
+    ShiftRight,
+    LeftParen,
+    RightParen,
+    Comma,
+    Equals,
+}
+
+impl TokenKind {
+    /// How the token looks in an error message
+    pub fn describe(&self) -> String {
+        match self {
+            TokenKind::Number(text) => format!("number {}", text),
+            TokenKind::Identifier(name) => format!("name '{}'", name),
+            TokenKind::Plus => "'+'".to_string(),
+            TokenKind::Minus => "'-'".to_string(),
+            TokenKind::Star => "'*'".to_string(),
+            TokenKind::Slash => "'/'".to_string(),
+            TokenKind::DoubleSlash => "'//'".to_string(),
+            TokenKind::Percent => "'%'".to_string(),
+            TokenKind::Caret => "'^'".to_string(),
+            TokenKind::Ampersand => "'&'".to_string(),
+            TokenKind::Pipe => "'|'".to_string(),
+            TokenKind::Tilde => "'~'".to_string(),
+            TokenKind::ShiftLeft => "'<<'".to_string(),
+            TokenKind::ShiftRight => "'>>'".to_string(),
+            TokenKind::LeftParen => "'('".to_string(),
+            TokenKind::RightParen => "')'".to_string(),
+            TokenKind::Comma => "','".to_string(),
+            TokenKind::Equals => "'='".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub column: usize,
+}
+
+pub fn tokenize(input: &str) -> Result<Vec<Token>, CalcError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if c.is_ascii_digit() || c == '.' {
+            // Take everything that could belong to the number, so "12abc" is
+            // reported as one bad number rather than 12 followed by a name
+            let start = i;
+            // In 0x1e-5 the e is a hex digit, not an exponent
+            let hex = c == '0' && matches!(chars.get(i + 1), Some('x' | 'X'));
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '.' || chars[i] == '_') {
+                // The sign of an exponent belongs to the number: 1e-5
+                let exponent_sign = !hex
+                    && matches!(chars.get(i + 1), Some('+' | '-'))
+                    && chars.get(i + 2).is_some_and(|c| c.is_ascii_digit());
+                i += if matches!(chars[i], 'e' | 'E') && exponent_sign { 2 } else { 1 };
+            }
+
+            let text: String = chars[start..i].iter().collect();
+            if !is_number(&text) {
+                // Point at the first character that stops it being a number,
+                // e.g. the second decimal point in 1.2.3
+                let valid = (1..text.len()).rev().find(|&n| is_number(&text[..n])).unwrap_or(0);
+                return Err(CalcError::InvalidNumber(text, Some(column + valid)));
+            }
+            tokens.push(Token {
+                kind: TokenKind::Number(text),
+                column,
+            });
+            continue;
+        }
+
+        if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token {
+                kind: TokenKind::Identifier(chars[start..i].iter().collect()),
+                column,
+            });
+            continue;
+        }
+
+        let double = match (c, chars.get(i + 1)) {
+            ('/', Some('/')) => Some(TokenKind::DoubleSlash),
+            ('<', Some('<')) => Some(TokenKind::ShiftLeft),
+            ('>', Some('>')) => Some(TokenKind::ShiftRight),
+            _ => None,
+        };
+        if let Some(kind) = double {
+            tokens.push(Token { kind, column });
+            i += 2;
+            continue;
+        }
+
+        let kind = match c {
+            '+' => TokenKind::Plus,
+            '-' => TokenKind::Minus,
+            '*' => TokenKind::Star,
+            '/' => TokenKind::Slash,
+            '%' => TokenKind::Percent,
+            '^' => TokenKind::Caret,
+            '&' => TokenKind::Ampersand,
+            '|' => TokenKind::Pipe,
+            '~' => TokenKind::Tilde,
+            '(' => TokenKind::LeftParen,
+            ')' => TokenKind::RightParen,
+            ',' => TokenKind::Comma,
+            '=' => TokenKind::Equals,
+            _ if c.is_ascii_punctuation() => return Err(CalcError::UnknownOperator(c.to_string(), Some(column))),
+            _ => return Err(ParseError::new(format!("unexpected character '{}'", c), column).into()),
+        };
+        tokens.push(Token { kind, column });
+        i += 1;
+    }
+
+    Ok(tokens)
+}
+
+/// Splits an integer literal in another base into the base and its digits:
+/// "0xff" is (16, "ff"), "0o17" is (8, "17"), "0b1010" is (2, "1010")
+pub fn radix_literal(text: &str) -> Option<(u32, &str)> {
+    let radix = match text.get(..2)? {
+        "0x" | "0X" => 16,
+        "0o" | "0O" => 8,
+        "0b" | "0B" => 2,
+        _ => return None,
+    };
+    Some((radix, &text[2..]))
+}
+
+// Digits with at most one decimal point and an optional exponent:
+// "3", "0.5", ".5", "5.", "1.5e3", "2E-4". Integers may also be written
+// in hex, octal or binary with _ between digits: 0xff, 0o17, 0b1010_0101
+fn is_number(text: &str) -> bool {
+    if let Some((radix, digits)) = radix_literal(text) {
+        return digits.starts_with(|c: char| c.is_digit(radix))
+            && digits.chars().all(|c| c.is_digit(radix) || c == '_');
+    }
+
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+        Some(at) => (&text[..at], Some(&text[at + 1..])),
+        None => (text, None),
+    };
+
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    let mantissa_ok = digits(whole) && digits(fraction) && whole.len() + fraction.len() > 0;
+
+    let exponent_ok = exponent.is_none_or(|e| {
+        let e = e.strip_prefix(['+', '-']).unwrap_or(e);
+        !e.is_empty() && digits(e)
+    });
+
+    mantissa_ok && exponent_ok
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(input: &str) -> Vec<TokenKind> {
+        tokenize(input).unwrap().into_iter().map(|t| t.kind).collect()
+    }
+
+    fn number(text: &str) -> TokenKind {
+        TokenKind::Number(text.to_string())
+    }
+
+    #[test]
+    fn test_tokenize_expression() {
+        assert_eq!(
+            kinds("2 * (3.5 + .5e-1)"),
+            vec![
+                number("2"),
+                TokenKind::Star,
+                TokenKind::LeftParen,
+                number("3.5"),
+                TokenKind::Plus,
+                number(".5e-1"),
+                TokenKind::RightParen,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_token_columns() {
+        let columns: Vec<usize> = tokenize("12 +  3").unwrap().iter().map(|t| t.column).collect();
+        assert_eq!(columns, vec![1, 4, 7]);
+    }
+
+    #[test]
+    fn test_tokenize_operators_and_calls() {
+        assert_eq!(
+            kinds("7 // 2 % 3 ^ max(1, 2)"),
+            vec![
+                number("7"),
+                TokenKind::DoubleSlash,
+                number("2"),
+                TokenKind::Percent,
+                number("3"),
+                TokenKind::Caret,
+                TokenKind::Identifier("max".to_string()),
+                TokenKind::LeftParen,
+                number("1"),
+                TokenKind::Comma,
+                number("2"),
+                TokenKind::RightParen,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_programmer_literals_and_operators() {
+        assert_eq!(
+            kinds("~0xff & 0b1010 | 0o17 << 2 >> 1 xor 0x1e-5"),
+            vec![
+                TokenKind::Tilde,
+                number("0xff"),
+                TokenKind::Ampersand,
+                number("0b1010"),
+                TokenKind::Pipe,
+                number("0o17"),
+                TokenKind::ShiftLeft,
+                number("2"),
+                TokenKind::ShiftRight,
+                number("1"),
+                TokenKind::Identifier("xor".to_string()),
+                number("0x1e"),
+                TokenKind::Minus,
+                number("5"),
+            ]
+        );
+        assert_eq!(kinds("0xffff_ffff"), vec![number("0xffff_ffff")]);
+        assert_eq!(radix_literal("0B11"), Some((2, "11")));
+        assert_eq!(radix_literal("011"), None);
+    }
+
+    #[test]
+    fn test_tokenize_assignment() {
+        assert_eq!(
+            kinds("rate_2 = ans"),
+            vec![
+                TokenKind::Identifier("rate_2".to_string()),
+                TokenKind::Equals,
+                TokenKind::Identifier("ans".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_errors() {
+        let invalid = |text: &str, column| Err(CalcError::InvalidNumber(text.to_string(), Some(column)));
+        // A second decimal point is reported at its own column
+        assert_eq!(tokenize("1.2.3 + 4"), invalid("1.2.3", 4));
+        assert_eq!(tokenize("1 + ."), invalid(".", 5));
+        assert_eq!(tokenize("12abc"), invalid("12abc", 3));
+        assert_eq!(tokenize("2e"), invalid("2e", 2));
+        assert_eq!(tokenize("1e5e5"), invalid("1e5e5", 4));
+        assert_eq!(tokenize("0x"), invalid("0x", 2));
+        assert_eq!(tokenize("0b102"), invalid("0b102", 5));
+        assert_eq!(tokenize("0x_1"), invalid("0x_1", 2));
+
+        // "1e" followed by a minus and a name is not an exponent
+        assert_eq!(tokenize("1e-x"), invalid("1e", 2));
+
+        assert_eq!(tokenize("5 @ 3"), Err(CalcError::UnknownOperator("@".to_string(), Some(3))));
+        assert_eq!(tokenize("2 + $x"), Err(CalcError::UnknownOperator("$".to_string(), Some(5))));
+        assert_eq!(tokenize("5 < 3"), Err(CalcError::UnknownOperator("<".to_string(), Some(3))));
+
+        match tokenize("2 × 3") {
+            Err(CalcError::Syntax(e)) => {
+                assert_eq!(e.column, 3);
+                assert!(e.message.contains("'×'"));
+            }
+            other => panic!("expected a syntax error, got {:?}", other),
+        }
+    }
+}
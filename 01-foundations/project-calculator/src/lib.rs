@@ -0,0 +1,64 @@
+// Calculator library - everything except the command line itself
+//
+// Parse a line into an `Ast`, evaluate it against an `Env`, and print it
+// back with `to_string()`:
+//
+//   let mut env = calculator::Env::default();
+//   let ast = calculator::parse("x = 512 MiB / 2 s")?;
+//   let value = calculator::evaluate(&ast, &mut env)?;
+//   assert_eq!(env.show(&value), "256 MiB/s");
+//   assert_eq!(ast.to_string(), "x = 512 MiB / 2 s");
+//
+// The input is split into tokens (lexer.rs) and parsed into an expression
+// tree (parser.rs), which eval.rs evaluates. value.rs checks and converts
+// units (units.rs), number.rs does the arithmetic in float, rational,
+// decimal or integer mode, falling back to the f64 operations in
+// arithmetic.rs and the function library in functions.rs. printer.rs
+// turns a tree back into text, and batch.rs evaluates a whole file.
+
+pub mod arithmetic;
+pub mod batch;
+pub mod error;
+pub mod eval;
+pub mod functions;
+pub mod lexer;
+pub mod number;
+pub mod parser;
+pub mod printer;
+pub mod units;
+pub mod value;
+
+pub use error::CalcError;
+pub use eval::{evaluate, Env};
+pub use functions::AngleMode;
+pub use number::{Number, NumberMode, Word};
+pub use parser::{BinaryOp, Expr, Statement};
+pub use units::Unit;
+pub use value::Value;
+
+/// A parsed line: an expression, or an assignment `name = expression`
+pub type Ast = Statement;
+
+/// Parses one line of input, which may assign a variable
+pub fn parse(input: &str) -> Result<Ast, CalcError> {
+    parser::parse_statement(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_evaluate_and_print() -> Result<(), CalcError> {
+        let mut env = Env::default();
+        let ast = parse("x = 512 MiB / 2 s")?;
+        let value = evaluate(&ast, &mut env)?;
+        assert_eq!(env.show(&value), "256 MiB/s");
+        assert_eq!(ast.to_string(), "x = 512 MiB / 2 s");
+
+        let value = evaluate(&parse("x * 1 min in GiB")?, &mut env)?;
+        assert_eq!(env.show(&value), "15 GiB");
+        assert_eq!(env.variables.get("ans"), Some(&value));
+        Ok(())
+    }
+}
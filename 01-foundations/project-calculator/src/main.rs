@@ -1,153 +1,237 @@
-// Baby Calculator - Fix the Broken Code!
+// Baby Calculator
 //
-// Your mission: Make this calculator work by fixing the compilation errors
-// and implementing the missing functionality.
+// Evaluates infix expressions with the usual precedence rules:
 //
-// INSTRUCTIONS:
-// 1. Run `cargo build` to see compilation errors
-// 2. Fix ONE error at a time
-// 3. Run `cargo test` to verify your implementations
-// 4. Test with: `cargo run 5 + 3`
-
-use std::env;
-
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    
-    // Check if we have the right number of arguments
-    if args.len() != 4 {
-        println!("Usage: calculator <number> <operation> <number>");
-        println!("Example: calculator 5 + 3");
-        return;
-    }
-    
-    // FIXME: Parse the arguments - these don't compile yet!
-    let left = parse_number(&args[1]);
-    let operator = &args[2];
-    let right = parse_number(&args[3]);
-    
-    // FIXME: Call the calculate function 
-    let result = calculate(left, operator, right);
-    
-    // Print the result nicely
-    println!("{} {} {} = {}", left, operator, right, result);
-}
+//   cargo run 5 + 3
+//   cargo run "2 * (3 + 4) / 7"
+//   cargo run -- "-(1.5 + 2) * 4"
+//   cargo run "2 ^ 10 % 7 + sqrt(2) * pi"
+//   cargo run -- --degrees "sin(30)"
+//   cargo run -- --rational "1/3 + 1/6"
+//   cargo run -- --decimal=30 "1 / 7"
+//   cargo run "512 MiB / 2 s"
+//   cargo run "3 h in min"
+//   cargo run -- --uint=8 --base=16 "~0x0f & 0b1100_0000 | 1 << 2"
+//   printf 'x = 2\nx ^ 10\n' | cargo run -- --batch --format=csv
+//   cargo run -- --file=sums.txt --fail-fast
+//
+// This file only reads the command line; the calculator itself is the
+// library in lib.rs, which other programs can use too.
+//
+// Without arguments the calculator starts an interactive session with
+// variables and history (repl.rs). --batch and --file evaluate one
+// expression per line instead (batch.rs).
+//
+// Failures exit with a code that says what went wrong (see error.rs); in
+// batch mode the first failed line decides the code:
+//   2 syntax error        5 unknown variable   8 domain error
+//   3 invalid number      6 division by zero   9 incompatible units
+//   4 unknown operator    7 number too large
 
-// FIXME: This function doesn't exist - implement it!
-// HINT: Use .parse() method on strings to convert to f64
-// TODO: What should this function return if parsing fails?
-fn parse_number(s: &str) -> f64 {
-    // TODO: Parse the string to f64 or panic with a helpful message
-    // HINT: s.parse().expect("Failed to parse number")
-    todo!("Parse string to number")
-}
+mod repl;
 
-// FIXME: This function doesn't compile - fix the signature and implementation!
-fn calculate(left: f64, operator: &str, right: f64) -> f64 {
-    // TODO: Use a match statement to handle different operators
-    // HINT: match operator { "+" => ..., "-" => ..., "*" => ..., "/" => ... }
-    
-    match operator {
-        // TODO: Implement each operation
-        "+" => todo!("Implement addition"),
-        "-" => todo!("Implement subtraction"), 
-        "*" => todo!("Implement multiplication"),
-        "/" => todo!("Implement division"),
-        _ => panic!("Unknown operator: {}", operator),
-    }
-}
+use calculator::number::{self, NumberMode, Word};
+use calculator::{batch, evaluate, parser, AngleMode, Ast, Env};
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::process;
 
-// BONUS: Implement these helper functions (optional)
-// Remove todo!() and add real implementations
+const USAGE: &str = "\
+Usage: calculator [OPTIONS] [EXPRESSION]...
 
-fn add(a: f64, b: f64) -> f64 {
-    todo!("Implement addition")
-}
+Evaluates the expression, or starts an interactive session without one.
 
-fn subtract(a: f64, b: f64) -> f64 {
-    todo!("Implement subtraction")
-}
+Options:
+  --degrees, --radians     Angle unit for trigonometry (default radians)
+  --float                  Floating-point numbers (default)
+  --rational               Exact fractions
+  --decimal[=N]            Decimals rounded to N places (default 20)
+  --int[=BITS], --uint[=BITS]
+                           Whole numbers in a word of 8, 16, 32, 64 or 128 bits
+  --base=N                 Show results in base N, from 2 to 36
+  --batch                  Evaluate one expression per line from stdin
+  --file=PATH              Evaluate one expression per line from a file
+  --format=FORMAT          Batch output: plain, csv or json
+  --fail-fast              Stop batch mode at the first failed line
+  --help                   Show this message
+  --                       End of options; the rest is the expression";
 
-fn multiply(a: f64, b: f64) -> f64 {
-    todo!("Implement multiplication")
+/// Where batch mode reads its expressions from
+enum BatchInput {
+    Stdin,
+    File(String),
 }
 
-fn divide(a: f64, b: f64) -> f64 {
-    // TODO: What should happen if b is 0?
-    todo!("Implement division")
-}
+fn main() {
+    let mut env = Env::default();
+    let mut batch_input = None;
+    let mut options = batch::Options::default();
+    let mut batch_only_flag = None;
+    let mut args = Vec::new();
+    let mut argv = std::env::args().skip(1);
+    for arg in argv.by_ref() {
+        match arg.as_str() {
+            "--batch" => batch_input = Some(BatchInput::Stdin),
+            _ if arg.starts_with("--file=") => {
+                batch_input = Some(BatchInput::File(arg["--file=".len()..].to_string()));
+            }
+            "--fail-fast" => {
+                options.fail_fast = true;
+                batch_only_flag = Some(arg);
+            }
+            _ if arg.starts_with("--format=") => match batch::Format::from_name(&arg["--format=".len()..]) {
+                Some(format) => {
+                    options.format = format;
+                    batch_only_flag = Some(arg);
+                }
+                None => {
+                    eprintln!("Error: --format takes plain, csv or json");
+                    process::exit(1);
+                }
+            },
+            "--degrees" => env.angles = AngleMode::Degrees,
+            "--radians" => env.angles = AngleMode::Radians,
+            "--float" => env.mode = NumberMode::Float,
+            "--rational" => env.mode = NumberMode::Rational,
+            "--decimal" => env.mode = NumberMode::Decimal(number::DEFAULT_DECIMAL_PLACES),
+            _ if arg.starts_with("--decimal=") => match number::decimal_places(&arg["--decimal=".len()..]) {
+                Some(places) => env.mode = NumberMode::Decimal(places),
+                None => {
+                    eprintln!(
+                        "Error: --decimal takes a number of places from 0 to {}",
+                        number::MAX_DECIMAL_PLACES
+                    );
+                    process::exit(1);
+                }
+            },
+            "--int" | "--uint" => {
+                env.mode = NumberMode::Integer(Word {
+                    bits: number::DEFAULT_WORD_BITS,
+                    signed: arg == "--int",
+                });
+            }
+            _ if arg.starts_with("--int=") || arg.starts_with("--uint=") => {
+                let (flag, bits) = arg.split_once('=').unwrap_or_default();
+                match number::word_bits(bits) {
+                    Some(bits) => {
+                        env.mode = NumberMode::Integer(Word {
+                            bits,
+                            signed: flag == "--int",
+                        });
+                    }
+                    None => {
+                        eprintln!("Error: {} takes a word size of 8, 16, 32, 64 or 128 bits", flag);
+                        process::exit(1);
+                    }
+                }
+            }
+            _ if arg.starts_with("--base=") => match number::output_base(&arg["--base=".len()..]) {
+                Some(base) => env.base = base,
+                None => {
+                    eprintln!("Error: --base takes a base from 2 to 36");
+                    process::exit(1);
+                }
+            },
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return;
+            }
+            // Lets an expression start with --, e.g. `calculator -- --5`
+            "--" => break,
+            // Anything else starting with -- is a mistyped flag, not an expression
+            _ if arg.starts_with("--") => {
+                eprintln!("Error: unknown option {}\n\n{}", arg, USAGE);
+                process::exit(1);
+            }
+            _ => args.push(arg),
+        }
+    }
+    args.extend(argv);
 
-// Tests to verify your implementation
-#[cfg(test)]
-mod tests {
-    use super::*;
-    
-    #[test]
-    fn test_addition() {
-        let result = calculate(5.0, "+", 3.0);
-        assert_eq!(result, 8.0);
+    if let Some(input) = batch_input {
+        if !args.is_empty() {
+            eprintln!("Error: batch mode reads expressions from stdin or --file, not the command line");
+            process::exit(1);
+        }
+        process::exit(run_batch(input, env, options));
     }
-    
-    #[test]
-    fn test_subtraction() {
-        let result = calculate(10.0, "-", 4.0);
-        assert_eq!(result, 6.0);
+    if let Some(flag) = batch_only_flag {
+        eprintln!("Error: {} only applies with --batch or --file", flag);
+        process::exit(1);
     }
-    
-    #[test]
-    fn test_multiplication() {
-        let result = calculate(6.0, "*", 7.0);
-        assert_eq!(result, 42.0);
+
+    if args.is_empty() {
+        if let Err(e) = repl::run(env) {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+        return;
     }
-    
-    #[test]
-    fn test_division() {
-        let result = calculate(15.0, "/", 3.0);
-        assert_eq!(result, 5.0);
+
+    // `calculator 5 + 3` and `calculator "5 + 3"` mean the same thing
+    let input = args.join(" ");
+
+    // A single expression; assignments only make sense in a session
+    let result = parser::parse(&input).and_then(|expr| evaluate(&Ast::Expr(expr), &mut env));
+    match result {
+        Ok(result) => println!("{} = {}", input, env.show(&result)),
+        Err(e) => {
+            eprintln!("{}", e.render(&input));
+            process::exit(e.exit_code());
+        }
     }
-    
-    #[test]
-    fn test_parse_number() {
-        assert_eq!(parse_number("42"), 42.0);
-        assert_eq!(parse_number("3.14"), 3.14);
+}
+
+// Returns the exit code: 0, 1 if the input could not be read, or the code
+// of the first line that failed
+fn run_batch(input: BatchInput, env: Env, options: batch::Options) -> i32 {
+    let mut stdout = io::stdout();
+    let mut stderr = io::stderr();
+    let result = match input {
+        BatchInput::Stdin => batch::run(io::stdin().lock(), &mut stdout, &mut stderr, env, options),
+        BatchInput::File(path) => match File::open(&path) {
+            Ok(file) => batch::run(BufReader::new(file), &mut stdout, &mut stderr, env, options),
+            Err(e) => {
+                eprintln!("Error: cannot read {}: {}", path, e);
+                return 1;
+            }
+        },
+    };
+
+    match result {
+        Ok(None) => 0,
+        Ok(Some(e)) => e.exit_code(),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            1
+        }
     }
-    
-    // Uncomment when you implement the helper functions
-    // #[test]
-    // fn test_helper_functions() {
-    //     assert_eq!(add(2.0, 3.0), 5.0);
-    //     assert_eq!(subtract(10.0, 4.0), 6.0);
-    //     assert_eq!(multiply(3.0, 4.0), 12.0);
-    //     assert_eq!(divide(8.0, 2.0), 4.0);
-    // }
 }
 
 // LEARNING OBJECTIVES:
 // 1. Function definition syntax: fn name(params) -> return_type
 // 2. Pattern matching with match statements
-// 3. String parsing with .parse() method
-// 4. Basic error handling with .expect()
-// 5. Command line argument parsing
-// 6. Writing and running tests
+// 3. Enums that hold data (TokenKind, Expr) and recursion over them
+//    (Expr::Call holds a Vec of argument expressions)
+// 4. Modules and crates: a library (lib.rs and its modules) that other
+//    code can use, and this thin binary on top of it
+// 5. Command line argument parsing, an interactive read-eval-print loop and
+//    batch processing over any BufRead (stdin or a file)
+// 6. Errors as values: Result<Value, CalcError> instead of panics
+// 7. Writing and running tests
 //
 // C# COMPARISON:
 // C#: public double Add(double a, double b) { return a + b; }
 // Rust: fn add(a: f64, b: f64) -> f64 { a + b }
 //
-// C#: double.Parse(str)
-// Rust: str.parse::<f64>().expect("message")
+// C#: double.TryParse(str, out var n) ? n : throw new FormatException()
+// Rust: str.parse::<f64>().map_err(|_| CalcError::InvalidNumber(..))?
 //
 // C#: switch (op) { case "+": return a + b; }
 // Rust: match op { "+" => a + b, }
 //
-// COMPILATION ERRORS TO EXPECT AND FIX:
-// 1. "cannot find function `parse_number`" - implement the function
-// 2. "cannot find function `calculate`" - implement the function  
-// 3. "`todo!` not yet implemented" - replace with actual code
-// 4. Pattern match errors - complete the match arms
+// C#: Environment.Exit(code)
+// Rust: process::exit(code)
 //
-// SUCCESS CRITERIA:
-// ✅ `cargo build` compiles without errors
-// ✅ `cargo test` passes all tests
-// ✅ `cargo run 5 + 3` outputs "5 + 3 = 8"
-// ✅ All four operations work correctly
+// C#: abstract record Expr; record Number(string Literal) : Expr;
+// Rust: enum Expr { Number(String), ... }  // read in the current number mode
@@ -0,0 +1,601 @@
+// Numbers - the values the calculator computes with
+//
+// The default float mode works on f64 through `calculate` in arithmetic.rs, so
+// 0.1 + 0.2 is 0.30000000000000004. The exact modes keep every value as a
+// fraction of two unbounded integers instead:
+//
+//   --rational      exact fractions: 1/3 + 1/6 = 1/2, 2 ^ 100 in full
+//   --decimal[=N]   fractions rounded to N decimal places (default 20)
+//                   and printed as decimals: 0.1 + 0.2 = 0.3
+//
+// Integers are fractions with a denominator of 1, so they never lose
+// digits in either exact mode. Operations without an exact answer
+// (sqrt(2), sin(1), 2 ^ 0.5) fall back to f64: rational mode keeps those
+// results as floats, decimal mode rounds them to its precision.
+//
+//   --int[=BITS]    programmer mode: whole numbers in a 64-bit (or 8, 16,
+//   --uint[=BITS]   32, 128-bit) signed or unsigned word. Every result is
+//                   cut toward zero and wraps around like a machine
+//                   integer, so in --uint=8 255 + 1 is 0 and ~0 is 255
+//
+// Integer literals may be written in hex, octal or binary (0xff, 0o17,
+// 0b1010) in any mode, and the bitwise operators work on any whole number.
+// Outside a word, they act as if numbers had infinitely many bits in two's
+// complement, so ~0 is -1.
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use crate::arithmetic::{calculate, parse_number};
+use crate::error::CalcError;
+use crate::functions::{self, AngleMode};
+use crate::lexer::radix_literal;
+use crate::parser::BinaryOp;
+
+pub const DEFAULT_DECIMAL_PLACES: u32 = 20;
+pub const MAX_DECIMAL_PLACES: u32 = 1000;
+
+// Exact results may use this many bits (about 315,000 digits), so a typo
+// like 9 ^ 99999999 fails quickly instead of eating all memory
+const MAX_BITS: u64 = 1 << 20;
+
+pub const DEFAULT_WORD_BITS: u32 = 64;
+
+/// Which arithmetic the calculator uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumberMode {
+    #[default]
+    Float,
+    Rational,
+    /// Exact, rounded to this many decimal places
+    Decimal(u32),
+    /// Whole numbers that wrap around within a machine word
+    Integer(Word),
+}
+
+/// The size and signedness of the integers in programmer mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Word {
+    pub bits: u32,
+    pub signed: bool,
+}
+
+impl Word {
+    // 2 ^ bits, the number of values the word can hold
+    fn modulus(&self) -> BigInt {
+        BigInt::one() << self.bits
+    }
+
+    /// Keeps the low `bits` bits, read as signed or unsigned
+    fn wrap(&self, x: BigInt) -> BigInt {
+        let modulus = self.modulus();
+        let mut wrapped = ((x % &modulus) + &modulus) % &modulus;
+        if self.signed && wrapped.bit(u64::from(self.bits) - 1) {
+            wrapped -= modulus;
+        }
+        wrapped
+    }
+}
+
+impl NumberMode {
+    pub fn name(&self) -> String {
+        match self {
+            NumberMode::Float => "float".to_string(),
+            NumberMode::Rational => "rational".to_string(),
+            NumberMode::Decimal(places) => format!("decimal, {} places", places),
+            NumberMode::Integer(word) => format!(
+                "integer, {}-bit {}",
+                word.bits,
+                if word.signed { "signed" } else { "unsigned" }
+            ),
+        }
+    }
+
+    fn round(&self, x: BigRational) -> BigRational {
+        match self {
+            NumberMode::Decimal(places) => round_to_places(&x, *places),
+            NumberMode::Integer(word) => BigRational::from_integer(word.wrap(x.trunc().to_integer())),
+            _ => x,
+        }
+    }
+
+    /// A result that only f64 could compute
+    pub fn inexact(&self, f: f64) -> Number {
+        match self {
+            NumberMode::Decimal(_) | NumberMode::Integer(_) => Number::Exact(self.round(from_f64(f))),
+            _ => Number::Float(f),
+        }
+    }
+
+    // The value to do exact arithmetic on, or None if this must be done in f64
+    fn exact(&self, n: &Number) -> Option<BigRational> {
+        match (self, n) {
+            (NumberMode::Float, _) => None,
+            (_, Number::Exact(x)) => Some(x.clone()),
+            (NumberMode::Decimal(_) | NumberMode::Integer(_), Number::Float(f)) => Some(from_f64(*f)),
+            (NumberMode::Rational, Number::Float(_)) => None,
+        }
+    }
+
+    fn word(&self) -> Option<Word> {
+        match self {
+            NumberMode::Integer(word) => Some(*word),
+            _ => None,
+        }
+    }
+}
+
+/// Reads the N of `--decimal=N` or `:decimal N`
+pub fn decimal_places(text: &str) -> Option<u32> {
+    text.trim().parse().ok().filter(|places| *places <= MAX_DECIMAL_PLACES)
+}
+
+/// Reads the word size of `--int=N` or `:int N`
+pub fn word_bits(text: &str) -> Option<u32> {
+    text.trim().parse().ok().filter(|bits| [8, 16, 32, 64, 128].contains(bits))
+}
+
+/// Reads the N of `--base=N` or `:base N`
+pub fn output_base(text: &str) -> Option<u32> {
+    text.trim().parse().ok().filter(|base| (2..=36).contains(base))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Number {
+    Float(f64),
+    /// Exact value; integers have a denominator of 1
+    Exact(BigRational),
+}
+
+impl Number {
+    /// Reads a literal the lexer has already checked, e.g. "12", ".5", "1.5e3", "0xff"
+    pub fn parse(text: &str, mode: NumberMode) -> Result<Number, CalcError> {
+        if let Some((radix, digits)) = radix_literal(text) {
+            let n = BigInt::parse_bytes(digits.replace('_', "").as_bytes(), radix)
+                .ok_or_else(|| CalcError::InvalidNumber(text.to_string(), None))?;
+            return integer(n, mode);
+        }
+        match mode {
+            NumberMode::Float => parse_number(text).map(Number::Float),
+            _ => Ok(Number::Exact(mode.round(parse_exact(text)?))),
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Number::Float(f) => *f,
+            Number::Exact(x) => x.to_f64().unwrap_or(f64::NAN),
+        }
+    }
+
+    pub fn negate(self) -> Number {
+        match self {
+            Number::Float(f) => Number::Float(-f),
+            Number::Exact(x) => Number::Exact(-x),
+        }
+    }
+
+    /// How the number is shown: floats as usual, exact values as a whole
+    /// number, a fraction such as 1/3, or a decimal in decimal mode
+    pub fn format(&self, mode: NumberMode) -> String {
+        match (self, mode) {
+            (Number::Float(f), _) => f.to_string(),
+            (Number::Exact(x), NumberMode::Decimal(places)) => format_decimal(x, places),
+            (Number::Exact(x), _) if x.is_integer() => x.numer().to_string(),
+            (Number::Exact(x), _) => format!("{}/{}", x.numer(), x.denom()),
+        }
+    }
+
+    /// Like `format`, but whole numbers are shown in `base`: 0xff, 0o17,
+    /// 0b1010, or just the digits for other bases. In a word, negative
+    /// numbers show their two's complement bits, so -1 in 8 bits is 0xff.
+    pub fn format_in_base(&self, mode: NumberMode, base: u32) -> String {
+        let whole = match self {
+            Number::Exact(x) if x.is_integer() => x.to_integer(),
+            Number::Float(f) if f.fract() == 0.0 => from_f64(*f).to_integer(),
+            _ => return self.format(mode),
+        };
+        if base == 10 {
+            return self.format(mode);
+        }
+
+        let whole = match mode.word() {
+            Some(word) if whole.is_negative() => whole + word.modulus(),
+            _ => whole,
+        };
+        let prefix = match base {
+            16 => "0x",
+            8 => "0o",
+            2 => "0b",
+            _ => "",
+        };
+        let sign = if whole.is_negative() { "-" } else { "" };
+        format!("{}{}{}", sign, prefix, whole.abs().to_str_radix(base))
+    }
+}
+
+pub fn binary(left: &Number, op: BinaryOp, right: &Number, mode: NumberMode) -> Result<Number, CalcError> {
+    if op.is_bitwise() {
+        return bitwise(left, op, right, mode);
+    }
+    let (Some(a), Some(b)) = (mode.exact(left), mode.exact(right)) else {
+        let result = calculate(left.to_f64(), op.symbol(), right.to_f64())?;
+        return Ok(mode.inexact(result));
+    };
+
+    let result = match op {
+        BinaryOp::Add => a + b,
+        BinaryOp::Subtract => a - b,
+        BinaryOp::Multiply => a * b,
+        BinaryOp::Divide | BinaryOp::IntDivide | BinaryOp::Modulo if b.is_zero() => {
+            return Err(CalcError::DivisionByZero)
+        }
+        BinaryOp::Divide => a / b,
+        BinaryOp::IntDivide => (a / b).floor(),
+        // Same sign as the divisor, like the float version
+        BinaryOp::Modulo => &a - &b * (&a / &b).floor(),
+        BinaryOp::Power if !b.is_integer() => {
+            let result = calculate(left.to_f64(), op.symbol(), right.to_f64())?;
+            return Ok(mode.inexact(result));
+        }
+        // Only the low bits of a word survive, so 3 ^ 1000001 is quick
+        BinaryOp::Power if mode.word().is_some() && !b.is_negative() => {
+            let modulus = mode.word().map(|word| word.modulus()).unwrap_or_default();
+            BigRational::from_integer(a.to_integer().modpow(b.numer(), &modulus))
+        }
+        BinaryOp::Power => power(&a, b.numer())?,
+        _ => unreachable!("{} is handled by bitwise", op.symbol()),
+    };
+
+    Ok(Number::Exact(mode.round(result)))
+}
+
+/// `~x`: every bit flipped, which is -x - 1
+pub fn not(n: &Number, mode: NumberMode) -> Result<Number, CalcError> {
+    integer(-whole(n, mode)? - 1, mode)
+}
+
+fn bitwise(left: &Number, op: BinaryOp, right: &Number, mode: NumberMode) -> Result<Number, CalcError> {
+    let (a, b) = (whole(left, mode)?, whole(right, mode)?);
+
+    let result = match op {
+        BinaryOp::BitAnd => a & b,
+        BinaryOp::BitOr => a | b,
+        BinaryOp::BitXor => a ^ b,
+        BinaryOp::ShiftLeft | BinaryOp::ShiftRight => {
+            if b.is_negative() {
+                return Err(CalcError::Domain(format!("cannot shift by a negative amount ({})", b)));
+            }
+            let amount = b.to_u64().unwrap_or(u64::MAX);
+            if op == BinaryOp::ShiftRight {
+                // Past the last bit only the sign is left: 0 or -1
+                let bits = a.bits();
+                a >> amount.min(bits + 1)
+            } else if mode.word().is_some_and(|word| amount >= u64::from(word.bits)) {
+                BigInt::zero()
+            } else if a.is_zero() {
+                a
+            } else if a.bits().saturating_add(amount) > MAX_BITS {
+                return Err(CalcError::Overflow);
+            } else {
+                a << amount
+            }
+        }
+        _ => unreachable!("{} is not a bitwise operator", op.symbol()),
+    };
+
+    integer(result, mode)
+}
+
+// The whole number a bitwise operator works on
+fn whole(n: &Number, mode: NumberMode) -> Result<BigInt, CalcError> {
+    let x = match n {
+        Number::Exact(x) => x.clone(),
+        Number::Float(f) => from_f64(*f),
+    };
+    if !x.is_integer() {
+        return Err(CalcError::Domain(format!(
+            "bitwise operators need whole numbers, found {}",
+            n.format(mode)
+        )));
+    }
+    Ok(x.to_integer())
+}
+
+// A whole number result in the current mode
+fn integer(n: BigInt, mode: NumberMode) -> Result<Number, CalcError> {
+    if mode != NumberMode::Float {
+        return Ok(Number::Exact(mode.round(BigRational::from_integer(n))));
+    }
+    match n.to_f64() {
+        Some(f) if f.is_finite() => Ok(Number::Float(f)),
+        _ => Err(CalcError::Overflow),
+    }
+}
+
+/// Calls a function from functions.rs, exactly where the answer is exact
+pub fn call(name: &str, args: &[Number], angles: AngleMode, mode: NumberMode) -> Result<Number, CalcError> {
+    let exact: Option<Vec<BigRational>> = args.iter().map(|n| mode.exact(n)).collect();
+
+    if let Some(exact) = exact {
+        let x = &exact[0];
+        let result = match name {
+            "abs" => Some(x.abs()),
+            "floor" => Some(x.floor()),
+            "ceil" => Some(x.ceil()),
+            "round" => Some(x.round()),
+            "min" => exact.iter().min().cloned(),
+            "max" => exact.iter().max().cloned(),
+            "sqrt" if !x.is_negative() => exact_sqrt(x, mode),
+            _ => None,
+        };
+        if let Some(result) = result {
+            return Ok(Number::Exact(mode.round(result)));
+        }
+    }
+
+    let floats: Vec<f64> = args.iter().map(Number::to_f64).collect();
+    let result = functions::call(name, &floats, angles)?;
+    Ok(mode.inexact(result))
+}
+
+fn power(base: &BigRational, exponent: &BigInt) -> Result<BigRational, CalcError> {
+    if exponent.is_zero() {
+        return Ok(BigRational::one());
+    }
+    if base.is_zero() && exponent.is_negative() {
+        return Err(CalcError::DivisionByZero);
+    }
+    // 0, 1 and -1 stay small however large the exponent
+    if base.is_zero() || base.abs().is_one() {
+        let odd = exponent.bit(0);
+        return Ok(if base.is_negative() && !odd { base.abs() } else { base.clone() });
+    }
+
+    let bits = base.numer().bits().max(base.denom().bits());
+    let Some(exponent) = exponent.to_i32().filter(|e| bits.saturating_mul(e.unsigned_abs() as u64) <= MAX_BITS)
+    else {
+        return Err(CalcError::Overflow);
+    };
+    Ok(num_traits::Pow::pow(base, exponent))
+}
+
+// sqrt of a perfect square is exact; in decimal mode any square root is
+// worked out to the precision with integer arithmetic
+fn exact_sqrt(x: &BigRational, mode: NumberMode) -> Option<BigRational> {
+    let (numer, denom) = (x.numer().sqrt(), x.denom().sqrt());
+    if &(&numer * &numer) == x.numer() && &(&denom * &denom) == x.denom() {
+        return Some(BigRational::new(numer, denom));
+    }
+
+    let NumberMode::Decimal(places) = mode else {
+        return None;
+    };
+    // One extra digit so the final rounding is correct
+    let scale = pow10(places + 1);
+    let scaled = (x * BigRational::from_integer(&scale * &scale)).floor().to_integer();
+    Some(BigRational::new(scaled.sqrt(), scale))
+}
+
+fn parse_exact(text: &str) -> Result<BigRational, CalcError> {
+    let invalid = || CalcError::InvalidNumber(text.to_string(), None);
+
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+        Some(at) => (&text[..at], text[at + 1..].parse::<i64>().map_err(|_| invalid())?),
+        None => (text, 0),
+    };
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits: BigInt = format!("{}{}", whole, fraction).parse().map_err(|_| invalid())?;
+
+    let shift = exponent - fraction.len() as i64;
+    // Each decimal digit takes about 3.3 bits
+    if shift.unsigned_abs().saturating_mul(4) > MAX_BITS {
+        return Err(CalcError::Overflow);
+    }
+    let factor = pow10(shift.unsigned_abs() as u32);
+    Ok(if shift >= 0 {
+        BigRational::from_integer(digits * factor)
+    } else {
+        BigRational::new(digits, factor)
+    })
+}
+
+// The shortest decimal that reads back as `f`, so 0.1 becomes exactly 1/10
+// rather than 0.1000000000000000055511151231257827...
+fn from_f64(f: f64) -> BigRational {
+    parse_exact(&format!("{:e}", f.abs()))
+        .map(|x| if f < 0.0 { -x } else { x })
+        .unwrap_or_else(|_| BigRational::zero())
+}
+
+fn pow10(exponent: u32) -> BigInt {
+    num_traits::Pow::pow(BigInt::from(10), exponent)
+}
+
+// Halves round away from zero, as `round` does
+fn round_to_places(x: &BigRational, places: u32) -> BigRational {
+    let scale = pow10(places);
+    let scaled = (x * BigRational::from_integer(scale.clone())).round().to_integer();
+    BigRational::new(scaled, scale)
+}
+
+fn format_decimal(x: &BigRational, places: u32) -> String {
+    let scaled = (x * BigRational::from_integer(pow10(places))).round().to_integer();
+    let sign = if scaled.is_negative() { "-" } else { "" };
+
+    let digits = scaled.abs().to_string();
+    let places = places as usize;
+    let digits = format!("{:0>width$}", digits, width = places + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - places);
+    let fraction = fraction.trim_end_matches('0');
+
+    if fraction.is_empty() {
+        format!("{}{}", sign, whole)
+    } else {
+        format!("{}{}.{}", sign, whole, fraction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DECIMAL: NumberMode = NumberMode::Decimal(DEFAULT_DECIMAL_PLACES);
+
+    fn num(text: &str, mode: NumberMode) -> Number {
+        Number::parse(text, mode).unwrap()
+    }
+
+    fn apply(left: &str, op: BinaryOp, right: &str, mode: NumberMode) -> String {
+        binary(&num(left, mode), op, &num(right, mode), mode).unwrap().format(mode)
+    }
+
+    #[test]
+    fn test_parse_exact_literals() {
+        let exact = |text: &str| parse_exact(text).unwrap();
+        let ratio = |n: i64, d: i64| BigRational::new(n.into(), d.into());
+
+        assert_eq!(exact("12"), ratio(12, 1));
+        assert_eq!(exact("0.1"), ratio(1, 10));
+        assert_eq!(exact(".5"), ratio(1, 2));
+        assert_eq!(exact("5."), ratio(5, 1));
+        assert_eq!(exact("1.5e3"), ratio(1500, 1));
+        assert_eq!(exact("25E-2"), ratio(1, 4));
+        assert_eq!(parse_exact("1e99999999"), Err(CalcError::Overflow));
+    }
+
+    #[test]
+    fn test_float_mode_matches_f64() {
+        assert_eq!(apply("0.1", BinaryOp::Add, "0.2", NumberMode::Float), "0.30000000000000004");
+    }
+
+    #[test]
+    fn test_rational_mode() {
+        let mode = NumberMode::Rational;
+
+        assert_eq!(apply("0.1", BinaryOp::Add, "0.2", mode), "3/10");
+        assert_eq!(apply("1", BinaryOp::Divide, "3", mode), "1/3");
+        assert_eq!(apply("2", BinaryOp::Power, "100", mode), "1267650600228229401496703205376");
+        assert_eq!(apply("2", BinaryOp::Power, "-2", mode), "1/4");
+        assert_eq!(apply("-7", BinaryOp::Modulo, "3", mode), "2");
+        assert_eq!(apply("-7", BinaryOp::IntDivide, "2", mode), "-4");
+        assert_eq!(
+            apply("99999999999999999999", BinaryOp::Add, "1", mode),
+            "100000000000000000000"
+        );
+        // No exact answer, so the result is a float
+        assert_eq!(apply("4", BinaryOp::Power, "0.5", mode), "2");
+    }
+
+    #[test]
+    fn test_decimal_mode() {
+        assert_eq!(apply("0.1", BinaryOp::Add, "0.2", DECIMAL), "0.3");
+        assert_eq!(apply("1", BinaryOp::Divide, "3", DECIMAL), "0.33333333333333333333");
+        assert_eq!(apply("2", BinaryOp::Divide, "3", NumberMode::Decimal(4)), "0.6667");
+        assert_eq!(apply("-1", BinaryOp::Divide, "8", NumberMode::Decimal(2)), "-0.13");
+        assert_eq!(apply("10", BinaryOp::Divide, "4", NumberMode::Decimal(0)), "3");
+        assert_eq!(apply("1.50", BinaryOp::Multiply, "2", DECIMAL), "3");
+    }
+
+    #[test]
+    fn test_exact_errors() {
+        let mode = NumberMode::Rational;
+        let error = |left: &str, op, right: &str| binary(&num(left, mode), op, &num(right, mode), mode).unwrap_err();
+
+        assert_eq!(error("1", BinaryOp::Divide, "0"), CalcError::DivisionByZero);
+        assert_eq!(error("1", BinaryOp::Modulo, "0"), CalcError::DivisionByZero);
+        assert_eq!(error("0", BinaryOp::Power, "-1"), CalcError::DivisionByZero);
+        assert_eq!(error("9", BinaryOp::Power, "99999999"), CalcError::Overflow);
+        assert_eq!(apply("-1", BinaryOp::Power, "99999999999", mode), "-1");
+        assert_eq!(apply("0", BinaryOp::Power, "0", mode), "1");
+    }
+
+    #[test]
+    fn test_radix_literals() {
+        assert_eq!(num("0xff", NumberMode::Float), Number::Float(255.0));
+        assert_eq!(num("0b1010_0101", NumberMode::Rational).format(NumberMode::Rational), "165");
+        assert_eq!(num("0o17", DECIMAL).format(DECIMAL), "15");
+        assert_eq!(
+            num("0xffffffffffffffffffff", NumberMode::Rational).format(NumberMode::Rational),
+            "1208925819614629174706175"
+        );
+    }
+
+    #[test]
+    fn test_bitwise_operators() {
+        let float = NumberMode::Float;
+        assert_eq!(apply("12", BinaryOp::BitAnd, "10", float), "8");
+        assert_eq!(apply("12", BinaryOp::BitOr, "10", float), "14");
+        assert_eq!(apply("12", BinaryOp::BitXor, "10", float), "6");
+        assert_eq!(apply("1", BinaryOp::ShiftLeft, "10", float), "1024");
+        assert_eq!(apply("-16", BinaryOp::ShiftRight, "2", float), "-4");
+        assert_eq!(apply("-1", BinaryOp::ShiftRight, "99999999999", float), "-1");
+        assert_eq!(not(&num("0", float), float), Ok(Number::Float(-1.0)));
+        assert_eq!(
+            apply("1", BinaryOp::ShiftLeft, "100", NumberMode::Rational),
+            "1267650600228229401496703205376"
+        );
+
+        let error = |left: &str, op, right: &str| binary(&num(left, float), op, &num(right, float), float).unwrap_err();
+        assert!(matches!(error("1.5", BinaryOp::BitAnd, "1"), CalcError::Domain(_)));
+        assert!(matches!(error("1", BinaryOp::ShiftLeft, "-1"), CalcError::Domain(_)));
+        assert_eq!(error("1", BinaryOp::ShiftLeft, "99999999"), CalcError::Overflow);
+    }
+
+    #[test]
+    fn test_integer_mode_wraps() {
+        let i8 = NumberMode::Integer(Word { bits: 8, signed: true });
+        let u8 = NumberMode::Integer(Word { bits: 8, signed: false });
+        let i64 = NumberMode::Integer(Word { bits: 64, signed: true });
+
+        assert_eq!(apply("127", BinaryOp::Add, "1", i8), "-128");
+        assert_eq!(apply("255", BinaryOp::Add, "1", u8), "0");
+        assert_eq!(apply("0", BinaryOp::Subtract, "1", u8), "255");
+        assert_eq!(apply("1", BinaryOp::ShiftLeft, "8", u8), "0");
+        assert_eq!(apply("1", BinaryOp::ShiftLeft, "63", i64), "-9223372036854775808");
+        assert_eq!(apply("3", BinaryOp::Power, "1000001", u8), "3");
+        assert_eq!(not(&num("0", u8), u8).unwrap().format(u8), "255");
+        // Division and non-integer results are cut toward zero
+        assert_eq!(apply("-7", BinaryOp::Divide, "2", i8), "-3");
+        assert_eq!(apply("-7", BinaryOp::IntDivide, "2", i8), "-4");
+        assert_eq!(num("2.9", i8).format(i8), "2");
+        assert_eq!(num("300", u8).format(u8), "44");
+    }
+
+    #[test]
+    fn test_format_in_base() {
+        let i8 = NumberMode::Integer(Word { bits: 8, signed: true });
+        let float = NumberMode::Float;
+
+        assert_eq!(num("255", float).format_in_base(float, 16), "0xff");
+        assert_eq!(num("5", float).format_in_base(float, 2), "0b101");
+        assert_eq!(num("8", float).format_in_base(float, 8), "0o10");
+        assert_eq!(num("35", float).format_in_base(float, 36), "z");
+        assert_eq!(num("-255", float).format_in_base(float, 16), "-0xff");
+        assert_eq!(num("-1", i8).negate().negate().format_in_base(i8, 16), "0xff");
+        assert_eq!(num("-128", i8).format_in_base(i8, 2), "0b10000000");
+        assert_eq!(num("0.5", float).format_in_base(float, 16), "0.5");
+        assert_eq!(num("255", float).format_in_base(float, 10), "255");
+    }
+
+    #[test]
+    fn test_exact_functions() {
+        let call_with = |name: &str, args: &[&str], mode| {
+            let args: Vec<Number> = args.iter().map(|a| num(a, mode)).collect();
+            call(name, &args, AngleMode::Radians, mode).unwrap().format(mode)
+        };
+
+        assert_eq!(call_with("sqrt", &["9"], NumberMode::Rational), "3");
+        assert_eq!(call_with("sqrt", &["0.25"], NumberMode::Rational), "1/2");
+        assert_eq!(call_with("sqrt", &["2"], NumberMode::Rational), "1.4142135623730951");
+        assert_eq!(call_with("sqrt", &["2"], NumberMode::Decimal(30)), "1.41421356237309504880168872421");
+        assert_eq!(call_with("max", &["0.1", "0.3", "0.2"], NumberMode::Rational), "3/10");
+        assert_eq!(call_with("round", &["2.5"], NumberMode::Rational), "3");
+        assert_eq!(call_with("ln", &["1"], NumberMode::Rational), "0");
+        assert!(matches!(
+            call("sqrt", &[num("1", NumberMode::Rational).negate()], AngleMode::Radians, NumberMode::Rational),
+            Err(CalcError::Domain(_))
+        ));
+    }
+}
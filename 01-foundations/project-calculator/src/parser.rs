@@ -0,0 +1,721 @@
+// Parser - turns tokens into an expression tree
+//
+// Uses precedence climbing: `*`, `/`, `//` and `%` bind tighter than `+`
+// and `-`, operators of equal precedence group from the left (8 - 3 - 2 is
+// (8 - 3) - 2), and unary minus applies to what follows it. `^` binds
+// tighter still and groups from the right, so -2 ^ 2 is -(2 ^ 2) and
+// 2 ^ 3 ^ 2 is 2 ^ (3 ^ 2).
+//
+// The bitwise operators bind more loosely than arithmetic, as in C, from
+// tightest to loosest: `<<` and `>>`, then `&`, `xor`, `|`. So
+// 1 << 4 - 1 is 1 << 3, and x & 0xff | 0x100 is (x & 0xff) | 0x100.
+// `~` flips all bits and, like unary minus, applies to what follows it.
+//
+// `name(a, b)` calls a function; the name and the number of arguments are
+// checked here so mistakes point at the right column.
+//
+// A unit right after a number belongs to it: `512 MiB`, `60 km/h`,
+// `9.81 m/s^2`. `in` or `to` converts a whole expression, and so comes
+// last: `1 h + 30 min in min`.
+//
+// A line is either an expression or an assignment: `name = expression`.
+
+use std::fmt;
+
+use crate::error::CalcError;
+use crate::functions;
+use crate::lexer::{tokenize, Token, TokenKind};
+use crate::units::{self, Unit};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    IntDivide,
+    Modulo,
+    Power,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
+}
+
+impl BinaryOp {
+    // The left-associative operators; `^` is handled on its own
+    fn from_token(kind: &TokenKind) -> Option<Self> {
+        match kind {
+            TokenKind::Plus => Some(BinaryOp::Add),
+            TokenKind::Minus => Some(BinaryOp::Subtract),
+            TokenKind::Star => Some(BinaryOp::Multiply),
+            TokenKind::Slash => Some(BinaryOp::Divide),
+            TokenKind::DoubleSlash => Some(BinaryOp::IntDivide),
+            TokenKind::Percent => Some(BinaryOp::Modulo),
+            TokenKind::Ampersand => Some(BinaryOp::BitAnd),
+            TokenKind::Pipe => Some(BinaryOp::BitOr),
+            TokenKind::Identifier(word) if word == "xor" => Some(BinaryOp::BitXor),
+            TokenKind::ShiftLeft => Some(BinaryOp::ShiftLeft),
+            TokenKind::ShiftRight => Some(BinaryOp::ShiftRight),
+            _ => None,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Subtract => "-",
+            BinaryOp::Multiply => "*",
+            BinaryOp::Divide => "/",
+            BinaryOp::IntDivide => "//",
+            BinaryOp::Modulo => "%",
+            BinaryOp::Power => "^",
+            BinaryOp::BitAnd => "&",
+            BinaryOp::BitOr => "|",
+            BinaryOp::BitXor => "xor",
+            BinaryOp::ShiftLeft => "<<",
+            BinaryOp::ShiftRight => ">>",
+        }
+    }
+
+    /// Operators that only work on whole numbers
+    pub fn is_bitwise(&self) -> bool {
+        matches!(
+            self,
+            BinaryOp::BitAnd | BinaryOp::BitOr | BinaryOp::BitXor | BinaryOp::ShiftLeft | BinaryOp::ShiftRight
+        )
+    }
+
+    pub fn precedence(&self) -> u8 {
+        match self {
+            BinaryOp::BitOr => 1,
+            BinaryOp::BitXor => 2,
+            BinaryOp::BitAnd => 3,
+            BinaryOp::ShiftLeft | BinaryOp::ShiftRight => 4,
+            BinaryOp::Add | BinaryOp::Subtract => 5,
+            BinaryOp::Multiply | BinaryOp::Divide | BinaryOp::IntDivide | BinaryOp::Modulo => 6,
+            BinaryOp::Power => 7,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// A literal as written, e.g. "0.1" or "1.5e3"
+    Number(String),
+    Variable(String),
+    Negate(Box<Expr>),
+    /// `~x`, every bit flipped
+    Not(Box<Expr>),
+    Binary {
+        op: BinaryOp,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    Call {
+        name: String,
+        args: Vec<Expr>,
+    },
+    /// A number with a unit: 512 MiB
+    Quantity {
+        number: String,
+        unit: Unit,
+    },
+    /// `value in unit` or `value to unit`
+    Convert {
+        value: Box<Expr>,
+        unit: Unit,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    /// 1-based column of the offending character or token
+    pub column: usize,
+}
+
+impl ParseError {
+    pub fn new(message: impl Into<String>, column: usize) -> Self {
+        ParseError {
+            message: message.into(),
+            column,
+        }
+    }
+
+    /// The error followed by the input with a caret under the column:
+    ///
+    /// ```text
+    /// Error: expected a number, found ')' at column 10
+    ///   2 * (3 + ) / 7
+    ///            ^
+    /// ```
+    pub fn render(&self, input: &str) -> String {
+        format!("Error: {}\n  {}\n  {}^", self, input, " ".repeat(self.column - 1))
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at column {}", self.message, self.column)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Assign { name: String, value: Expr },
+    Expr(Expr),
+}
+
+/// Names that hold calculator state, constants or keywords and cannot be assigned
+pub const RESERVED: [&str; 6] = ["ans", "pi", "e", "in", "to", "xor"];
+
+fn is_conversion(kind: &TokenKind) -> bool {
+    matches!(kind, TokenKind::Identifier(word) if word == "in" || word == "to")
+}
+
+pub fn parse(input: &str) -> Result<Expr, CalcError> {
+    let mut parser = Parser::new(input)?;
+    Ok(parser.finish_expression()?)
+}
+
+pub fn parse_statement(input: &str) -> Result<Statement, CalcError> {
+    let mut parser = Parser::new(input)?;
+
+    if let [Token { kind: TokenKind::Identifier(name), column }, Token { kind: TokenKind::Equals, .. }, ..] =
+        parser.tokens.as_slice()
+    {
+        if RESERVED.contains(&name.as_str()) {
+            return Err(ParseError::new(format!("cannot assign to '{}'", name), *column).into());
+        }
+        let name = name.clone();
+        parser.position = 2;
+        let value = parser.finish_expression()?;
+        return Ok(Statement::Assign { name, value });
+    }
+
+    Ok(Statement::Expr(parser.finish_expression()?))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    // Column just past the input, where "unexpected end" errors point
+    end_column: usize,
+}
+
+impl Parser {
+    fn new(input: &str) -> Result<Self, CalcError> {
+        Ok(Parser {
+            tokens: tokenize(input)?,
+            position: 0,
+            end_column: input.chars().count() + 1,
+        })
+    }
+
+    // An expression that must use up the rest of the tokens
+    fn finish_expression(&mut self) -> Result<Expr, ParseError> {
+        let expr = self.conversion()?;
+        if let Some(token) = self.peek() {
+            let message = match token.kind {
+                TokenKind::RightParen => "unmatched ')'".to_string(),
+                _ if matches!(expr, Expr::Convert { .. }) => {
+                    "a conversion must come last; put it in parentheses to keep calculating".to_string()
+                }
+                _ => format!("expected an operator, found {}", token.kind.describe()),
+            };
+            return Err(ParseError::new(message, token.column));
+        }
+        Ok(expr)
+    }
+
+    // An expression followed by any number of `in unit` conversions
+    fn conversion(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.expression(0)?;
+        while self.peek().is_some_and(|t| is_conversion(&t.kind)) {
+            self.position += 1;
+            expr = Expr::Convert {
+                value: Box::new(expr),
+                unit: self.unit()?,
+            };
+        }
+        Ok(expr)
+    }
+
+    // A known unit name at `position`, not used as a function like min(...)
+    fn unit_at(&self, position: usize) -> bool {
+        let is_unit = matches!(
+            self.tokens.get(position),
+            Some(Token { kind: TokenKind::Identifier(name), .. }) if units::lookup(name).is_some()
+        );
+        let is_call = matches!(self.tokens.get(position + 1), Some(Token { kind: TokenKind::LeftParen, .. }));
+        is_unit && !is_call
+    }
+
+    // Units multiplied or divided together, each with an optional power:
+    // MiB/s, kg*m/s^2. `*` and `/` only continue the unit when another
+    // unit follows, so in `2 m * x` the x is an ordinary operand.
+    fn unit(&mut self) -> Result<Unit, ParseError> {
+        let mut unit = Unit::default();
+        let mut sign = 1;
+
+        loop {
+            let token = self.next();
+            let def = match token {
+                Some(Token { kind: TokenKind::Identifier(name), column }) => units::lookup(&name)
+                    .ok_or_else(|| ParseError::new(format!("unknown unit '{}'", name), column))?,
+                Some(other) => {
+                    return Err(ParseError::new(
+                        format!("expected a unit, found {}", other.kind.describe()),
+                        other.column,
+                    ))
+                }
+                None => return Err(ParseError::new("expected a unit, found end of input", self.end_column)),
+            };
+            let column = self.tokens[self.position - 1].column;
+
+            let mut power = 1;
+            if self.peek_is(&TokenKind::Caret) {
+                self.position += 1;
+                power = self.unit_power()?;
+            }
+
+            if let Some((existing, _)) = unit.part(def.dimension).filter(|(existing, _)| *existing != def) {
+                return Err(ParseError::new(
+                    format!("cannot mix {} and {} in one unit", existing.name, def.name),
+                    column,
+                ));
+            }
+            unit = unit.with(def, power * sign);
+
+            sign = match self.peek().map(|t| &t.kind) {
+                Some(TokenKind::Star) if self.unit_at(self.position + 1) => 1,
+                Some(TokenKind::Slash) if self.unit_at(self.position + 1) => -1,
+                _ => return Ok(unit),
+            };
+            self.position += 1;
+        }
+    }
+
+    // The whole number after `^` in a unit, possibly negative: s^-1
+    fn unit_power(&mut self) -> Result<i32, ParseError> {
+        let negative = self.peek_is(&TokenKind::Minus);
+        if negative {
+            self.position += 1;
+        }
+        match self.next() {
+            Some(Token { kind: TokenKind::Number(text), column }) => match text.parse::<i32>() {
+                Ok(power) if negative => Ok(-power),
+                Ok(power) => Ok(power),
+                Err(_) => Err(ParseError::new("a unit power must be a whole number", column)),
+            },
+            Some(other) => Err(ParseError::new(
+                format!("expected a power, found {}", other.kind.describe()),
+                other.column,
+            )),
+            None => Err(ParseError::new("expected a power, found end of input", self.end_column)),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn peek_is(&self, kind: &TokenKind) -> bool {
+        self.peek().is_some_and(|t| t.kind == *kind)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    // Parses operators of at least `min_precedence`, e.g. with 6 only `*` and `/`
+    fn expression(&mut self, min_precedence: u8) -> Result<Expr, ParseError> {
+        let mut left = self.unary()?;
+
+        while let Some(op) = self.peek().and_then(|t| BinaryOp::from_token(&t.kind)) {
+            if op.precedence() < min_precedence {
+                break;
+            }
+            self.position += 1;
+
+            // Left associative: the right operand may only contain tighter operators
+            let right = self.expression(op.precedence() + 1)?;
+            left = Expr::Binary {
+                op,
+                left: Box::new(left),
+                right: Box::new(right),
+            };
+        }
+
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if self.peek_is(&TokenKind::Minus) {
+            self.position += 1;
+            return Ok(Expr::Negate(Box::new(self.unary()?)));
+        }
+        if self.peek_is(&TokenKind::Tilde) {
+            self.position += 1;
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<Expr, ParseError> {
+        let base = self.primary()?;
+        if !self.peek_is(&TokenKind::Caret) {
+            return Ok(base);
+        }
+        self.position += 1;
+
+        // Right associative, and the exponent may be negative: 2 ^ -1
+        let exponent = self.unary()?;
+        Ok(Expr::Binary {
+            op: BinaryOp::Power,
+            left: Box::new(base),
+            right: Box::new(exponent),
+        })
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        let Some(token) = self.next() else {
+            return Err(ParseError::new("expected a number, found end of input", self.end_column));
+        };
+
+        match token.kind {
+            TokenKind::Number(number) if self.unit_at(self.position) => {
+                let unit = self.unit()?;
+                // 5 m/m is just 5
+                if unit.is_none() {
+                    return Ok(Expr::Number(number));
+                }
+                Ok(Expr::Quantity { number, unit })
+            }
+            TokenKind::Number(text) => Ok(Expr::Number(text)),
+            TokenKind::Identifier(name) if self.peek_is(&TokenKind::LeftParen) => self.call(name, token.column),
+            TokenKind::Identifier(name) => Ok(Expr::Variable(name)),
+            TokenKind::LeftParen => {
+                let inner = self.conversion()?;
+                match self.next() {
+                    Some(Token { kind: TokenKind::RightParen, .. }) => Ok(inner),
+                    Some(other) => Err(ParseError::new(
+                        format!("expected ')', found {}", other.kind.describe()),
+                        other.column,
+                    )),
+                    None => Err(ParseError::new(
+                        format!("expected ')' to close the '(' at column {}", token.column),
+                        self.end_column,
+                    )),
+                }
+            }
+            other => Err(ParseError::new(
+                format!("expected a number, found {}", other.describe()),
+                token.column,
+            )),
+        }
+    }
+
+    // Arguments of `name(...)`, starting at the '('
+    fn call(&mut self, name: String, column: usize) -> Result<Expr, ParseError> {
+        let Some(arity) = functions::arity(&name) else {
+            return Err(ParseError::new(format!("unknown function '{}'", name), column));
+        };
+        let open_column = self.next().map_or(column, |t| t.column);
+
+        let mut args = Vec::new();
+        if self.peek_is(&TokenKind::RightParen) {
+            self.position += 1;
+        } else {
+            loop {
+                args.push(self.conversion()?);
+                match self.next() {
+                    Some(Token { kind: TokenKind::Comma, .. }) => continue,
+                    Some(Token { kind: TokenKind::RightParen, .. }) => break,
+                    Some(other) => {
+                        return Err(ParseError::new(
+                            format!("expected ',' or ')', found {}", other.kind.describe()),
+                            other.column,
+                        ))
+                    }
+                    None => {
+                        return Err(ParseError::new(
+                            format!("expected ')' to close the '(' at column {}", open_column),
+                            self.end_column,
+                        ))
+                    }
+                }
+            }
+        }
+
+        if !arity.accepts(args.len()) {
+            return Err(ParseError::new(
+                format!("{} takes {}, found {}", name, arity.describe(), args.len()),
+                column,
+            ));
+        }
+        Ok(Expr::Call { name, args })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn num(n: f64) -> Box<Expr> {
+        Box::new(Expr::Number(n.to_string()))
+    }
+
+    fn binary(op: BinaryOp, left: Box<Expr>, right: Box<Expr>) -> Box<Expr> {
+        Box::new(Expr::Binary { op, left, right })
+    }
+
+    fn syntax_error(result: Result<impl fmt::Debug, CalcError>) -> ParseError {
+        match result {
+            Err(CalcError::Syntax(e)) => e,
+            other => panic!("expected a syntax error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_precedence() {
+        // 1 + 2 * 3 is 1 + (2 * 3)
+        assert_eq!(
+            parse("1 + 2 * 3").unwrap(),
+            *binary(BinaryOp::Add, num(1.0), binary(BinaryOp::Multiply, num(2.0), num(3.0)))
+        );
+    }
+
+    #[test]
+    fn test_left_associativity() {
+        // 8 - 3 - 2 is (8 - 3) - 2
+        assert_eq!(
+            parse("8 - 3 - 2").unwrap(),
+            *binary(BinaryOp::Subtract, binary(BinaryOp::Subtract, num(8.0), num(3.0)), num(2.0))
+        );
+    }
+
+    #[test]
+    fn test_parentheses_and_unary_minus() {
+        assert_eq!(
+            parse("-(1 + 2) * -3").unwrap(),
+            *binary(
+                BinaryOp::Multiply,
+                Box::new(Expr::Negate(binary(BinaryOp::Add, num(1.0), num(2.0)))),
+                Box::new(Expr::Negate(num(3.0)))
+            )
+        );
+        assert_eq!(parse("--4").unwrap(), Expr::Negate(Box::new(Expr::Negate(num(4.0)))));
+    }
+
+    #[test]
+    fn test_power_binds_tightest_and_groups_right() {
+        // -2 ^ 2 is -(2 ^ 2)
+        assert_eq!(
+            parse("-2 ^ 2").unwrap(),
+            Expr::Negate(binary(BinaryOp::Power, num(2.0), num(2.0)))
+        );
+        // 2 ^ 3 ^ 2 is 2 ^ (3 ^ 2)
+        assert_eq!(
+            parse("2 ^ 3 ^ 2").unwrap(),
+            *binary(BinaryOp::Power, num(2.0), binary(BinaryOp::Power, num(3.0), num(2.0)))
+        );
+        // 2 * 3 ^ -1 is 2 * (3 ^ (-1))
+        assert_eq!(
+            parse("2 * 3 ^ -1").unwrap(),
+            *binary(
+                BinaryOp::Multiply,
+                num(2.0),
+                binary(BinaryOp::Power, num(3.0), Box::new(Expr::Negate(num(1.0))))
+            )
+        );
+        // % and // share precedence with *
+        assert_eq!(
+            parse("1 + 7 // 2 % 3").unwrap(),
+            *binary(
+                BinaryOp::Add,
+                num(1.0),
+                binary(BinaryOp::Modulo, binary(BinaryOp::IntDivide, num(7.0), num(2.0)), num(3.0))
+            )
+        );
+    }
+
+    #[test]
+    fn test_function_calls() {
+        assert_eq!(
+            parse("max(1, -x, 2 * 3)").unwrap(),
+            Expr::Call {
+                name: "max".to_string(),
+                args: vec![
+                    *num(1.0),
+                    Expr::Negate(Box::new(Expr::Variable("x".to_string()))),
+                    *binary(BinaryOp::Multiply, num(2.0), num(3.0)),
+                ],
+            }
+        );
+
+        let error = |input: &str| syntax_error(parse(input));
+        assert_eq!(error("2 + foo(1)").message, "unknown function 'foo'");
+        assert_eq!(error("2 + foo(1)").column, 5);
+        assert_eq!(error("sqrt(1, 2)").message, "sqrt takes 1 argument, found 2");
+        assert_eq!(error("max()").message, "max takes at least 1 argument, found 0");
+        assert_eq!(error("min(1 2)").message, "expected ',' or ')', found number 2");
+        assert_eq!(error("abs(1").column, 6);
+    }
+
+    #[test]
+    fn test_units_and_conversions() {
+        let unit = |parts: &[(&str, i32)]| {
+            parts
+                .iter()
+                .fold(Unit::default(), |u, (name, power)| u.with(units::lookup(name).unwrap(), *power))
+        };
+        let quantity = |number: &str, parts: &[(&str, i32)]| Expr::Quantity {
+            number: number.to_string(),
+            unit: unit(parts),
+        };
+
+        // 512 MiB / 2 s divides two quantities
+        assert_eq!(
+            parse("512 MiB / 2 s").unwrap(),
+            *binary(
+                BinaryOp::Divide,
+                Box::new(quantity("512", &[("MiB", 1)])),
+                Box::new(quantity("2", &[("s", 1)]))
+            )
+        );
+        // ... while in 60 km/h the unit is km per hour
+        assert_eq!(parse("60 km/h").unwrap(), quantity("60", &[("km", 1), ("h", -1)]));
+        assert_eq!(parse("9.81 m/s^2").unwrap(), quantity("9.81", &[("m", 1), ("s", -2)]));
+        // min( is the function, not minutes
+        assert_eq!(
+            parse("2 m * min(1, 2)").unwrap(),
+            *binary(
+                BinaryOp::Multiply,
+                Box::new(quantity("2", &[("m", 1)])),
+                Box::new(Expr::Call {
+                    name: "min".to_string(),
+                    args: vec![*num(1.0), *num(2.0)],
+                })
+            )
+        );
+
+        assert_eq!(
+            parse("1 h + 30 min in min").unwrap(),
+            Expr::Convert {
+                value: binary(
+                    BinaryOp::Add,
+                    Box::new(quantity("1", &[("h", 1)])),
+                    Box::new(quantity("30", &[("min", 1)]))
+                ),
+                unit: unit(&[("min", 1)]),
+            }
+        );
+        assert_eq!(
+            parse("(1 GB to GiB) * 2").unwrap(),
+            *binary(
+                BinaryOp::Multiply,
+                Box::new(Expr::Convert {
+                    value: Box::new(quantity("1", &[("GB", 1)])),
+                    unit: unit(&[("GiB", 1)]),
+                }),
+                num(2.0)
+            )
+        );
+
+        let error = |input: &str| syntax_error(parse(input));
+        assert_eq!(error("3 h in parsecs").message, "unknown unit 'parsecs'");
+        assert_eq!(error("3 h in parsecs").column, 8);
+        assert_eq!(error("5 km*m").message, "cannot mix km and m in one unit");
+        assert_eq!(error("3 h in min + 1").column, 12);
+        assert_eq!(error("3 h in").message, "expected a unit, found end of input");
+        assert_eq!(error("2 s^1.5").message, "a unit power must be a whole number");
+    }
+
+    #[test]
+    fn test_bitwise_precedence() {
+        let var = |name: &str| Box::new(Expr::Variable(name.to_string()));
+        let literal = |text: &str| Box::new(Expr::Number(text.to_string()));
+
+        // 1 << 4 - 1 is 1 << (4 - 1)
+        assert_eq!(
+            parse("1 << 4 - 1").unwrap(),
+            *binary(
+                BinaryOp::ShiftLeft,
+                num(1.0),
+                binary(BinaryOp::Subtract, num(4.0), num(1.0))
+            )
+        );
+        // x & 0xff | y xor z is (x & 0xff) | (y xor z)
+        assert_eq!(
+            parse("x & 0xff | y xor z").unwrap(),
+            *binary(
+                BinaryOp::BitOr,
+                binary(BinaryOp::BitAnd, var("x"), literal("0xff")),
+                binary(BinaryOp::BitXor, var("y"), var("z"))
+            )
+        );
+        assert_eq!(
+            parse("~x & 1").unwrap(),
+            *binary(BinaryOp::BitAnd, Box::new(Expr::Not(var("x"))), num(1.0))
+        );
+        assert_eq!(syntax_error(parse_statement("xor = 1")).message, "cannot assign to 'xor'");
+    }
+
+    #[test]
+    fn test_statements() {
+        assert_eq!(
+            parse_statement("x = 2 * ans").unwrap(),
+            Statement::Assign {
+                name: "x".to_string(),
+                value: *binary(BinaryOp::Multiply, num(2.0), Box::new(Expr::Variable("ans".to_string()))),
+            }
+        );
+        assert_eq!(parse_statement("x").unwrap(), Statement::Expr(Expr::Variable("x".to_string())));
+
+        assert_eq!(syntax_error(parse_statement("ans = 1")).message, "cannot assign to 'ans'");
+        assert_eq!(syntax_error(parse_statement("pi = 3")).message, "cannot assign to 'pi'");
+        assert_eq!(syntax_error(parse_statement("x = ")).column, 5);
+        assert_eq!(syntax_error(parse_statement("1 = 2")).column, 3);
+        assert_eq!(syntax_error(parse("x = 1")).column, 3);
+    }
+
+    #[test]
+    fn test_error_columns() {
+        let column = |input: &str| syntax_error(parse(input)).column;
+
+        assert_eq!(column(""), 1);
+        assert_eq!(column("5 +"), 4);
+        assert_eq!(column("2 * (3 + ) / 7"), 10);
+        assert_eq!(column("(1 + 2"), 7);
+        assert_eq!(column("1 + 2)"), 6);
+        assert_eq!(column("2 3"), 3);
+        assert_eq!(column("2 * * 3"), 5);
+    }
+
+    #[test]
+    fn test_error_messages() {
+        let message = |input: &str| syntax_error(parse(input)).message;
+
+        assert_eq!(message("(1 + 2"), "expected ')' to close the '(' at column 1");
+        assert_eq!(message("1 + 2)"), "unmatched ')'");
+        assert_eq!(message("2 3"), "expected an operator, found number 3");
+    }
+
+    #[test]
+    fn test_render_points_at_column() {
+        let input = "2 * (3 + ) / 7";
+        let rendered = parse(input).unwrap_err().render(input);
+
+        assert_eq!(
+            rendered,
+            "Error: expected a number, found ')' at column 10\n  2 * (3 + ) / 7\n           ^"
+        );
+    }
+}
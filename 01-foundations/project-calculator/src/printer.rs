@@ -0,0 +1,158 @@
+// Printer - turns an expression tree back into text
+//
+// The output has one space around each operator and only the parentheses
+// the precedence rules need, and it parses back to the same tree:
+//
+//   2*(3+4)        prints as  2 * (3 + 4)
+//   -(2^2)         prints as  -2 ^ 2
+//   (-2)^2         keeps its parentheses
+//   1 h+30 min to min  prints as  1 h + 30 min in min
+//
+// Units need some care, because the parser reads `*`, `/` and `^` right
+// after a unit as more of the unit: the variable s in `2 m * s` is written
+// `2 m * (s)`, and `(2 m) ^ 2` keeps its parentheses.
+
+use std::fmt;
+
+use crate::parser::{BinaryOp, Expr, Statement};
+use crate::units;
+
+// How tightly each kind of expression holds together. Binary operators
+// use twice their precedence so unary minus and ~ fit between `*` and `^`.
+const CONVERT: u8 = 0;
+const UNARY: u8 = 13;
+const ATOM: u8 = 16;
+
+fn binding(expr: &Expr) -> u8 {
+    match expr {
+        Expr::Convert { .. } => CONVERT,
+        Expr::Binary { op, .. } => op.precedence() * 2,
+        Expr::Negate(_) | Expr::Not(_) => UNARY,
+        Expr::Number(_) | Expr::Variable(_) | Expr::Call { .. } | Expr::Quantity { .. } => ATOM,
+    }
+}
+
+// Whether the left and right operands of `op` need parentheses
+fn parens(op: BinaryOp, left: &Expr, right: &Expr) -> (bool, bool) {
+    let precedence = op.precedence() * 2;
+    if op == BinaryOp::Power {
+        // Right associative, and -2 ^ 2 means -(2 ^ 2)
+        let left_parens = binding(left) <= precedence || matches!(left, Expr::Quantity { .. });
+        return (left_parens, binding(right) < UNARY);
+    }
+
+    let left_parens = binding(left) < precedence;
+    let right_parens = binding(right) <= precedence
+        || (matches!(op, BinaryOp::Multiply | BinaryOp::Divide)
+            && !left_parens
+            && ends_with_quantity(left)
+            && starts_with_unit(right));
+    (left_parens, right_parens)
+}
+
+// Whether the printed expression ends with a number and its unit
+fn ends_with_quantity(expr: &Expr) -> bool {
+    match expr {
+        Expr::Quantity { .. } => true,
+        Expr::Negate(inner) | Expr::Not(inner) => binding(inner) >= UNARY && ends_with_quantity(inner),
+        Expr::Binary { op, left, right } => !parens(*op, left, right).1 && ends_with_quantity(right),
+        _ => false,
+    }
+}
+
+// Whether the printed expression starts with a name that is also a unit
+fn starts_with_unit(expr: &Expr) -> bool {
+    match expr {
+        Expr::Variable(name) => units::lookup(name).is_some(),
+        Expr::Binary { op, left, right } => !parens(*op, left, right).0 && starts_with_unit(left),
+        Expr::Convert { value, .. } => starts_with_unit(value),
+        _ => false,
+    }
+}
+
+fn operand(f: &mut fmt::Formatter, expr: &Expr, parens: bool) -> fmt::Result {
+    if parens {
+        write!(f, "({})", expr)
+    } else {
+        write!(f, "{}", expr)
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Number(text) => write!(f, "{}", text),
+            Expr::Variable(name) => write!(f, "{}", name),
+            Expr::Quantity { number, unit } => write!(f, "{} {}", number, unit),
+            Expr::Negate(inner) => {
+                write!(f, "-")?;
+                operand(f, inner, binding(inner) < UNARY)
+            }
+            Expr::Not(inner) => {
+                write!(f, "~")?;
+                operand(f, inner, binding(inner) < UNARY)
+            }
+            Expr::Binary { op, left, right } => {
+                let (left_parens, right_parens) = parens(*op, left, right);
+                operand(f, left, left_parens)?;
+                write!(f, " {} ", op.symbol())?;
+                operand(f, right, right_parens)
+            }
+            Expr::Call { name, args } => {
+                write!(f, "{}(", name)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ")")
+            }
+            Expr::Convert { value, unit } => write!(f, "{} in {}", value, unit),
+        }
+    }
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Statement::Assign { name, value } => write!(f, "{} = {}", name, value),
+            Statement::Expr(expr) => write!(f, "{}", expr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse;
+
+    fn print(input: &str) -> String {
+        parse(input).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_minimal_parentheses() {
+        assert_eq!(print("2*(3+4)"), "2 * (3 + 4)");
+        assert_eq!(print("(2*3)+4"), "2 * 3 + 4");
+        assert_eq!(print("8-(3-2)"), "8 - (3 - 2)");
+        assert_eq!(print("(8-3)-2"), "8 - 3 - 2");
+        assert_eq!(print("-(2^2)"), "-2 ^ 2");
+        assert_eq!(print("(-2)^2"), "(-2) ^ 2");
+        assert_eq!(print("2^(3^2)"), "2 ^ 3 ^ 2");
+        assert_eq!(print("(2^3)^2"), "(2 ^ 3) ^ 2");
+        assert_eq!(print("2^-(1+1)"), "2 ^ -(1 + 1)");
+        assert_eq!(print("~(x&1)|y<<2"), "~(x & 1) | y << 2");
+        assert_eq!(print("max(1,(2+3))"), "max(1, 2 + 3)");
+    }
+
+    #[test]
+    fn test_units_and_conversions() {
+        assert_eq!(print("512 MiB/(2 s)"), "512 MiB / 2 s");
+        assert_eq!(print("60 km/h"), "60 km/h");
+        assert_eq!(print("1 h+30 min to min"), "1 h + 30 min in min");
+        assert_eq!(print("(2 ft to m)*2"), "(2 ft in m) * 2");
+        assert_eq!(print("(2 m)^2"), "(2 m) ^ 2");
+        assert_eq!(print("2 m * (s)"), "2 m * (s)");
+        assert_eq!(print("x * s"), "x * s");
+    }
+}
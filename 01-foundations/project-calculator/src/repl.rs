@@ -0,0 +1,283 @@
+// REPL - the interactive mode started when no expression is given
+//
+//   >> x = 3.5
+//   x = 3.5
+//   >> x * 2
+//   7
+//   >> ans + 1
+//   8
+//
+// Every result is stored in `ans`. Mistakes are reported and the session
+// carries on; Ctrl-C clears the current line and Ctrl-D (or :quit) exits.
+
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+
+use calculator::number::{self, NumberMode, Word};
+use calculator::{evaluate, parse, AngleMode, Ast, Env};
+
+const PROMPT: &str = ">> ";
+
+const HELP: &str = "\
+Enter an expression such as 2 * (3 + 4), or assign one with x = 3.5
+The previous result is available as `ans`
+
+Operators:  + - * /  // (floor division)  % (modulo)  ^ (power)
+Bitwise:    & | xor ~ << >>  on whole numbers such as 0xff 0o17 0b1010
+Functions:  sqrt abs ln log10 exp  sin cos tan asin acos atan
+            floor ceil round  min(a, b, ...) max(a, b, ...)
+Constants:  pi e
+Units:      512 MiB / 2 s, 60 km/h, 3 h in min, 1 GB to GiB
+            data  bit kbit Mbit Gbit B kB MB GB TB PB KiB MiB GiB TiB PiB
+            time  ns us ms s min h d day week
+            length  nm um mm cm m km inch ft yd mi
+            mass  mg g kg t oz lb
+
+Commands:
+  :help    show this message
+  :vars    list variables and their values
+  :clear   forget all variables (including ans)
+  :deg     trigonometry in degrees
+  :rad     trigonometry in radians
+  :float       ordinary floating point numbers (the default)
+  :rational    exact fractions: 1/3 + 1/6 = 1/2
+  :decimal N   exact decimals rounded to N places (default 20)
+  :int N       N-bit signed integers that wrap around (default 64)
+  :uint N      N-bit unsigned integers that wrap around (default 64)
+  :base N      show whole numbers in base N, e.g. 16 or 2 (default 10)
+  :quit    leave the calculator (Ctrl-D works too)";
+
+pub struct Session {
+    env: Env,
+}
+
+impl Session {
+    pub fn new(env: Env) -> Self {
+        Session { env }
+    }
+
+    /// Runs one line of input. Returns the text to show, if any, or an
+    /// error message ready to print.
+    pub fn execute(&mut self, line: &str) -> Result<Option<String>, String> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(None);
+        }
+        if let Some(command) = line.strip_prefix(':') {
+            return self.command(command.trim()).map(Some);
+        }
+
+        let ast = parse(line).map_err(|e| e.render(line))?;
+        let result = evaluate(&ast, &mut self.env).map_err(|e| e.render(line))?;
+        let shown = self.env.show(&result);
+        match ast {
+            Ast::Assign { name, .. } => Ok(Some(format!("{} = {}", name, shown))),
+            Ast::Expr(_) => Ok(Some(shown)),
+        }
+    }
+
+    fn command(&mut self, command: &str) -> Result<String, String> {
+        let (command, argument) = command.split_once(' ').unwrap_or((command, ""));
+        match command {
+            "help" | "h" => Ok(HELP.to_string()),
+            "vars" => {
+                if self.env.variables.is_empty() {
+                    return Ok("No variables defined".to_string());
+                }
+                let lines: Vec<String> = self
+                    .env
+                    .variables
+                    .iter()
+                    .map(|(name, value)| format!("{} = {}", name, self.env.show(value)))
+                    .collect();
+                Ok(lines.join("\n"))
+            }
+            "clear" => {
+                self.env.variables.clear();
+                Ok("Variables cleared".to_string())
+            }
+            "deg" | "rad" => {
+                self.env.angles = if command == "deg" { AngleMode::Degrees } else { AngleMode::Radians };
+                Ok(format!("Angles in {}", self.env.angles.name()))
+            }
+            "float" => self.set_mode(NumberMode::Float),
+            "rational" => self.set_mode(NumberMode::Rational),
+            "decimal" if argument.trim().is_empty() => {
+                self.set_mode(NumberMode::Decimal(number::DEFAULT_DECIMAL_PLACES))
+            }
+            "decimal" => match number::decimal_places(argument) {
+                Some(places) => self.set_mode(NumberMode::Decimal(places)),
+                None => Err(format!(
+                    "Error: :decimal takes a number of places from 0 to {}",
+                    number::MAX_DECIMAL_PLACES
+                )),
+            },
+            "int" | "uint" => {
+                let bits = if argument.trim().is_empty() {
+                    Some(number::DEFAULT_WORD_BITS)
+                } else {
+                    number::word_bits(argument)
+                };
+                match bits {
+                    Some(bits) => self.set_mode(NumberMode::Integer(Word {
+                        bits,
+                        signed: command == "int",
+                    })),
+                    None => Err(format!(
+                        "Error: :{} takes a word size of 8, 16, 32, 64 or 128 bits",
+                        command
+                    )),
+                }
+            }
+            "base" => match number::output_base(argument) {
+                Some(base) => {
+                    self.env.base = base;
+                    Ok(format!("Whole numbers in base {}", base))
+                }
+                None => Err("Error: :base takes a base from 2 to 36".to_string()),
+            },
+            _ => Err(format!("Error: unknown command ':{}' (try :help)", command)),
+        }
+    }
+
+    fn set_mode(&mut self, mode: NumberMode) -> Result<String, String> {
+        self.env.mode = mode;
+        Ok(format!("Numbers are {}", mode.name()))
+    }
+}
+
+pub fn run(env: Env) -> rustyline::Result<()> {
+    let mut editor = DefaultEditor::new()?;
+    println!(
+        "Calculator ({}, {}) - type :help for commands, Ctrl-D to quit",
+        env.mode.name(),
+        env.angles.name()
+    );
+    let mut session = Session::new(env);
+
+    loop {
+        let line = match editor.readline(PROMPT) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e),
+        };
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        editor.add_history_entry(line)?;
+
+        if matches!(line, ":quit" | ":q") {
+            break;
+        }
+        match session.execute(line) {
+            Ok(Some(output)) => println!("{}", output),
+            Ok(None) => {}
+            Err(message) => eprintln!("{}", message),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(session: &mut Session, line: &str) -> String {
+        session.execute(line).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_assignment_and_ans() {
+        let mut session = Session::new(Env::default());
+
+        assert_eq!(output(&mut session, "x = 3.5"), "x = 3.5");
+        assert_eq!(output(&mut session, "x * 2"), "7");
+        assert_eq!(output(&mut session, "ans + 1"), "8");
+        assert_eq!(output(&mut session, "y = ans / 4"), "y = 2");
+        assert_eq!(output(&mut session, "ans"), "2");
+    }
+
+    #[test]
+    fn test_errors_keep_the_session() {
+        let mut session = Session::new(Env::default());
+        output(&mut session, "x = 2");
+
+        let error = session.execute("x +").unwrap_err();
+        assert!(error.starts_with("Error: expected a number, found end of input at column 4"));
+        assert_eq!(session.execute("z * 2"), Err("Error: unknown variable 'z'".to_string()));
+        assert!(session.execute(":nope").unwrap_err().contains(":help"));
+
+        // A failed line leaves everything as it was
+        assert_eq!(output(&mut session, "x"), "2");
+        assert_eq!(session.execute("   "), Ok(None));
+    }
+
+    #[test]
+    fn test_commands() {
+        let mut session = Session::new(Env::default());
+        assert_eq!(session.execute("ans"), Err("Error: unknown variable 'ans'".to_string()));
+        assert_eq!(output(&mut session, ":vars"), "No variables defined");
+        assert!(output(&mut session, ":help").contains(":clear"));
+
+        output(&mut session, "width = 4");
+        output(&mut session, "area = width * width");
+        assert_eq!(output(&mut session, ":vars"), "ans = 16\narea = 16\nwidth = 4");
+
+        output(&mut session, ":clear");
+        assert_eq!(output(&mut session, ":vars"), "No variables defined");
+    }
+
+    #[test]
+    fn test_angle_mode_commands() {
+        let mut session = Session::new(Env::default());
+        assert_eq!(output(&mut session, "asin(1) * 2 / pi"), "1");
+
+        assert_eq!(output(&mut session, ":deg"), "Angles in degrees");
+        assert_eq!(output(&mut session, "asin(1)"), "90");
+        assert_eq!(output(&mut session, ":rad"), "Angles in radians");
+        assert!(session.execute("sqrt(-4)").unwrap_err().contains("domain error"));
+    }
+
+    #[test]
+    fn test_number_mode_commands() {
+        let mut session = Session::new(Env::default());
+        assert_eq!(output(&mut session, "0.1 + 0.2"), "0.30000000000000004");
+
+        assert_eq!(output(&mut session, ":rational"), "Numbers are rational");
+        assert_eq!(output(&mut session, "third = 1 / 3"), "third = 1/3");
+        assert_eq!(output(&mut session, ":decimal 5"), "Numbers are decimal, 5 places");
+        assert_eq!(output(&mut session, "third"), "0.33333");
+        assert_eq!(output(&mut session, "0.1 + 0.2"), "0.3");
+        assert!(session.execute(":decimal lots").unwrap_err().contains("places"));
+        assert_eq!(output(&mut session, ":decimal"), "Numbers are decimal, 20 places");
+    }
+
+    #[test]
+    fn test_programmer_mode_commands() {
+        let mut session = Session::new(Env::default());
+        assert_eq!(output(&mut session, ":uint 8"), "Numbers are integer, 8-bit unsigned");
+        assert_eq!(output(&mut session, "mask = ~0x0f"), "mask = 240");
+        assert_eq!(output(&mut session, ":base 16"), "Whole numbers in base 16");
+        assert_eq!(output(&mut session, "mask | 1 << 2"), "0xf4");
+        assert_eq!(output(&mut session, ":int"), "Numbers are integer, 64-bit signed");
+        assert_eq!(output(&mut session, "-1"), "0xffffffffffffffff");
+        assert!(session.execute(":int 12").unwrap_err().contains("word size"));
+        assert!(session.execute(":base 1").unwrap_err().contains("2 to 36"));
+    }
+
+    #[test]
+    fn test_units() {
+        let mut session = Session::new(Env::default());
+        assert_eq!(output(&mut session, "size = 512 MiB"), "size = 512 MiB");
+        assert_eq!(output(&mut session, "size / 2 s"), "256 MiB/s");
+        assert_eq!(output(&mut session, "ans * 1 min in GiB"), "15 GiB");
+        assert_eq!(
+            session.execute("size + 1 s"),
+            Err("Error: incompatible units: MiB + s".to_string())
+        );
+    }
+}
@@ -0,0 +1,211 @@
+// Units - the table of known units and products of them like MiB/s
+//
+// Every unit measures one dimension and knows its size in that
+// dimension's base unit: bytes, seconds, metres and kilograms. Sizes are
+// written as exact decimals so the rational and decimal modes convert
+// without rounding.
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dimension {
+    Data,
+    Time,
+    Length,
+    Mass,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct UnitDef {
+    pub name: &'static str,
+    pub dimension: Dimension,
+    /// Size in the base unit of the dimension, as a number literal
+    pub factor: &'static str,
+}
+
+const fn unit(name: &'static str, dimension: Dimension, factor: &'static str) -> UnitDef {
+    UnitDef {
+        name,
+        dimension,
+        factor,
+    }
+}
+
+pub static UNITS: &[UnitDef] = &[
+    // Data, in bytes
+    unit("bit", Dimension::Data, "0.125"),
+    unit("kbit", Dimension::Data, "125"),
+    unit("Mbit", Dimension::Data, "125000"),
+    unit("Gbit", Dimension::Data, "125000000"),
+    unit("B", Dimension::Data, "1"),
+    unit("kB", Dimension::Data, "1e3"),
+    unit("KB", Dimension::Data, "1e3"),
+    unit("MB", Dimension::Data, "1e6"),
+    unit("GB", Dimension::Data, "1e9"),
+    unit("TB", Dimension::Data, "1e12"),
+    unit("PB", Dimension::Data, "1e15"),
+    unit("KiB", Dimension::Data, "1024"),
+    unit("MiB", Dimension::Data, "1048576"),
+    unit("GiB", Dimension::Data, "1073741824"),
+    unit("TiB", Dimension::Data, "1099511627776"),
+    unit("PiB", Dimension::Data, "1125899906842624"),
+    // Time, in seconds
+    unit("ns", Dimension::Time, "1e-9"),
+    unit("us", Dimension::Time, "1e-6"),
+    unit("ms", Dimension::Time, "1e-3"),
+    unit("s", Dimension::Time, "1"),
+    unit("min", Dimension::Time, "60"),
+    unit("h", Dimension::Time, "3600"),
+    unit("d", Dimension::Time, "86400"),
+    unit("day", Dimension::Time, "86400"),
+    unit("week", Dimension::Time, "604800"),
+    // Length, in metres
+    unit("nm", Dimension::Length, "1e-9"),
+    unit("um", Dimension::Length, "1e-6"),
+    unit("mm", Dimension::Length, "1e-3"),
+    unit("cm", Dimension::Length, "1e-2"),
+    unit("m", Dimension::Length, "1"),
+    unit("km", Dimension::Length, "1e3"),
+    unit("inch", Dimension::Length, "0.0254"),
+    unit("ft", Dimension::Length, "0.3048"),
+    unit("yd", Dimension::Length, "0.9144"),
+    unit("mi", Dimension::Length, "1609.344"),
+    // Mass, in kilograms
+    unit("mg", Dimension::Mass, "1e-6"),
+    unit("g", Dimension::Mass, "1e-3"),
+    unit("kg", Dimension::Mass, "1"),
+    unit("t", Dimension::Mass, "1e3"),
+    unit("oz", Dimension::Mass, "0.028349523125"),
+    unit("lb", Dimension::Mass, "0.45359237"),
+];
+
+pub fn lookup(name: &str) -> Option<&'static UnitDef> {
+    UNITS.iter().find(|unit| unit.name == name)
+}
+
+/// A product of units raised to powers, e.g. MiB/s is MiB^1 * s^-1.
+/// There is at most one unit per dimension; no parts means a plain number.
+#[derive(Debug, Clone, Default)]
+pub struct Unit {
+    parts: Vec<(&'static UnitDef, i32)>,
+}
+
+// The order of the parts does not matter: s^-1*MiB is MiB/s
+impl PartialEq for Unit {
+    fn eq(&self, other: &Unit) -> bool {
+        self.parts.len() == other.parts.len() && self.parts.iter().all(|part| other.parts.contains(part))
+    }
+}
+
+impl Unit {
+    pub fn is_none(&self) -> bool {
+        self.parts.is_empty()
+    }
+
+    pub fn parts(&self) -> &[(&'static UnitDef, i32)] {
+        &self.parts
+    }
+
+    /// The unit used for `dimension`, and its power
+    pub fn part(&self, dimension: Dimension) -> Option<(&'static UnitDef, i32)> {
+        self.parts.iter().copied().find(|(unit, _)| unit.dimension == dimension)
+    }
+
+    /// Multiplies in `unit ^ power`. The caller converts first if this
+    /// already uses a different unit for the same dimension.
+    pub fn with(mut self, unit: &'static UnitDef, power: i32) -> Unit {
+        match self.parts.iter().position(|(u, _)| u.dimension == unit.dimension) {
+            Some(i) => {
+                self.parts[i].1 += power;
+                if self.parts[i].1 == 0 {
+                    self.parts.remove(i);
+                }
+            }
+            None if power != 0 => self.parts.push((unit, power)),
+            None => {}
+        }
+        self
+    }
+
+    pub fn pow(&self, exponent: i32) -> Unit {
+        Unit {
+            parts: self.parts.iter().map(|&(unit, power)| (unit, power * exponent)).collect(),
+        }
+    }
+
+    /// Whether the two measure the same thing, e.g. MiB/s and GB/h
+    pub fn same_dimensions(&self, other: &Unit) -> bool {
+        let powers = |unit: &Unit| {
+            let mut powers: Vec<(Dimension, i32)> = unit.parts.iter().map(|(u, p)| (u.dimension, *p)).collect();
+            powers.sort_by_key(|(dimension, _)| *dimension as u8);
+            powers
+        };
+        powers(self) == powers(other)
+    }
+}
+
+// MiB/s, kg*m/s^2, and s^-1 when nothing is on top
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let part = |unit: &UnitDef, power: i32| match power {
+            1 => unit.name.to_string(),
+            _ => format!("{}^{}", unit.name, power),
+        };
+
+        let above: Vec<String> = self.parts.iter().filter(|(_, p)| *p > 0).map(|(u, p)| part(u, *p)).collect();
+        let below: Vec<String> = self.parts.iter().filter(|(_, p)| *p < 0).map(|(u, p)| part(u, -p)).collect();
+
+        if above.is_empty() {
+            let below: Vec<String> = self.parts.iter().map(|(u, p)| part(u, *p)).collect();
+            return write!(f, "{}", below.join("*"));
+        }
+        write!(f, "{}", above.join("*"))?;
+        for unit in below {
+            write!(f, "/{}", unit)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_of(parts: &[(&str, i32)]) -> Unit {
+        parts
+            .iter()
+            .fold(Unit::default(), |unit, (name, power)| unit.with(lookup(name).unwrap(), *power))
+    }
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!(lookup("MiB").unwrap().factor, "1048576");
+        assert_eq!(lookup("h").unwrap().dimension, Dimension::Time);
+        assert!(lookup("mib").is_none());
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(unit_of(&[("MiB", 1), ("s", -1)]).to_string(), "MiB/s");
+        assert_eq!(unit_of(&[("kg", 1), ("m", 1), ("s", -2)]).to_string(), "kg*m/s^2");
+        assert_eq!(unit_of(&[("m", 2)]).to_string(), "m^2");
+        assert_eq!(unit_of(&[("s", -1)]).to_string(), "s^-1");
+    }
+
+    #[test]
+    fn test_powers_combine_and_cancel() {
+        let speed = unit_of(&[("km", 1), ("h", -1)]);
+        assert!(speed.clone().with(lookup("h").unwrap(), 1).same_dimensions(&unit_of(&[("m", 1)])));
+        assert!(speed.with(lookup("h").unwrap(), 1).with(lookup("km").unwrap(), -1).is_none());
+        assert_eq!(unit_of(&[("m", 1), ("s", -1)]).pow(2), unit_of(&[("m", 2), ("s", -2)]));
+        assert_eq!(unit_of(&[("s", -1), ("MiB", 1)]), unit_of(&[("MiB", 1), ("s", -1)]));
+    }
+
+    #[test]
+    fn test_same_dimensions() {
+        assert!(unit_of(&[("MiB", 1), ("s", -1)]).same_dimensions(&unit_of(&[("GB", 1), ("h", -1)])));
+        assert!(unit_of(&[("s", -1), ("B", 1)]).same_dimensions(&unit_of(&[("MB", 1), ("ms", -1)])));
+        assert!(!unit_of(&[("MiB", 1)]).same_dimensions(&unit_of(&[("s", 1)])));
+        assert!(!unit_of(&[("m", 1)]).same_dimensions(&Unit::default()));
+    }
+}
@@ -0,0 +1,317 @@
+// Values - a number and its unit, if it has one
+//
+// Arithmetic checks dimensions: 2 h + 30 min is 2.5 h, but 1 MiB + 1 s is
+// an error. When the two sides use different units for one dimension, the
+// right side is converted to the left side's unit first, so 1 km + 500 m
+// is 1.5 km and 512 MiB / 2 s stays in MiB/s.
+
+use crate::error::CalcError;
+use crate::functions::AngleMode;
+use crate::number::{self, Number, NumberMode};
+use crate::parser::BinaryOp;
+use crate::units::Unit;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Value {
+    pub number: Number,
+    pub unit: Unit,
+}
+
+impl Value {
+    pub fn plain(number: Number) -> Value {
+        Value {
+            number,
+            unit: Unit::default(),
+        }
+    }
+
+    pub fn negate(self) -> Value {
+        Value {
+            number: self.number.negate(),
+            unit: self.unit,
+        }
+    }
+
+    /// "256 MiB/s", or just the number when there is no unit. Whole
+    /// numbers are shown in `base`: "0x100 B"
+    pub fn format_in_base(&self, mode: NumberMode, base: u32) -> String {
+        let number = self.number.format_in_base(mode, base);
+        if self.unit.is_none() {
+            number
+        } else {
+            format!("{} {}", number, self.unit)
+        }
+    }
+}
+
+pub fn binary(left: Value, op: BinaryOp, right: Value, mode: NumberMode) -> Result<Value, CalcError> {
+    match op {
+        BinaryOp::Add | BinaryOp::Subtract | BinaryOp::Modulo | BinaryOp::IntDivide => {
+            if !left.unit.same_dimensions(&right.unit) {
+                return Err(CalcError::Unit(format!(
+                    "incompatible units: {} {} {}",
+                    describe(&left.unit),
+                    op.symbol(),
+                    describe(&right.unit)
+                )));
+            }
+            let right = convert_number(right.number, &right.unit, &left.unit, mode)?;
+            let number = number::binary(&left.number, op, &right, mode)?;
+            // 7 h // 2 h is how many times 2 h fits, a plain 3
+            let unit = if op == BinaryOp::IntDivide { Unit::default() } else { left.unit };
+            Ok(Value { number, unit })
+        }
+        BinaryOp::Multiply | BinaryOp::Divide => {
+            let sign = if op == BinaryOp::Multiply { 1 } else { -1 };
+            let mut unit = left.unit;
+            let mut right_number = right.number;
+
+            for &(part, power) in right.unit.parts() {
+                let part = match unit.part(part.dimension) {
+                    Some((existing, _)) if existing != part => {
+                        let from = Unit::default().with(part, power);
+                        let to = Unit::default().with(existing, power);
+                        right_number = convert_number(right_number, &from, &to, mode)?;
+                        existing
+                    }
+                    _ => part,
+                };
+                unit = unit.with(part, power * sign);
+            }
+
+            let number = number::binary(&left.number, op, &right_number, mode)?;
+            Ok(Value { number, unit })
+        }
+        BinaryOp::BitAnd | BinaryOp::BitOr | BinaryOp::BitXor | BinaryOp::ShiftLeft | BinaryOp::ShiftRight => {
+            if !left.unit.is_none() || !right.unit.is_none() {
+                return Err(CalcError::Unit(format!(
+                    "{} needs plain numbers, found {} {} {}",
+                    op.symbol(),
+                    describe(&left.unit),
+                    op.symbol(),
+                    describe(&right.unit)
+                )));
+            }
+            number::binary(&left.number, op, &right.number, mode).map(Value::plain)
+        }
+        BinaryOp::Power => {
+            if !right.unit.is_none() {
+                return Err(CalcError::Unit(format!("an exponent cannot have a unit, found {}", right.unit)));
+            }
+            let number = number::binary(&left.number, op, &right.number, mode)?;
+            if left.unit.is_none() {
+                return Ok(Value::plain(number));
+            }
+
+            let exponent = right.number.to_f64();
+            if exponent.fract() != 0.0 || exponent.abs() > i32::MAX as f64 {
+                return Err(CalcError::Unit(format!(
+                    "{} can only be raised to a whole power",
+                    left.unit
+                )));
+            }
+            Ok(Value {
+                number,
+                unit: left.unit.pow(exponent as i32),
+            })
+        }
+    }
+}
+
+/// `~value`, for plain whole numbers
+pub fn not(value: Value, mode: NumberMode) -> Result<Value, CalcError> {
+    if !value.unit.is_none() {
+        return Err(CalcError::Unit(format!("~ needs a plain number, found {}", value.unit)));
+    }
+    number::not(&value.number, mode).map(Value::plain)
+}
+
+/// `value in unit`
+pub fn convert(value: Value, target: &Unit, mode: NumberMode) -> Result<Value, CalcError> {
+    if !value.unit.same_dimensions(target) {
+        return Err(CalcError::Unit(format!(
+            "cannot convert {} to {}",
+            describe(&value.unit),
+            target
+        )));
+    }
+    Ok(Value {
+        number: convert_number(value.number, &value.unit, target, mode)?,
+        unit: target.clone(),
+    })
+}
+
+/// Calls a function, keeping the unit where that makes sense: abs, floor,
+/// ceil, round, min and max of lengths are lengths, and sqrt halves powers
+pub fn call(name: &str, args: Vec<Value>, angles: AngleMode, mode: NumberMode) -> Result<Value, CalcError> {
+    let unit = args[0].unit.clone();
+    if unit.is_none() && args.iter().all(|arg| arg.unit.is_none()) {
+        return number::call(name, &numbers(args), angles, mode).map(Value::plain);
+    }
+
+    let result_unit = match name {
+        "abs" | "floor" | "ceil" | "round" => unit.clone(),
+        "min" | "max" => {
+            let mut converted = Vec::with_capacity(args.len());
+            for arg in args {
+                if !arg.unit.same_dimensions(&unit) {
+                    return Err(CalcError::Unit(format!(
+                        "{} of incompatible units: {} and {}",
+                        name,
+                        unit,
+                        describe(&arg.unit)
+                    )));
+                }
+                converted.push(convert_number(arg.number, &arg.unit, &unit, mode)?);
+            }
+            let number = number::call(name, &converted, angles, mode)?;
+            return Ok(Value { number, unit });
+        }
+        "sqrt" if unit.parts().iter().all(|(_, power)| power % 2 == 0) => {
+            unit.parts().iter().fold(Unit::default(), |half, &(part, power)| half.with(part, power / 2))
+        }
+        _ => {
+            return Err(CalcError::Unit(format!(
+                "{} needs a plain number, found {}",
+                name,
+                unit
+            )))
+        }
+    };
+
+    let number = number::call(name, &numbers(args), angles, mode)?;
+    Ok(Value {
+        number,
+        unit: result_unit,
+    })
+}
+
+fn numbers(args: Vec<Value>) -> Vec<Number> {
+    args.into_iter().map(|arg| arg.number).collect()
+}
+
+// Re-expresses `number` from `from` units in `to` units of the same dimensions
+fn convert_number(number: Number, from: &Unit, to: &Unit, mode: NumberMode) -> Result<Number, CalcError> {
+    if from == to {
+        return Ok(number);
+    }
+    let in_base = number::binary(&number, BinaryOp::Multiply, &scale(from, mode)?, mode)?;
+    number::binary(&in_base, BinaryOp::Divide, &scale(to, mode)?, mode)
+}
+
+// How many base units (bytes, seconds, ...) one of `unit` is
+fn scale(unit: &Unit, mode: NumberMode) -> Result<Number, CalcError> {
+    let mut scale = Number::parse("1", mode)?;
+    for &(part, power) in unit.parts() {
+        let factor = Number::parse(part.factor, mode)?;
+        let op = if power > 0 { BinaryOp::Multiply } else { BinaryOp::Divide };
+        for _ in 0..power.unsigned_abs() {
+            scale = number::binary(&scale, op, &factor, mode)?;
+        }
+    }
+    Ok(scale)
+}
+
+fn describe(unit: &Unit) -> String {
+    if unit.is_none() {
+        "a plain number".to_string()
+    } else {
+        unit.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::lookup;
+
+    fn quantity(number: &str, unit: &[(&str, i32)], mode: NumberMode) -> Value {
+        Value {
+            number: Number::parse(number, mode).unwrap(),
+            unit: unit
+                .iter()
+                .fold(Unit::default(), |u, (name, power)| u.with(lookup(name).unwrap(), *power)),
+        }
+    }
+
+    fn q(number: &str, unit: &str) -> Value {
+        quantity(number, &[(unit, 1)], NumberMode::Float)
+    }
+
+    fn apply(left: Value, op: BinaryOp, right: Value) -> String {
+        binary(left, op, right, NumberMode::Float).unwrap().format_in_base(NumberMode::Float, 10)
+    }
+
+    #[test]
+    fn test_same_dimension_arithmetic() {
+        assert_eq!(apply(q("2", "h"), BinaryOp::Add, q("30", "min")), "2.5 h");
+        assert_eq!(apply(q("1", "km"), BinaryOp::Subtract, q("500", "m")), "0.5 km");
+        assert_eq!(apply(q("7", "h"), BinaryOp::IntDivide, q("120", "min")), "3");
+        assert_eq!(apply(q("7", "h"), BinaryOp::Modulo, q("2", "h")), "1 h");
+    }
+
+    #[test]
+    fn test_multiply_and_divide_combine_units() {
+        assert_eq!(apply(q("512", "MiB"), BinaryOp::Divide, q("2", "s")), "256 MiB/s");
+        assert_eq!(apply(q("1", "GiB"), BinaryOp::Divide, q("1", "MiB")), "1024");
+        assert_eq!(apply(q("3", "m"), BinaryOp::Multiply, q("200", "cm")), "6 m^2");
+        let rate = quantity("10", &[("MB", 1), ("s", -1)], NumberMode::Float);
+        assert_eq!(apply(rate, BinaryOp::Multiply, q("1", "min")), "600 MB");
+    }
+
+    #[test]
+    fn test_incompatible_units() {
+        let error = binary(q("1", "MiB"), BinaryOp::Add, q("1", "s"), NumberMode::Float).unwrap_err();
+        assert_eq!(error, CalcError::Unit("incompatible units: MiB + s".to_string()));
+
+        let plain = Value::plain(Number::Float(1.0));
+        let error = binary(q("1", "m"), BinaryOp::Subtract, plain, NumberMode::Float).unwrap_err();
+        assert_eq!(error, CalcError::Unit("incompatible units: m - a plain number".to_string()));
+
+        assert!(binary(q("2", "m"), BinaryOp::Power, q("2", "s"), NumberMode::Float).is_err());
+
+        let plain = Value::plain(Number::Float(1.0));
+        let error = binary(q("8", "B"), BinaryOp::BitAnd, plain, NumberMode::Float).unwrap_err();
+        assert_eq!(error, CalcError::Unit("& needs plain numbers, found B & a plain number".to_string()));
+        assert!(matches!(not(q("1", "B"), NumberMode::Float), Err(CalcError::Unit(_))));
+    }
+
+    #[test]
+    fn test_conversions() {
+        let to = |value: Value, unit: &[(&str, i32)], mode: NumberMode| {
+            let target = quantity("1", unit, mode).unit;
+            convert(value, &target, mode).unwrap().format_in_base(mode, 10)
+        };
+
+        assert_eq!(to(q("3", "h"), &[("min", 1)], NumberMode::Float), "180 min");
+        assert_eq!(
+            to(quantity("1", &[("GB", 1)], NumberMode::Rational), &[("GiB", 1)], NumberMode::Rational),
+            "1953125/2097152 GiB"
+        );
+        assert_eq!(
+            to(quantity("1", &[("GB", 1)], NumberMode::Decimal(4)), &[("GiB", 1)], NumberMode::Decimal(4)),
+            "0.9313 GiB"
+        );
+        let speed = quantity("36", &[("km", 1), ("h", -1)], NumberMode::Float);
+        assert_eq!(to(speed, &[("m", 1), ("s", -1)], NumberMode::Float), "10 m/s");
+
+        let error = convert(q("1", "MiB"), &q("1", "s").unit, NumberMode::Float).unwrap_err();
+        assert_eq!(error, CalcError::Unit("cannot convert MiB to s".to_string()));
+    }
+
+    #[test]
+    fn test_functions_with_units() {
+        let call_float = |name: &str, args: Vec<Value>| {
+            call(name, args, AngleMode::Radians, NumberMode::Float).map(|v| v.format_in_base(NumberMode::Float, 10))
+        };
+
+        assert_eq!(call_float("max", vec![q("90", "s"), q("1", "min")]), Ok("90 s".to_string()));
+        assert_eq!(call_float("abs", vec![q("-3", "kg")]), Ok("3 kg".to_string()));
+        assert_eq!(
+            call_float("sqrt", vec![quantity("16", &[("m", 2)], NumberMode::Float)]),
+            Ok("4 m".to_string())
+        );
+        assert!(matches!(call_float("sin", vec![q("1", "m")]), Err(CalcError::Unit(_))));
+        assert!(matches!(call_float("min", vec![q("1", "m"), q("1", "s")]), Err(CalcError::Unit(_))));
+    }
+}
@@ -1,4 +1,6 @@
 // Integration tests for the calculator project
+// The original tests pass argument arrays by reference
+#![allow(clippy::needless_borrows_for_generic_args)]
 use assert_cmd::Command;
 use predicates::prelude::*;
 
@@ -61,23 +63,70 @@ fn test_division_by_zero() {
     let mut cmd = Command::cargo_bin("calculator").unwrap();
     cmd.args(&["10", "/", "0"])
         .assert()
-        .failure();  // Should fail, don't require specific error message
+        .code(6)
+        .stderr(predicate::str::contains("division by zero"))
+        .stderr(predicate::str::contains("panicked").not());
 }
 
 #[test]
 fn test_invalid_number() {
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.args(["1.2.3", "+", "3"])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("invalid number '1.2.3'"));
+
+    // A word is read as a variable name, and none exist outside the REPL
     let mut cmd = Command::cargo_bin("calculator").unwrap();
     cmd.args(&["abc", "+", "3"])
         .assert()
-        .failure();  // Should fail, don't require specific error message
+        .code(5)
+        .stderr(predicate::str::contains("unknown variable 'abc'"));
 }
 
 #[test]
 fn test_invalid_operation() {
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.args(["5", "@", "3"])
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains("unknown operator '@'"));
+}
+
+#[test]
+fn test_power_modulo_and_integer_division() {
     let mut cmd = Command::cargo_bin("calculator").unwrap();
     cmd.args(&["5", "^", "3"])
         .assert()
-        .failure();  // Should fail, don't require specific error message
+        .success()
+        .stdout(predicate::str::contains("5 ^ 3 = 125"));
+
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.arg("17 // 5 + 17 % 5")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("= 5"));
+}
+
+#[test]
+fn test_functions_and_constants() {
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.arg("max(sqrt(16), 2) * round(e)")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("= 12"));
+
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.args(["--degrees", "sin(30) + acos(0)"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("= 90.5"));
+
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.arg("sqrt(-1)")
+        .assert()
+        .code(8)
+        .stderr(predicate::str::contains("domain error"));
 }
 
 #[test]
@@ -85,7 +134,40 @@ fn test_wrong_number_of_arguments() {
     let mut cmd = Command::cargo_bin("calculator").unwrap();
     cmd.args(&["5", "+"])
         .assert()
-        .failure();  // Should fail, don't require specific error message
+        .code(2)
+        .stderr(predicate::str::contains("at column 4"));
+}
+
+#[test]
+fn test_overflow() {
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.arg("1e308 * 10")
+        .assert()
+        .code(7)
+        .stderr(predicate::str::contains("number too large"));
+}
+
+#[test]
+fn test_unknown_option() {
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.args(["--rationl", "1/3"])
+        .assert()
+        .code(1)
+        .stdout("")
+        .stderr(predicate::str::contains("unknown option --rationl").and(predicate::str::contains("Usage:")));
+
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Usage: calculator"));
+
+    // After a bare --, everything is the expression
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.args(["--", "--5", "+", "--rational"])
+        .assert()
+        .code(5)
+        .stderr(predicate::str::contains("unknown variable 'rational'"));
 }
 
 #[test]
@@ -121,4 +203,184 @@ fn test_very_small_numbers() {
         .assert()
         .success()
         .stdout(predicate::str::contains("0.0001 * 10000 = 1"));
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_expression_with_precedence_and_parentheses() {
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.arg("2 * (3 + 4) / 7")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("2 * (3 + 4) / 7 = 2"));
+
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.arg("1 + 2 * 3 - -4")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("= 11"));
+}
+
+#[test]
+fn test_parse_error_points_at_column() {
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.arg("2 * (3 + ) / 7")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("found ')' at column 10"))
+        .stderr(predicate::str::contains("\n           ^"));
+}
+
+#[test]
+fn test_interactive_session() {
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.write_stdin("x = 3.5\nx * 2\n2 +\nans + 1\n:vars\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("x = 3.5"))
+        .stdout(predicate::str::contains("7\n"))
+        .stdout(predicate::str::contains("8\n"))
+        .stdout(predicate::str::contains("ans = 8\nx = 3.5"))
+        .stderr(predicate::str::contains("at column 4"));
+}
+
+#[test]
+fn test_exact_number_modes() {
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.args(["--rational", "1/3 + 1/6"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1/3 + 1/6 = 1/2"));
+
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.args(["--rational", "2 ^ 100 + 1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("= 1267650600228229401496703205377"));
+
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.args(["--decimal", "0.1 + 0.2"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("0.1 + 0.2 = 0.3\n"));
+
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.args(["--decimal=30", "1 / 7"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("= 0.142857142857142857142857142857"));
+
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.args(["--decimal=many", "1 / 7"]).assert().code(1);
+}
+
+#[test]
+fn test_units() {
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.arg("512 MiB / 2 s")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("512 MiB / 2 s = 256 MiB/s"));
+
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.args(["3", "h", "in", "min"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("3 h in min = 180 min"));
+
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.args(["--rational", "1 GB to GiB"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("= 1953125/2097152 GiB"));
+
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.arg("1 MiB + 1 s")
+        .assert()
+        .code(9)
+        .stderr(predicate::str::contains("incompatible units: MiB + s"));
+
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.arg("3 h in parsecs")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("unknown unit 'parsecs'"));
+}
+
+#[test]
+fn test_batch_from_stdin() {
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.arg("--batch")
+        .write_stdin("x = 2\n\n# comment\nx ^ 10\n")
+        .assert()
+        .success()
+        .stdout("2\n1024\n");
+
+    // Failed lines are reported and skipped, and the run still fails
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.args(["--batch", "--format=csv"])
+        .write_stdin("1 / 0\nmax(1, 2)\n2 +\n")
+        .assert()
+        .code(6)
+        .stdout("line,expression,result\n2,\"max(1, 2)\",2\n")
+        .stderr(predicate::str::contains("line 1: Error: division by zero"))
+        .stderr(predicate::str::contains("line 3: Error: expected a number"));
+
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.args(["--batch", "--fail-fast", "--format=json"])
+        .write_stdin("1 + 1\nnope\n3\n")
+        .assert()
+        .code(5)
+        .stdout("{\"line\":1,\"expression\":\"1 + 1\",\"result\":\"2\"}\n");
+}
+
+#[test]
+fn test_batch_from_file() {
+    let path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("batch.txt");
+    std::fs::write(&path, "1/3 + 1/6\n512 MiB / 2 s\n").unwrap();
+
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.arg("--rational")
+        .arg(format!("--file={}", path.display()))
+        .assert()
+        .success()
+        .stdout("1/2\n256 MiB/s\n");
+
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.arg("--file=does/not/exist.txt")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("cannot read does/not/exist.txt"));
+
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.args(["--format=csv", "1 + 1"]).assert().code(1);
+}
+
+#[test]
+fn test_programmer_mode() {
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.args(["--uint=8", "--base=16", "~0x0f & 0b1100_0000 | 1 << 2"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("= 0xc4"));
+
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.args(["--int=32", "0x7fffffff + 1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("= -2147483648"));
+
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.args(["--base=2", "0xa xor 0b11"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("= 0b1001"));
+
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.arg("1.5 & 1")
+        .assert()
+        .code(8)
+        .stderr(predicate::str::contains("bitwise operators need whole numbers"));
+
+    let mut cmd = Command::cargo_bin("calculator").unwrap();
+    cmd.args(["--int=12", "1"]).assert().code(1);
+}
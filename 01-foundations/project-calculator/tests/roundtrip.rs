@@ -0,0 +1,123 @@
+// Property tests for the printer: any expression tree, printed and parsed
+// again, comes back unchanged
+
+use calculator::parser::RESERVED;
+use calculator::units::{self, Dimension, Unit};
+use calculator::{parse, Ast, BinaryOp, Expr};
+use proptest::prelude::*;
+use proptest::sample::select;
+
+fn literal() -> impl Strategy<Value = String> {
+    prop_oneof![
+        "[0-9]{1,6}",
+        "[0-9]{1,3}\\.[0-9]{1,3}",
+        "\\.[0-9]{1,3}",
+        "[0-9]{1,3}[eE][+-]?[0-9]{1,2}",
+        "0x[0-9a-f]{1,6}",
+        "0o[0-7]{1,4}",
+        "0b[01]{1,8}",
+    ]
+}
+
+// Variable names, including some that are also unit or function names
+fn name() -> impl Strategy<Value = String> {
+    prop_oneof![
+        "[a-z][a-z0-9_]{0,4}".prop_filter("reserved", |name| !RESERVED.contains(&name.as_str())),
+        select(vec!["s", "m", "kg", "min", "MiB", "sqrt", "pi"]).prop_map(str::to_string),
+    ]
+}
+
+// Up to one unit per dimension, each with a power from -3 to 3
+fn unit() -> impl Strategy<Value = Unit> {
+    let dimension = |dimension: Dimension| {
+        let names: Vec<&str> = units::UNITS
+            .iter()
+            .filter(|unit| unit.dimension == dimension)
+            .map(|unit| unit.name)
+            .collect();
+        proptest::option::of((select(names), prop_oneof![-3..=-1, 1..=3]))
+    };
+    (
+        dimension(Dimension::Data),
+        dimension(Dimension::Time),
+        dimension(Dimension::Length),
+        dimension(Dimension::Mass),
+    )
+        .prop_map(|(data, time, length, mass)| {
+            [data, time, length, mass]
+                .into_iter()
+                .flatten()
+                .fold(Unit::default(), |unit, (name, power)| unit.with(units::lookup(name).unwrap(), power))
+        })
+        .prop_filter("a unit", |unit| !unit.is_none())
+}
+
+fn binary_op() -> impl Strategy<Value = BinaryOp> {
+    select(vec![
+        BinaryOp::Add,
+        BinaryOp::Subtract,
+        BinaryOp::Multiply,
+        BinaryOp::Divide,
+        BinaryOp::IntDivide,
+        BinaryOp::Modulo,
+        BinaryOp::Power,
+        BinaryOp::BitAnd,
+        BinaryOp::BitOr,
+        BinaryOp::BitXor,
+        BinaryOp::ShiftLeft,
+        BinaryOp::ShiftRight,
+    ])
+}
+
+fn expr() -> impl Strategy<Value = Expr> {
+    let leaf = prop_oneof![
+        literal().prop_map(Expr::Number),
+        name().prop_map(Expr::Variable),
+        (literal(), unit()).prop_map(|(number, unit)| Expr::Quantity { number, unit }),
+    ];
+
+    leaf.prop_recursive(5, 48, 3, |inner| {
+        prop_oneof![
+            inner.clone().prop_map(|e| Expr::Negate(Box::new(e))),
+            inner.clone().prop_map(|e| Expr::Not(Box::new(e))),
+            (binary_op(), inner.clone(), inner.clone()).prop_map(|(op, left, right)| Expr::Binary {
+                op,
+                left: Box::new(left),
+                right: Box::new(right),
+            }),
+            (select(vec!["sqrt", "abs", "sin", "round"]), inner.clone()).prop_map(|(name, arg)| Expr::Call {
+                name: name.to_string(),
+                args: vec![arg],
+            }),
+            (select(vec!["min", "max"]), prop::collection::vec(inner.clone(), 1..4)).prop_map(|(name, args)| {
+                Expr::Call {
+                    name: name.to_string(),
+                    args,
+                }
+            }),
+            (inner, unit()).prop_map(|(value, unit)| Expr::Convert {
+                value: Box::new(value),
+                unit,
+            }),
+        ]
+    })
+}
+
+fn ast() -> impl Strategy<Value = Ast> {
+    prop_oneof![
+        expr().prop_map(Ast::Expr),
+        (name().prop_filter("reserved", |name| name != "pi"), expr())
+            .prop_map(|(name, value)| Ast::Assign { name, value }),
+    ]
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(2000))]
+
+    #[test]
+    fn printing_then_parsing_gives_the_same_tree(ast in ast()) {
+        let printed = ast.to_string();
+        let reparsed = parse(&printed);
+        prop_assert_eq!(reparsed, Ok(ast), "printed as {}", printed);
+    }
+}
@@ -0,0 +1,224 @@
+// Allocator - counts the allocations Rust really makes
+//
+// Built with `--features count-allocations`, every heap allocation in the
+// program goes through CountingAllocator, which hands the work to the
+// system allocator and counts it. A Snapshot taken before a block of code
+// tells what that block did:
+//
+//   let snapshot = Snapshot::take();
+//   let s = String::from("hello");
+//   let stats = snapshot.since();   // 1 allocation of 5 bytes
+//
+// The counts are kept per thread, so a snapshot only sees its own thread:
+// other threads (or tests running in parallel) don't disturb it.
+//
+// Without the feature the program uses the system allocator directly and
+// nothing is counted.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::fmt;
+
+#[cfg(feature = "count-allocations")]
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Whether this build counts real allocations
+pub fn is_enabled() -> bool {
+    cfg!(feature = "count-allocations")
+}
+
+// Plain Cells with const initializers: reading them never allocates, which
+// matters inside an allocator
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    static DEALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    static REALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    static BYTES_ALLOCATED: Cell<usize> = const { Cell::new(0) };
+    static BYTES_DEALLOCATED: Cell<usize> = const { Cell::new(0) };
+}
+
+#[cfg_attr(not(feature = "count-allocations"), allow(dead_code))]
+fn add(counter: &'static std::thread::LocalKey<Cell<usize>>, amount: usize) {
+    // try_with: a thread may still free memory while it is shutting down
+    let _ = counter.try_with(|count| count.set(count.get() + amount));
+}
+
+fn read(counter: &'static std::thread::LocalKey<Cell<usize>>) -> usize {
+    counter.try_with(Cell::get).unwrap_or(0)
+}
+
+/// The system allocator, counting as it goes. Only installed with the
+/// count-allocations feature.
+#[cfg_attr(not(feature = "count-allocations"), allow(dead_code))]
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let pointer = System.alloc(layout);
+        if !pointer.is_null() {
+            add(&ALLOCATIONS, 1);
+            add(&BYTES_ALLOCATED, layout.size());
+        }
+        pointer
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let pointer = System.alloc_zeroed(layout);
+        if !pointer.is_null() {
+            add(&ALLOCATIONS, 1);
+            add(&BYTES_ALLOCATED, layout.size());
+        }
+        pointer
+    }
+
+    unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout) {
+        System.dealloc(pointer, layout);
+        add(&DEALLOCATIONS, 1);
+        add(&BYTES_DEALLOCATED, layout.size());
+    }
+
+    // A reallocation frees the old size and allocates the new one, so the
+    // live byte count stays right whether it grows or shrinks
+    unsafe fn realloc(&self, pointer: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_pointer = System.realloc(pointer, layout, new_size);
+        if !new_pointer.is_null() {
+            add(&REALLOCATIONS, 1);
+            add(&BYTES_DEALLOCATED, layout.size());
+            add(&BYTES_ALLOCATED, new_size);
+        }
+        new_pointer
+    }
+}
+
+/// Allocation counts, either since the thread started or for one block
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AllocationStats {
+    pub allocations: usize,
+    pub deallocations: usize,
+    pub reallocations: usize,
+    pub bytes_allocated: usize,
+    pub bytes_deallocated: usize,
+}
+
+impl AllocationStats {
+    /// Everything this thread has counted so far
+    pub fn current() -> Self {
+        AllocationStats {
+            allocations: read(&ALLOCATIONS),
+            deallocations: read(&DEALLOCATIONS),
+            reallocations: read(&REALLOCATIONS),
+            bytes_allocated: read(&BYTES_ALLOCATED),
+            bytes_deallocated: read(&BYTES_DEALLOCATED),
+        }
+    }
+
+    /// Bytes allocated minus bytes freed. Negative when a block freed
+    /// memory that was allocated before it started.
+    pub fn live_bytes(&self) -> isize {
+        self.bytes_allocated as isize - self.bytes_deallocated as isize
+    }
+
+    fn since(&self, start: &AllocationStats) -> Self {
+        AllocationStats {
+            allocations: self.allocations - start.allocations,
+            deallocations: self.deallocations - start.deallocations,
+            reallocations: self.reallocations - start.reallocations,
+            bytes_allocated: self.bytes_allocated - start.bytes_allocated,
+            bytes_deallocated: self.bytes_deallocated - start.bytes_deallocated,
+        }
+    }
+}
+
+impl fmt::Display for AllocationStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} allocations ({} bytes), {} frees ({} bytes), {} reallocations, {} bytes live",
+            self.allocations,
+            self.bytes_allocated,
+            self.deallocations,
+            self.bytes_deallocated,
+            self.reallocations,
+            self.live_bytes()
+        )
+    }
+}
+
+/// The counts at one moment, to compare against later
+pub struct Snapshot {
+    start: AllocationStats,
+}
+
+impl Snapshot {
+    pub fn take() -> Self {
+        Snapshot {
+            start: AllocationStats::current(),
+        }
+    }
+
+    /// What this thread has done since the snapshot was taken
+    pub fn since(&self) -> AllocationStats {
+        AllocationStats::current().since(&self.start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_allocations_and_frees() {
+        let layout = Layout::from_size_align(32, 8).unwrap();
+        let snapshot = Snapshot::take();
+
+        unsafe {
+            let pointer = CountingAllocator.alloc(layout);
+            assert!(!pointer.is_null());
+            let pointer = CountingAllocator.realloc(pointer, layout, 64);
+            assert!(!pointer.is_null());
+            CountingAllocator.dealloc(pointer, Layout::from_size_align(64, 8).unwrap());
+        }
+
+        let stats = snapshot.since();
+        assert_eq!(
+            stats,
+            AllocationStats {
+                allocations: 1,
+                deallocations: 1,
+                reallocations: 1,
+                bytes_allocated: 96,
+                bytes_deallocated: 96,
+            }
+        );
+        assert_eq!(stats.live_bytes(), 0);
+    }
+
+    #[test]
+    fn test_display() {
+        let stats = AllocationStats {
+            allocations: 2,
+            deallocations: 1,
+            reallocations: 0,
+            bytes_allocated: 24,
+            bytes_deallocated: 8,
+        };
+        assert_eq!(
+            stats.to_string(),
+            "2 allocations (24 bytes), 1 frees (8 bytes), 0 reallocations, 16 bytes live"
+        );
+    }
+
+    #[cfg(feature = "count-allocations")]
+    #[test]
+    fn test_snapshot_sees_real_allocations() {
+        let snapshot = Snapshot::take();
+        let s = String::from("hello");
+        let moved = s;
+        let stats = snapshot.since();
+
+        assert_eq!(stats.allocations, 1);
+        assert_eq!(stats.bytes_allocated, 5);
+        drop(moved);
+    }
+}
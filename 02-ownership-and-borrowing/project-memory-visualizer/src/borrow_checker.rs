@@ -0,0 +1,390 @@
+// Borrow Checker - the ownership rules, applied to a timeline
+//
+// Each operation is checked against everything that happened before it.
+// Like the real borrow checker, a borrow lasts until its last use, not to
+// the end of a scope: taking `&mut s` while `r = &s` exists is only an error
+// if `r` is used again afterwards. So conflicts are reported when the
+// earlier borrow is used, pointing at every step involved:
+//
+//   error[E0502]: cannot borrow `s` as mutable because it is also borrowed as immutable
+//     step 2: `r` borrows `s` here
+//     step 3: `m` borrows `s` mutably here
+//     step 4: `r` is used here, so the first borrow is still needed
+//
+// Three rules are checked:
+//   use after move         a moved (or dropped) value is used, moved, borrowed
+//                          or dropped again
+//   aliased &mut           a mutable borrow overlaps another borrow or use
+//   borrow outliving owner the owner is moved or dropped while a borrow is
+//                          still needed
+
+use crate::memory_tracker::{MemoryOperation, Operation};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    UseAfterMove,
+    AliasedMutableBorrow,
+    BorrowOutlivesOwner,
+}
+
+/// A step involved in a violation, and what happened there
+#[derive(Debug, Clone, PartialEq)]
+pub struct Note {
+    pub step: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub rule: Rule,
+    /// The rustc error code for the same mistake
+    pub code: &'static str,
+    pub message: String,
+    /// The step that broke the rule
+    pub step: usize,
+    /// Every step involved, in order
+    pub notes: Vec<Note>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "error[{}]: {}", self.code, self.message)?;
+        for note in &self.notes {
+            write!(f, "\n  step {}: {}", note.step, note.message)?;
+        }
+        Ok(())
+    }
+}
+
+fn note(step: usize, message: String) -> Note {
+    Note { step, message }
+}
+
+// "from -> to" for moves, borrows and clones, or just the name
+fn names(operation: &MemoryOperation) -> (&str, &str) {
+    operation
+        .location
+        .split_once(" -> ")
+        .unwrap_or((operation.location.as_str(), ""))
+}
+
+// `numbers[0]` borrows from `numbers`, `node.next` belongs to `node`
+fn owner_of(place: &str) -> &str {
+    place.split(['[', '.']).next().unwrap_or(place)
+}
+
+fn is_mutable(operation: &MemoryOperation) -> bool {
+    operation.operation == Operation::MutableBorrow
+}
+
+/// Checks every operation of a timeline
+pub fn check(operations: &[MemoryOperation]) -> Vec<Diagnostic> {
+    (0..operations.len())
+        .filter_map(|i| check_operation(&operations[..i], &operations[i]))
+        .collect()
+}
+
+/// Checks one operation against the operations before it
+pub fn check_operation(history: &[MemoryOperation], operation: &MemoryOperation) -> Option<Diagnostic> {
+    let (from, _) = names(operation);
+    match operation.operation {
+        Operation::Move | Operation::Clone | Operation::Use => {
+            use_after_move(history, from, operation).or_else(|| stale_borrow(history, from, operation))
+        }
+        Operation::Borrow | Operation::MutableBorrow => {
+            let (_, place) = names(operation);
+            use_after_move(history, owner_of(place), operation)
+        }
+        // Freeing a moved or dropped value would free it twice
+        Operation::Deallocate | Operation::Drop => use_after_move(history, from, operation),
+        // Storing a pointer uses both the value it is stored in and its target
+        Operation::Link => {
+            let (_, target) = names(operation);
+            use_after_move(history, owner_of(from), operation).or_else(|| use_after_move(history, target, operation))
+        }
+        Operation::Allocate | Operation::EnterScope | Operation::ExitScope => None,
+    }
+}
+
+// The last operation that created or ended `name`, and its index
+fn last_change<'a>(history: &'a [MemoryOperation], name: &str) -> Option<(usize, &'a MemoryOperation)> {
+    history.iter().enumerate().rev().find(|(_, earlier)| {
+        let (from, to) = names(earlier);
+        match earlier.operation {
+            Operation::Allocate | Operation::Deallocate | Operation::Drop => from == name,
+            Operation::Move => from == name || to == name,
+            // A borrow creates the reference, a clone creates the copy
+            Operation::Borrow | Operation::MutableBorrow => from == name,
+            Operation::Clone => to == name,
+            Operation::Use | Operation::EnterScope | Operation::ExitScope | Operation::Link => false,
+        }
+    })
+}
+
+// The borrow behind the reference `name`, and its index. A moved reference
+// carries its borrow: after `r -> r2`, r2 holds what r borrowed.
+fn borrow_behind<'a>(history: &'a [MemoryOperation], name: &str) -> Option<(usize, &'a MemoryOperation)> {
+    let (index, change) = last_change(history, name)?;
+    match change.operation {
+        Operation::Borrow | Operation::MutableBorrow => Some((index, change)),
+        Operation::Move if names(change).1 == name => borrow_behind(&history[..index], names(change).0),
+        _ => None,
+    }
+}
+
+fn use_after_move(history: &[MemoryOperation], name: &str, operation: &MemoryOperation) -> Option<Diagnostic> {
+    let (_, change) = last_change(history, name)?;
+    let (from, to) = names(change);
+    let action = match operation.operation {
+        Operation::Move => "moved again here",
+        Operation::Clone => "cloned here",
+        Operation::Borrow | Operation::MutableBorrow => "borrowed here",
+        Operation::Deallocate | Operation::Drop => "dropped here",
+        _ => "used here",
+    };
+
+    let (message, earlier) = match change.operation {
+        Operation::Move if from == name => (
+            format!("use of moved value: `{}`", name),
+            format!("`{}` is moved to `{}` here", name, to),
+        ),
+        Operation::Deallocate | Operation::Drop => (
+            format!("use of dropped value: `{}`", name),
+            format!("`{}` is dropped here", name),
+        ),
+        _ => return None,
+    };
+
+    Some(Diagnostic {
+        rule: Rule::UseAfterMove,
+        code: "E0382",
+        message,
+        step: operation.step,
+        notes: vec![
+            note(change.step, earlier),
+            note(operation.step, format!("`{}` is {} after that", name, action)),
+        ],
+    })
+}
+
+// Using a reference after something happened to its owner that the
+// reference should have prevented
+fn stale_borrow(history: &[MemoryOperation], name: &str, operation: &MemoryOperation) -> Option<Diagnostic> {
+    let (borrow_index, borrow) = borrow_behind(history, name)?;
+    let (borrower, place) = names(borrow);
+    let owner = owner_of(place);
+    let mutable = is_mutable(borrow);
+    let first = if mutable {
+        note(borrow.step, format!("`{}` borrows `{}` mutably here", borrower, owner))
+    } else {
+        note(borrow.step, format!("`{}` borrows `{}` here", borrower, owner))
+    };
+    let still_needed = note(
+        operation.step,
+        format!("`{}` is used here, so the first borrow is still needed", name),
+    );
+
+    for later in &history[borrow_index + 1..] {
+        let (from, to) = names(later);
+        let (rule, code, message, what) = match later.operation {
+            Operation::Borrow | Operation::MutableBorrow
+                if owner_of(to) == owner && from != borrower && (mutable || is_mutable(later)) =>
+            {
+                let (code, message) = match (mutable, is_mutable(later)) {
+                    (true, true) => ("E0499", format!("cannot borrow `{}` as mutable more than once at a time", owner)),
+                    (false, _) => ("E0502", format!("cannot borrow `{}` as mutable because it is also borrowed as immutable", owner)),
+                    (true, false) => ("E0502", format!("cannot borrow `{}` as immutable because it is also borrowed as mutable", owner)),
+                };
+                let what = if is_mutable(later) {
+                    format!("`{}` borrows `{}` mutably here", from, owner)
+                } else {
+                    format!("`{}` borrows `{}` here", from, owner)
+                };
+                (Rule::AliasedMutableBorrow, code, message, what)
+            }
+            Operation::Use | Operation::Clone if mutable && from == owner => (
+                Rule::AliasedMutableBorrow,
+                "E0503",
+                format!("cannot use `{}` because it was mutably borrowed", owner),
+                format!("`{}` is used here", owner),
+            ),
+            Operation::Move if from == owner => (
+                Rule::BorrowOutlivesOwner,
+                "E0505",
+                format!("cannot move out of `{}` because it is borrowed", owner),
+                format!("`{}` is moved to `{}` here", owner, to),
+            ),
+            Operation::Deallocate | Operation::Drop if from == owner => (
+                Rule::BorrowOutlivesOwner,
+                "E0597",
+                format!("`{}` does not live long enough", owner),
+                format!("`{}` is dropped here while still borrowed", owner),
+            ),
+            _ => continue,
+        };
+
+        return Some(Diagnostic {
+            rule,
+            code,
+            message,
+            step: operation.step,
+            notes: vec![first, note(later.step, what), still_needed],
+        });
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timeline(steps: &[(Operation, &str)]) -> Vec<MemoryOperation> {
+        steps
+            .iter()
+            .enumerate()
+            .map(|(i, (operation, location))| MemoryOperation {
+                step: i + 1,
+                operation: *operation,
+                location: location.to_string(),
+                value_type: "String".to_string(),
+                size: 0,
+            })
+            .collect()
+    }
+
+    fn rules(steps: &[(Operation, &str)]) -> Vec<(Rule, &'static str, usize)> {
+        check(&timeline(steps))
+            .into_iter()
+            .map(|d| (d.rule, d.code, d.step))
+            .collect()
+    }
+
+    #[test]
+    fn test_use_after_move() {
+        let diagnostics = check(&timeline(&[
+            (Operation::Allocate, "s1"),
+            (Operation::Move, "s1 -> s2"),
+            (Operation::Use, "s2"),
+            (Operation::Use, "s1"),
+        ]));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error[E0382]: use of moved value: `s1`\n  step 2: `s1` is moved to `s2` here\n  step 4: `s1` is used here after that"
+        );
+
+        // Borrowing or moving a moved value is also a use, but a new value
+        // under the old name is fine
+        assert_eq!(
+            rules(&[
+                (Operation::Allocate, "s1"),
+                (Operation::Move, "s1 -> s2"),
+                (Operation::Borrow, "r -> s1"),
+                (Operation::Move, "s1 -> s3"),
+                (Operation::Allocate, "s1"),
+                (Operation::Move, "s1 -> s4"),
+            ]),
+            vec![(Rule::UseAfterMove, "E0382", 3), (Rule::UseAfterMove, "E0382", 4)]
+        );
+        assert_eq!(
+            rules(&[
+                (Operation::Allocate, "s"),
+                (Operation::Move, "s -> t"),
+                (Operation::Deallocate, "t"),
+                (Operation::Deallocate, "s"),
+                (Operation::Deallocate, "t"),
+            ]),
+            vec![(Rule::UseAfterMove, "E0382", 4), (Rule::UseAfterMove, "E0382", 5)]
+        );
+    }
+
+    #[test]
+    fn test_aliased_mutable_borrows() {
+        // r is not used after m is taken, so the borrows don't overlap
+        assert!(rules(&[
+            (Operation::Allocate, "s"),
+            (Operation::Borrow, "r -> s"),
+            (Operation::Use, "r"),
+            (Operation::MutableBorrow, "m -> s"),
+            (Operation::Use, "m"),
+        ])
+        .is_empty());
+
+        assert_eq!(
+            rules(&[
+                (Operation::Allocate, "s"),
+                (Operation::Borrow, "r -> s"),
+                (Operation::MutableBorrow, "m -> s"),
+                (Operation::Use, "r"),
+            ]),
+            vec![(Rule::AliasedMutableBorrow, "E0502", 4)]
+        );
+        assert_eq!(
+            rules(&[
+                (Operation::Allocate, "v"),
+                (Operation::MutableBorrow, "a -> v[0]"),
+                (Operation::MutableBorrow, "b -> v[1]"),
+                (Operation::Use, "a"),
+            ]),
+            vec![(Rule::AliasedMutableBorrow, "E0499", 4)]
+        );
+        assert_eq!(
+            rules(&[
+                (Operation::Allocate, "s"),
+                (Operation::MutableBorrow, "m -> s"),
+                (Operation::Use, "s"),
+                (Operation::Use, "m"),
+            ]),
+            vec![(Rule::AliasedMutableBorrow, "E0503", 4)]
+        );
+
+        // Shared borrows never conflict with each other
+        assert!(rules(&[
+            (Operation::Allocate, "s"),
+            (Operation::Borrow, "r1 -> s"),
+            (Operation::Borrow, "r2 -> s"),
+            (Operation::Use, "s"),
+            (Operation::Use, "r1"),
+        ])
+        .is_empty());
+    }
+
+    #[test]
+    fn test_borrow_outliving_owner() {
+        let diagnostics = check(&timeline(&[
+            (Operation::Allocate, "temp"),
+            (Operation::Borrow, "r -> temp"),
+            (Operation::Deallocate, "temp"),
+            (Operation::Use, "r"),
+        ]));
+        assert_eq!(diagnostics[0].rule, Rule::BorrowOutlivesOwner);
+        assert_eq!(diagnostics[0].code, "E0597");
+        let steps: Vec<usize> = diagnostics[0].notes.iter().map(|n| n.step).collect();
+        assert_eq!(steps, vec![2, 3, 4]);
+
+        assert_eq!(
+            rules(&[
+                (Operation::Allocate, "s"),
+                (Operation::Borrow, "r -> s"),
+                (Operation::Move, "s -> t"),
+                (Operation::Use, "r"),
+            ]),
+            vec![(Rule::BorrowOutlivesOwner, "E0505", 4)]
+        );
+
+        // Moving the reference hands its borrow to the new name
+        let diagnostics = check(&timeline(&[
+            (Operation::Allocate, "s"),
+            (Operation::Borrow, "r -> s"),
+            (Operation::Move, "r -> r2"),
+            (Operation::Deallocate, "s"),
+            (Operation::Use, "r2"),
+        ]));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error[E0597]: `s` does not live long enough\n  step 2: `r` borrows `s` here\n  step 4: `s` is dropped here while still borrowed\n  step 5: `r2` is used here, so the first borrow is still needed"
+        );
+    }
+}
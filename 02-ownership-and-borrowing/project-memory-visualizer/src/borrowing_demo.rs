@@ -3,7 +3,9 @@
 // Your task: Make all the borrowing examples compile and work correctly
 // This demonstrates borrowing and reference concepts from Module 02
 
+use crate::allocator::Snapshot;
 use crate::memory_tracker::MemoryTracker;
+use crate::visualizer::MemoryVisualizer;
 use colored::*;
 
 pub fn run_demonstrations(tracker: &mut MemoryTracker) {
@@ -32,20 +34,20 @@ fn demo_immutable_borrowing(tracker: &mut MemoryTracker) {
     tracker.track_allocation("data", "String", data.len());
     
     // TODO: Create an immutable reference to data
-    let reference = /* TODO: borrow data */;
+    // let reference = /* TODO: borrow data */;
     tracker.track_borrow("reference", "data", false);
     
     // TODO: Use the reference to print the data
     println!("Original: {}", data);
-    println!("Reference: {}", /* TODO: use reference */);
+    // println!("Reference: {}", /* TODO: use reference */);
     
     // QUESTION: Can we modify data through the reference?
     // TODO: Try uncommenting this line and see what happens:
     // reference.push_str(" modified");  // Should this work?
     
     // TODO: Calculate the length using the reference
-    let length = /* TODO: get length through reference */;
-    println!("Length through reference: {}", length);
+    // let length = /* TODO: get length through reference */;
+    // println!("Length through reference: {}", length);
 }
 
 fn demo_mutable_borrowing(tracker: &mut MemoryTracker) {
@@ -56,7 +58,7 @@ fn demo_mutable_borrowing(tracker: &mut MemoryTracker) {
     tracker.track_allocation("data", "String", data.len());
     
     // TODO: Create a mutable reference
-    let mut_ref = /* TODO: create mutable reference */;
+    // let mut_ref = /* TODO: create mutable reference */;
     tracker.track_borrow("mut_ref", "data", true);
     
     // TODO: Modify data through the mutable reference
@@ -121,18 +123,20 @@ fn demo_multiple_references(tracker: &mut MemoryTracker) {
     // References go out of scope here...
     
     // Now we can modify
-    numbers.push(6);
+    let snapshot = Snapshot::take();
+    numbers.push(6);  // The Vec is full, so it grows into a bigger buffer
+    tracker.record_measurement("pushing onto a full Vec", &snapshot);
     println!("Modified vector: {:?}", numbers);
     
     // TODO: Demonstrate slice borrowing
-    let slice = /* TODO: borrow a slice of numbers */;
-    println!("Slice: {:?}", slice);
+    // let slice = /* TODO: borrow a slice of numbers */;
+    // println!("Slice: {:?}", slice);
 }
 
 fn demo_reference_scope(tracker: &mut MemoryTracker) {
     println!("Demonstrating reference scope and lifetimes...");
     
-    let mut data = String::from("lifetime demo");
+    let data = String::from("lifetime demo");
     tracker.track_allocation("data", "String", data.len());
     
     let reference;
@@ -159,26 +163,28 @@ fn demo_reference_scope(tracker: &mut MemoryTracker) {
 }
 
 // TODO: Fix this function that tries to return a reference
-fn get_string_reference() -> &str {  // FIXME: Missing lifetime parameter
-    let s = String::from("local string");
-    &s  // FIXME: Returning reference to local variable
-}
+// fn get_string_reference() -> &str {  // FIXME: Missing lifetime parameter
+//     let s = String::from("local string");
+//     &s  // FIXME: Returning reference to local variable
+// }
 
 // TODO: Fix this function to work correctly
-fn get_string_correctly() -> String {
-    // HINT: Return the owned value, not a reference
-    let s = String::from("owned string");
-    // TODO: Return s in a way that transfers ownership
-}
+// fn get_string_correctly() -> String {
+//     // HINT: Return the owned value, not a reference
+//     let s = String::from("owned string");
+//     // TODO: Return s in a way that transfers ownership
+// }
 
 // TODO: This function should borrow rather than take ownership
+#[allow(dead_code)] // Unused until the exercises are done
 fn calculate_length(s: String) -> usize {  // FIXME: Takes ownership
     s.len()
 }  // s is dropped here - wasteful!
 
-// TODO: Fix the function to borrow instead
-fn calculate_length_correctly(/* TODO: parameter */) -> usize {
-    // TODO: Implementation
+// Borrows, so the test below can use the string afterwards
+#[allow(dead_code)]
+fn calculate_length_correctly(s: &str) -> usize {
+    s.len()
 }
 
 pub fn interactive_demo(tracker: &mut MemoryTracker) {
@@ -189,11 +195,15 @@ pub fn interactive_demo(tracker: &mut MemoryTracker) {
     println!("  create <name>     - Create a new String");
     println!("  borrow <name>     - Create immutable reference");
     println!("  borrow_mut <name> - Create mutable reference");
-    println!("  modify <ref>      - Modify through mutable reference");
-    println!("  print <name>      - Print value or reference");
+    println!("  modify <name>     - Modify through the mutable reference &mut <name>");
+    println!("  print <name>      - Print a value");
+    println!("  use <reference>   - Use a reference, e.g. use &name");
+    println!("  drop <name>       - Drop a value");
+    println!("Errors the compiler would report are explained as they happen.");
     println!("  back              - Return to main menu");
     
     let mut values: std::collections::HashMap<String, String> = std::collections::HashMap::new();
+    let visualizer = MemoryVisualizer::new();
     
     loop {
         print!("borrowing> ");
@@ -201,7 +211,7 @@ pub fn interactive_demo(tracker: &mut MemoryTracker) {
         
         let mut input = String::new();
         io::stdin().read_line(&mut input).unwrap();
-        let parts: Vec<&str> = input.trim().split_whitespace().collect();
+        let parts: Vec<&str> = input.split_whitespace().collect();
         
         match parts.as_slice() {
             ["create", name] => {
@@ -213,7 +223,7 @@ pub fn interactive_demo(tracker: &mut MemoryTracker) {
             ["borrow", name] => {
                 if values.contains_key(*name) {
                     tracker.track_borrow(&format!("&{}", name), name, false);
-                    println!("✅ Created immutable reference to {}", name);
+                    visualizer.show_borrowing(name, &format!("&{}", name), false);
                 } else {
                     println!("❌ {} not found", name);
                 }
@@ -221,14 +231,32 @@ pub fn interactive_demo(tracker: &mut MemoryTracker) {
             ["borrow_mut", name] => {
                 if values.contains_key(*name) {
                     tracker.track_borrow(&format!("&mut {}", name), name, true);
-                    println!("✅ Created mutable reference to {}", name);
+                    visualizer.show_borrowing(name, &format!("&mut {}", name), true);
+                } else {
+                    println!("❌ {} not found", name);
+                }
+            }
+            ["modify", name] => {
+                if let Some(value) = values.get_mut(*name) {
+                    tracker.track_use(&format!("&mut {}", name));
+                    value.push('!');
                 } else {
                     println!("❌ {} not found", name);
                 }
             }
             ["print", name] => {
+                tracker.track_use(name);
                 if let Some(value) = values.get(*name) {
                     println!("{}: \"{}\"", name, value);
+                }
+            }
+            ["use", reference @ ..] if !reference.is_empty() => {
+                tracker.track_use(&reference.join(" "));
+            }
+            ["drop", name] => {
+                if let Some(value) = values.remove(*name) {
+                    tracker.track_deallocation(name, value.len());
+                    println!("✅ Dropped {}", name);
                 } else {
                     println!("❌ {} not found", name);
                 }
@@ -244,6 +272,7 @@ pub fn interactive_demo(tracker: &mut MemoryTracker) {
 // CHALLENGE EXERCISES:
 
 // TODO: Fix this function that has borrowing issues
+#[allow(dead_code)]
 fn process_strings(strings: Vec<String>) -> Vec<usize> {
     let mut lengths = Vec::new();
     
@@ -259,16 +288,16 @@ fn process_strings(strings: Vec<String>) -> Vec<usize> {
 }
 
 // TODO: Implement a function that sorts strings in place
-fn sort_strings_in_place(strings: /* TODO: parameter type */) {
-    // TODO: Sort the strings vector in place
-    // HINT: You need mutable access
-}
+// fn sort_strings_in_place(strings: /* TODO: parameter type */) {
+//     // TODO: Sort the strings vector in place
+//     // HINT: You need mutable access
+// }
 
 // TODO: Implement a function that finds the longest string
-fn find_longest_string<'a>(strings: /* TODO: parameter */) -> Option</* TODO: return type */> {
-    // TODO: Return a reference to the longest string
-    // HINT: You'll need lifetime annotations
-}
+// fn find_longest_string<'a>(strings: /* TODO: parameter */) -> Option</* TODO: return type */> {
+//     // TODO: Return a reference to the longest string
+//     // HINT: You'll need lifetime annotations
+// }
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,467 @@
+// Diagram - the memory layout as Graphviz DOT and SVG
+//
+// The ASCII boxes in visualizer.rs are fixed-width and can't draw arrows.
+// These draw the same stack, heap and links for documents instead:
+//
+//   DOT  for Graphviz: dot -Tpng step-001.dot -o step-001.png
+//   SVG  self-contained, laid out here, so browsers and Markdown viewers
+//        show it without Graphviz installed
+//
+// Stack frames and block scopes are boxes nested inside the scope that
+// encloses them, starting with `fn main`. Owners point at their heap data
+// with solid arrows, references with dashed blue ones and Weak pointers
+// with dotted grey ones. Rc and Arc heap items show their strong and weak
+// counts, point at the heap items stored in their fields, and are outlined
+// in red when a reference cycle leaks them.
+//
+// `--diagrams <DIR>` writes both for every step of a run:
+// step-001.dot, step-001.svg, step-002.dot, ...
+
+use crate::memory_tracker::MemoryOperation;
+use crate::timeline::describe_all;
+use crate::visualizer::{HeapItem, MemoryVisualizer, StackItem};
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Link {
+    Owns,
+    Borrows,
+    Weak,
+}
+
+fn link(item: &StackItem) -> Link {
+    if item.is_reference() {
+        Link::Borrows
+    } else if item.is_weak() {
+        Link::Weak
+    } else {
+        Link::Owns
+    }
+}
+
+fn stack_label(item: &StackItem) -> String {
+    format!("{}: {}", item.name, item.value_type)
+}
+
+fn heap_lines(item: &HeapItem) -> Vec<String> {
+    let size = format!("{} bytes", item.size);
+    // Replayed values only know their size, which is their content too
+    let content = (!item.content.is_empty() && item.content != size).then(|| item.content.clone());
+    let mut lines = vec![format!("#{} {}", item.id, item.value_type), size];
+    lines.extend(content);
+    if item.is_shared() {
+        lines.push(format!("strong {}, weak {}", item.strong, item.weak));
+    }
+    if item.leaked {
+        lines.push("leaked in a cycle".to_string());
+    }
+    lines
+}
+
+// The items declared in one scope, with their index, oldest first
+fn items_in(visualizer: &MemoryVisualizer, scope: usize) -> impl Iterator<Item = (usize, &StackItem)> {
+    visualizer
+        .stack_items()
+        .iter()
+        .enumerate()
+        .filter(move |(_, item)| item.scope == scope)
+}
+
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+pub fn to_dot(visualizer: &MemoryVisualizer, title: &str) -> String {
+    let mut dot = String::new();
+    writeln!(dot, "digraph memory {{").unwrap();
+    writeln!(dot, "    label=\"{}\";", dot_escape(title)).unwrap();
+    writeln!(dot, "    labelloc=t;").unwrap();
+    writeln!(dot, "    rankdir=LR;").unwrap();
+    writeln!(dot, "    node [shape=box, fontname=\"monospace\"];").unwrap();
+
+    // Each scope is a cluster inside the one enclosing it
+    writeln!(dot, "\n    subgraph cluster_stack {{").unwrap();
+    writeln!(dot, "        label=\"Stack\";").unwrap();
+    let scopes = visualizer.scopes();
+    for (depth, scope) in scopes.iter().enumerate() {
+        let indent = "    ".repeat(depth + 2);
+        writeln!(dot, "{}subgraph cluster_scope_{} {{", indent, depth).unwrap();
+        writeln!(dot, "{}    label=\"{}\";", indent, dot_escape(&scope.label())).unwrap();
+        if visualizer.stack_items().is_empty() && scopes.len() == 1 {
+            writeln!(dot, "{}    stack_empty [label=\"(empty)\", shape=plaintext];", indent).unwrap();
+        }
+        for (i, item) in items_in(visualizer, depth) {
+            writeln!(dot, "{}    stack_{} [label=\"{}\"];", indent, i, dot_escape(&stack_label(item))).unwrap();
+        }
+    }
+    for depth in (0..scopes.len()).rev() {
+        writeln!(dot, "{}}}", "    ".repeat(depth + 2)).unwrap();
+    }
+    writeln!(dot, "    }}").unwrap();
+
+    writeln!(dot, "\n    subgraph cluster_heap {{").unwrap();
+    writeln!(dot, "        label=\"Heap\";").unwrap();
+    if visualizer.heap_items().is_empty() {
+        writeln!(dot, "        heap_empty [label=\"(empty)\", shape=plaintext];").unwrap();
+    }
+    for item in visualizer.heap_items() {
+        let label: Vec<String> = heap_lines(item).iter().map(|line| dot_escape(line)).collect();
+        let colour = if item.leaked { ", color=red" } else { "" };
+        writeln!(dot, "        heap_{} [label=\"{}\"{}];", item.id, label.join("\\n"), colour).unwrap();
+    }
+    writeln!(dot, "    }}").unwrap();
+
+    let mut links = visualizer
+        .stack_items()
+        .iter()
+        .enumerate()
+        .filter_map(|(i, item)| item.points_to_heap.map(|id| (i, id, link(item))))
+        .peekable();
+    if links.peek().is_some() {
+        writeln!(dot).unwrap();
+    }
+    for (i, id, link) in links {
+        let style = match link {
+            Link::Owns => "",
+            Link::Borrows => " [style=dashed, color=blue]",
+            Link::Weak => " [style=dotted, color=gray]",
+        };
+        writeln!(dot, "    stack_{} -> heap_{}{};", i, id, style).unwrap();
+    }
+    for item in visualizer.heap_items() {
+        for heap_link in &item.links {
+            let style = if heap_link.weak { ", style=dotted, color=gray" } else { "" };
+            writeln!(dot, "    heap_{} -> heap_{} [label=\"{}\"{}];", item.id, heap_link.target, dot_escape(&heap_link.field), style).unwrap();
+        }
+    }
+    writeln!(dot, "}}").unwrap();
+    dot
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Monospace text at 13px is about 8px a character
+const CHAR_WIDTH: usize = 8;
+const LINE_HEIGHT: usize = 18;
+const MARGIN: usize = 20;
+const GAP: usize = 12;
+// How far a scope's box sits inside the one enclosing it
+const INDENT: usize = 12;
+
+fn box_width<'a>(lines: impl Iterator<Item = &'a String>) -> usize {
+    let longest = lines.map(|line| line.chars().count()).max().unwrap_or(0);
+    (longest * CHAR_WIDTH + 24).max(160)
+}
+
+pub fn to_svg(visualizer: &MemoryVisualizer, title: &str) -> String {
+    let scopes = visualizer.scopes();
+    let labels: Vec<String> = visualizer.stack_items().iter().map(stack_label).collect();
+    let scope_labels: Vec<String> = scopes.iter().map(|scope| scope.label()).collect();
+    let heap: Vec<(usize, Vec<String>)> = visualizer
+        .heap_items()
+        .iter()
+        .map(|item| (item.id, heap_lines(item)))
+        .collect();
+
+    // Two columns, sized to fit the longest text, with room for arrows
+    // between them. The stack column also fits the nested scopes.
+    let stack_width = box_width(labels.iter().chain(&scope_labels)) + 2 * INDENT * scopes.len();
+    let heap_width = box_width(heap.iter().flat_map(|(_, lines)| lines));
+    let stack_x = MARGIN;
+    let heap_x = stack_x + stack_width + 120;
+    let top = 70;
+
+    // Scopes are laid out top to bottom, each box closing after the ones
+    // nested in it. Their boxes are drawn first, so they sit behind.
+    let mut stack_centres = Vec::new();
+    let mut scope_boxes = String::new();
+    let mut body = String::new();
+    let mut scope_tops = Vec::new();
+    let mut item_right = Vec::new();
+    let mut y = top;
+    for (depth, label) in scope_labels.iter().enumerate() {
+        scope_tops.push(y);
+        writeln!(body, r#"  <text x="{}" y="{}" class="scope">{}</text>"#, stack_x + depth * INDENT + 8, y + 18, xml_escape(label)).unwrap();
+        y += LINE_HEIGHT + GAP;
+
+        let x = stack_x + (depth + 1) * INDENT;
+        let width = stack_width - 2 * (depth + 1) * INDENT;
+        for (i, _) in items_in(visualizer, depth) {
+            let height = LINE_HEIGHT + GAP;
+            writeln!(body, r#"  <rect x="{}" y="{}" width="{}" height="{}" class="stack"/>"#, x, y, width, height).unwrap();
+            writeln!(body, r#"  <text x="{}" y="{}">{}</text>"#, x + 12, y + 20, xml_escape(&labels[i])).unwrap();
+            stack_centres.push((i, y + height / 2));
+            item_right.push((i, x + width));
+            y += height + GAP;
+        }
+    }
+    if labels.is_empty() {
+        writeln!(body, r#"  <text x="{}" y="{}">(empty)</text>"#, stack_x + INDENT + 8, y + 14).unwrap();
+        y += LINE_HEIGHT + GAP;
+    }
+    // Closed innermost first, drawn outermost first
+    let mut frames = Vec::new();
+    for depth in (0..scopes.len()).rev() {
+        let x = stack_x + depth * INDENT;
+        let width = stack_width - 2 * depth * INDENT;
+        frames.push(format!(r#"  <rect x="{}" y="{}" width="{}" height="{}" class="frame"/>"#, x, scope_tops[depth], width, y - scope_tops[depth]));
+        y += GAP / 2;
+    }
+    for frame in frames.iter().rev() {
+        writeln!(scope_boxes, "{}", frame).unwrap();
+    }
+    let stack_bottom = y + GAP;
+
+    let mut heap_centres = Vec::new();
+    let mut y = top;
+    for ((id, lines), item) in heap.iter().zip(visualizer.heap_items()) {
+        let height = lines.len() * LINE_HEIGHT + GAP;
+        let class = if item.leaked { "heap leaked" } else { "heap" };
+        writeln!(body, r#"  <rect x="{}" y="{}" width="{}" height="{}" class="{}"/>"#, heap_x, y, heap_width, height, class).unwrap();
+        for (n, line) in lines.iter().enumerate() {
+            let class = if n == 0 { r#" class="heading""# } else { "" };
+            writeln!(body, r#"  <text x="{}" y="{}"{}>{}</text>"#, heap_x + 12, y + 20 + n * LINE_HEIGHT, class, xml_escape(line)).unwrap();
+        }
+        heap_centres.push((*id, y + height / 2));
+        y += height + GAP;
+    }
+    let heap_bottom = y;
+
+    for (i, item) in visualizer.stack_items().iter().enumerate() {
+        let Some(id) = item.points_to_heap else { continue };
+        let from = stack_centres.iter().find(|(index, _)| *index == i);
+        let to = heap_centres.iter().find(|(heap_id, _)| *heap_id == id);
+        let right = item_right.iter().find(|(index, _)| *index == i);
+        if let (Some((_, from_y)), Some((_, to_y)), Some((_, x1))) = (from, to, right) {
+            let (x1, x2) = (*x1, heap_x);
+            let middle = (x1 + x2) / 2;
+            let class = match link(item) {
+                Link::Owns => "owns",
+                Link::Borrows => "borrows",
+                Link::Weak => "weak",
+            };
+            writeln!(
+                body,
+                r#"  <path d="M {} {} C {} {}, {} {}, {} {}" class="{}" marker-end="url(#arrow-{})"/>"#,
+                x1, from_y, middle, from_y, middle, to_y, x2, to_y, class, class
+            )
+            .unwrap();
+        }
+    }
+
+    // Pointers between heap items loop out to the right of the heap column,
+    // further out the further apart the items are
+    let heap_right = heap_x + heap_width;
+    let mut loop_width = 0;
+    for item in visualizer.heap_items() {
+        for heap_link in &item.links {
+            let from = heap_centres.iter().find(|(id, _)| *id == item.id);
+            let to = heap_centres.iter().find(|(id, _)| *id == heap_link.target);
+            let (Some((_, from_y)), Some((_, to_y))) = (from, to) else { continue };
+            let bulge = 30 + from_y.abs_diff(*to_y) / 4;
+            loop_width = loop_width.max(bulge);
+            let class = if heap_link.weak { "weak" } else { "owns" };
+            writeln!(
+                body,
+                r#"  <path d="M {} {} C {} {}, {} {}, {} {}" class="{}" marker-end="url(#arrow-{})"/>"#,
+                heap_right, from_y - 4, heap_right + bulge, from_y - 4, heap_right + bulge, to_y + 4, heap_right, to_y + 4, class, class
+            )
+            .unwrap();
+            writeln!(body, r#"  <text x="{}" y="{}" class="field">{}</text>"#, heap_right + bulge + 4, (from_y + to_y) / 2 + 4, xml_escape(&heap_link.field)).unwrap();
+            loop_width = loop_width.max(bulge + 8 + heap_link.field.chars().count() * CHAR_WIDTH);
+        }
+    }
+
+    let width = heap_right + loop_width + MARGIN;
+    let height = stack_bottom.max(heap_bottom).max(top + 40) + MARGIN;
+
+    let mut svg = String::new();
+    writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}">"#, width, height, width, height).unwrap();
+    writeln!(svg, "  <style>").unwrap();
+    writeln!(svg, "    text {{ font: 13px monospace; fill: #222; }}").unwrap();
+    writeln!(svg, "    .title {{ font-weight: bold; }}").unwrap();
+    writeln!(svg, "    .heading {{ font-weight: bold; }}").unwrap();
+    writeln!(svg, "    .scope {{ fill: #555; font-style: italic; }}").unwrap();
+    writeln!(svg, "    .frame {{ fill: #fafafa; stroke: #999; }}").unwrap();
+    writeln!(svg, "    .stack {{ fill: #e8f0fe; stroke: #3367d6; }}").unwrap();
+    writeln!(svg, "    .heap {{ fill: #f3e8fd; stroke: #8e24aa; }}").unwrap();
+    writeln!(svg, "    .leaked {{ stroke: #d93025; stroke-width: 2; }}").unwrap();
+    writeln!(svg, "    .field {{ fill: #555; }}").unwrap();
+    writeln!(svg, "    path {{ fill: none; stroke-width: 1.5; }}").unwrap();
+    writeln!(svg, "    .owns {{ stroke: #222; }}").unwrap();
+    writeln!(svg, "    .borrows {{ stroke: #1a73e8; stroke-dasharray: 6 4; }}").unwrap();
+    writeln!(svg, "    .weak {{ stroke: #999; stroke-dasharray: 2 3; }}").unwrap();
+    writeln!(svg, "  </style>").unwrap();
+    writeln!(svg, "  <defs>").unwrap();
+    for (class, colour) in [("owns", "#222"), ("borrows", "#1a73e8"), ("weak", "#999")] {
+        writeln!(
+            svg,
+            r#"    <marker id="arrow-{}" viewBox="0 0 10 10" refX="10" refY="5" markerWidth="8" markerHeight="8" orient="auto"><path d="M 0 0 L 10 5 L 0 10 z" fill="{}"/></marker>"#,
+            class, colour
+        )
+        .unwrap();
+    }
+    writeln!(svg, "  </defs>").unwrap();
+    writeln!(svg, r#"  <rect width="100%" height="100%" fill="white"/>"#).unwrap();
+    writeln!(svg, r#"  <text x="{}" y="28" class="title">{}</text>"#, MARGIN, xml_escape(title)).unwrap();
+    writeln!(svg, r#"  <text x="{}" y="58" class="title">Stack</text>"#, stack_x).unwrap();
+    writeln!(svg, r#"  <text x="{}" y="58" class="title">Heap</text>"#, heap_x).unwrap();
+    if heap.is_empty() {
+        writeln!(svg, r#"  <text x="{}" y="{}">(empty)</text>"#, heap_x, top + 20).unwrap();
+    }
+    svg.push_str(&scope_boxes);
+    svg.push_str(&body);
+    writeln!(svg, "</svg>").unwrap();
+    svg
+}
+
+/// Replays the operations and writes a DOT and an SVG file for every step.
+/// Returns how many steps were written.
+pub fn write_steps(operations: &[MemoryOperation], dir: &Path) -> io::Result<usize> {
+    fs::create_dir_all(dir)?;
+    let mut visualizer = MemoryVisualizer::new();
+    let descriptions = describe_all(operations);
+
+    for (operation, description) in operations.iter().zip(&descriptions) {
+        visualizer.apply(operation);
+        let title = format!("Step {} of {}: {}", operation.step, operations.len(), description);
+        let name = format!("step-{:03}", operation.step);
+        fs::write(dir.join(format!("{}.dot", name)), to_dot(&visualizer, &title))?;
+        fs::write(dir.join(format!("{}.svg", name)), to_svg(&visualizer, &title))?;
+    }
+    Ok(operations.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory_tracker::MemoryTracker;
+
+    fn layout(build: impl FnOnce(&mut MemoryTracker)) -> MemoryVisualizer {
+        let mut tracker = MemoryTracker::new();
+        build(&mut tracker);
+        let mut visualizer = MemoryVisualizer::new();
+        for operation in tracker.operations() {
+            visualizer.apply(operation);
+        }
+        visualizer
+    }
+
+    #[test]
+    fn test_dot_links() {
+        let visualizer = layout(|tracker| {
+            tracker.track_allocation("s1", "String", 5);
+            tracker.track_move("s1", "s2", "String");
+            tracker.track_borrow("r", "s2", false);
+        });
+        let dot = to_dot(&visualizer, "a \"quoted\" title");
+
+        assert!(dot.starts_with("digraph memory {"));
+        assert!(dot.contains(r#"label="a \"quoted\" title";"#));
+        assert!(dot.contains(r#"stack_0 [label="s2: String"];"#));
+        assert!(dot.contains(r##"heap_0 [label="#0 String\n5 bytes"];"##));
+        assert!(dot.contains("stack_0 -> heap_0;"));
+        assert!(dot.contains("stack_1 -> heap_0 [style=dashed, color=blue];"));
+    }
+
+    #[test]
+    fn test_shared_ownership_counts() {
+        let visualizer = layout(|tracker| {
+            tracker.track_allocation("a", "Rc<String>", 6);
+            tracker.track_clone("a", "b", "Rc<String>", 0);
+            tracker.track_clone("a", "c", "Rc<String>", 0);
+            tracker.track_clone("a", "w", "Weak<String>", 0);
+            tracker.track_deallocation("c", 0);
+        });
+        let dot = to_dot(&visualizer, "shared");
+
+        // One heap item, kept alive by two owners
+        assert_eq!(visualizer.heap_items().len(), 1);
+        assert!(dot.contains(r#"strong 2, weak 1"]"#));
+        assert_eq!(dot.matches("-> heap_0;").count(), 2);
+        assert!(dot.contains("-> heap_0 [style=dotted, color=gray];"));
+
+        let svg = to_svg(&visualizer, "shared");
+        assert!(svg.contains("w: Weak&lt;String&gt;"));
+        assert_eq!(svg.matches(r#"marker-end="url(#arrow-owns)""#).count(), 2);
+        assert_eq!(svg.matches(r#"marker-end="url(#arrow-weak)""#).count(), 1);
+    }
+
+    #[test]
+    fn test_leaked_cycle() {
+        let visualizer = layout(|tracker| {
+            tracker.track_allocation("a", "Rc<Node>", 16);
+            tracker.track_allocation("b", "Rc<Node>", 16);
+            tracker.track_link("a", "next", "b", "Rc<Node>");
+            tracker.track_link("b", "next", "a", "Rc<Node>");
+            tracker.track_deallocation("a", 0);
+            tracker.track_deallocation("b", 0);
+        });
+        let dot = to_dot(&visualizer, "cycle");
+        assert!(dot.contains(r##"heap_0 [label="#0 Rc<Node>\n16 bytes\nstrong 1, weak 0\nleaked in a cycle", color=red];"##));
+        assert!(dot.contains(r#"heap_0 -> heap_1 [label="next"];"#));
+        assert!(dot.contains(r#"heap_1 -> heap_0 [label="next"];"#));
+
+        let svg = to_svg(&visualizer, "cycle");
+        assert_eq!(svg.matches(r#"class="heap leaked""#).count(), 2);
+        assert_eq!(svg.matches(r#"class="field">next</text>"#).count(), 2);
+    }
+
+    #[test]
+    fn test_svg_fits_long_names() {
+        let visualizer = layout(|tracker| {
+            tracker.track_allocation("a_rather_long_variable_name", "HashMap<String, Vec<u8>>", 48);
+        });
+        let svg = to_svg(&visualizer, "long");
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.trim_end().ends_with("</svg>"));
+
+        // "a_rather_long_variable_name: HashMap<String, Vec<u8>>" is 53 characters
+        let label_width = 53 * CHAR_WIDTH + 24;
+        assert!(svg.contains(&format!(r#"width="{}" height="{}" class="stack""#, label_width, LINE_HEIGHT + GAP)));
+    }
+
+    #[test]
+    fn test_scopes_are_nested() {
+        let visualizer = layout(|tracker| {
+            tracker.track_allocation("s", "String", 5);
+            tracker.enter_function("takes_ownership");
+            tracker.track_move("s", "param", "String");
+            tracker.enter_block("inner");
+            tracker.track_allocation("temp", "String", 4);
+        });
+        let dot = to_dot(&visualizer, "scopes");
+        let outer = dot.find("label=\"fn takes_ownership\"").unwrap();
+        let inner = dot.find("label=\"{ inner }\"").unwrap();
+        let param = dot.find("stack_0 [label=\"param: String\"]").unwrap();
+        let temp = dot.find("stack_1 [label=\"temp: String\"]").unwrap();
+        assert!(outer < param && param < inner && inner < temp);
+        assert!(dot.contains("        }\n            }\n        }\n    }\n"));
+
+        let svg = to_svg(&visualizer, "scopes");
+        assert_eq!(svg.matches(r#"class="frame""#).count(), 3);
+        assert!(svg.contains(r#"class="scope">{ inner }</text>"#));
+    }
+
+    #[test]
+    fn test_write_steps() {
+        let dir = std::env::temp_dir().join(format!("memory-visualizer-diagrams-{}", std::process::id()));
+        let mut tracker = MemoryTracker::new();
+        tracker.track_allocation("s1", "String", 5);
+        tracker.track_move("s1", "s2", "String");
+
+        assert_eq!(write_steps(tracker.operations(), &dir).unwrap(), 2);
+        let dot = fs::read_to_string(dir.join("step-002.dot")).unwrap();
+        assert!(dot.contains("Step 2 of 2: ownership moves from s1 to s2"));
+        assert!(dir.join("step-001.svg").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
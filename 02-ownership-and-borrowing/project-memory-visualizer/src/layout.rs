@@ -0,0 +1,529 @@
+// Layout - how real Rust types are laid out in memory
+//
+// Every size, alignment and offset here is measured on the real type, with
+// size_of, align_of and offset_of!, so it is what this compiler does on
+// this target:
+//
+//   Padded  #[repr(C)] struct
+//   size 12 bytes, align 4, 5 bytes of padding (41%)
+//      0  a···bbbb cc··
+//
+// Each letter is a byte of one field and · is padding, the bytes a field
+// has to skip so the next one is aligned. Fields after the 26th get Greek
+// letters, and any after the 50th share +. A plain struct has no promised
+// field order, and rustc reorders it to need less padding: the same fields
+// without #[repr(C)] fit in 8 bytes.
+//
+// An Option costs nothing extra when its value has a niche, a bit pattern
+// the value can never have: None is stored as that pattern, like the null
+// pointer for a Box. Otherwise the Option needs a tag, and the tag's bytes
+// are marked T.
+//
+// User structs are registered with `struct_layout!`, which needs no derive:
+//
+//   struct_layout!(Point { x: f64, y: f64 })
+//
+// `inspect --struct "Packet { flag: bool, id: u64 }"` describes one without
+// compiling it, built from the registered types. Only #[repr(C)] has a
+// defined layout then, so that is the one shown.
+
+use colored::*;
+use std::fmt::Write;
+use std::mem::{align_of, size_of};
+use std::num::NonZeroU32;
+use std::ops::Range;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub name: String,
+    pub type_name: String,
+    pub offset: usize,
+    pub size: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeLayout {
+    pub name: String,
+    /// What kind of type it is, e.g. "#[repr(C)] struct"
+    pub kind: String,
+    pub size: usize,
+    pub align: usize,
+    /// Empty when the parts aren't public, like a String's
+    pub fields: Vec<Field>,
+    /// The bytes no field covers hold an enum tag, not just padding
+    pub tagged: bool,
+    /// How the value leaves room for a None
+    pub niche: Option<String>,
+    pub notes: Vec<String>,
+}
+
+/// What one byte of a value is used for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Byte {
+    /// Part of the field with this index
+    Field(usize),
+    Padding,
+    /// The enum tag, or padding around it
+    Tag,
+    /// Part of a value whose parts aren't known
+    Opaque,
+}
+
+impl TypeLayout {
+    pub fn new(name: &str, kind: &str, size: usize, align: usize) -> Self {
+        TypeLayout {
+            name: name.to_string(),
+            kind: kind.to_string(),
+            size,
+            align,
+            fields: Vec::new(),
+            tagged: false,
+            niche: None,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn field(mut self, name: &str, type_name: &str, offset: usize, size: usize) -> Self {
+        self.fields.push(Field {
+            name: name.to_string(),
+            type_name: type_name.to_string(),
+            offset,
+            size,
+        });
+        self
+    }
+
+    pub fn niche(mut self, explanation: &str) -> Self {
+        self.niche = Some(explanation.to_string());
+        self
+    }
+
+    pub fn note(mut self, note: &str) -> Self {
+        self.notes.push(note.to_string());
+        self
+    }
+
+    /// What each byte is used for, in order
+    pub fn bytes(&self) -> Vec<Byte> {
+        if self.fields.is_empty() {
+            return vec![Byte::Opaque; self.size];
+        }
+        let gap = if self.tagged { Byte::Tag } else { Byte::Padding };
+        (0..self.size)
+            .map(|byte| {
+                self.fields
+                    .iter()
+                    .position(|field| (field.offset..field.offset + field.size).contains(&byte))
+                    .map_or(gap, Byte::Field)
+            })
+            .collect()
+    }
+
+    /// The byte ranges that are only padding
+    pub fn padding(&self) -> Vec<Range<usize>> {
+        let bytes = self.bytes();
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for (i, byte) in bytes.iter().enumerate() {
+            if *byte != Byte::Padding {
+                continue;
+            }
+            match ranges.last_mut() {
+                Some(range) if range.end == i => range.end += 1,
+                _ => ranges.push(i..i + 1),
+            }
+        }
+        ranges
+    }
+
+    pub fn padding_bytes(&self) -> usize {
+        self.padding().iter().map(|range| range.len()).sum()
+    }
+}
+
+/// The layout of a struct, with the offset the compiler gave each field
+macro_rules! struct_layout {
+    ($kind:literal, $type:ident { $($field:ident : $field_type:ty),* $(,)? }) => {
+        TypeLayout::new(
+            stringify!($type),
+            $kind,
+            std::mem::size_of::<$type>(),
+            std::mem::align_of::<$type>(),
+        )
+        $(.field(
+            stringify!($field),
+            stringify!($field_type),
+            std::mem::offset_of!($type, $field),
+            std::mem::size_of::<$field_type>(),
+        ))*
+    };
+    ($type:ident { $($fields:tt)* }) => {
+        struct_layout!("struct", $type { $($fields)* })
+    };
+}
+
+// A type measured as a whole, without its parts
+fn whole<T>(name: &str, kind: &str) -> TypeLayout {
+    TypeLayout::new(name, kind, size_of::<T>(), align_of::<T>())
+}
+
+// Some(value), with the offset of the value measured from a real Some
+fn option<T>(inner: &str, value: T, niche: &str) -> TypeLayout {
+    let option = Some(value);
+    let payload = option.as_ref().expect("built as Some");
+    let offset = payload as *const T as usize - &option as *const Option<T> as usize;
+
+    let mut layout = TypeLayout::new(&format!("Option<{}>", inner), "enum", size_of::<Option<T>>(), align_of::<Option<T>>())
+        .field("Some", inner, offset, size_of::<T>());
+    if layout.size == size_of::<T>() {
+        layout = layout.niche(niche);
+    } else {
+        layout.tagged = true;
+        let extra = layout.size - size_of::<T>();
+        layout = layout.note(&format!("{} has no spare bit patterns, so a tag takes {} more", inner, byte_count(extra)));
+    }
+    layout
+}
+
+#[repr(C)]
+#[allow(dead_code)]
+struct Padded {
+    a: u8,
+    b: u32,
+    c: u16,
+}
+
+#[allow(dead_code)]
+struct Reordered {
+    a: u8,
+    b: u32,
+    c: u16,
+}
+
+#[allow(dead_code)]
+struct Point {
+    x: f64,
+    y: f64,
+}
+
+#[allow(dead_code)]
+enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+/// Every type `inspect` knows, smallest building blocks first
+pub fn registry() -> Vec<TypeLayout> {
+    let word = size_of::<usize>();
+    vec![
+        whole::<bool>("bool", "primitive").note("only 0 and 1 are valid, which leaves a niche"),
+        whole::<u8>("u8", "primitive"),
+        whole::<u16>("u16", "primitive"),
+        whole::<u32>("u32", "primitive"),
+        whole::<u64>("u64", "primitive"),
+        whole::<u128>("u128", "primitive"),
+        whole::<usize>("usize", "primitive"),
+        whole::<f64>("f64", "primitive"),
+        whole::<char>("char", "primitive").note("a Unicode scalar value; anything above 0x10FFFF is a niche"),
+        whole::<&u8>("&u8", "reference").note("a pointer, never null"),
+        whole::<&str>("&str", "reference").note(&format!("a fat pointer: the address and the length, {} bytes each", word)),
+        whole::<Box<i32>>("Box<i32>", "smart pointer").note("a pointer to the heap, never null"),
+        whole::<String>("String", "std struct").note(&format!(
+            "a pointer to the heap buffer, its capacity and the length, {} bytes each, in an order std doesn't promise",
+            word
+        )),
+        whole::<Vec<u8>>("Vec<u8>", "std struct").note("like String: pointer, capacity and length"),
+        whole::<Rc<String>>("Rc<String>", "smart pointer").note("one pointer; the strong and weak counts live on the heap, next to the String"),
+        whole::<NonZeroU32>("NonZeroU32", "std struct").note("a u32 that is never 0"),
+        option("u8", 0u8, ""),
+        option("u32", 0u32, ""),
+        option("f64", 0f64, ""),
+        option("bool", false, "None uses a byte other than 0 or 1, which a bool never is"),
+        option("char", 'a', "None uses a value above 0x10FFFF, which a char never is"),
+        option("NonZeroU32", NonZeroU32::MIN, "None is 0, which a NonZeroU32 never is"),
+        option("&u8", &0u8, "None is the null pointer, which a reference never is"),
+        option("Box<i32>", Box::new(0i32), "None is the null pointer, which a Box never is"),
+        option("String", String::new(), "None uses a bit pattern a String never has: today, a capacity above isize::MAX"),
+        TypeLayout::new("Direction", "enum", size_of::<Direction>(), align_of::<Direction>())
+            .note("four variants without data: a one-byte tag, 0 to 3"),
+        option("Direction", Direction::North, "None uses a tag no Direction variant uses"),
+        struct_layout!("#[repr(C)] struct", Padded { a: u8, b: u32, c: u16 })
+            .note("#[repr(C)] keeps the declared order, so b and the end need padding"),
+        struct_layout!(Reordered { a: u8, b: u32, c: u16 })
+            .note("the same fields as Padded, reordered by rustc to avoid padding"),
+        struct_layout!(Point { x: f64, y: f64 }),
+    ]
+}
+
+// Type names compare without spaces, so `Option< u8 >` finds Option<u8>
+fn normalize(name: &str) -> String {
+    name.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+pub fn find(name: &str) -> Option<TypeLayout> {
+    registry().into_iter().find(|layout| normalize(&layout.name) == normalize(name))
+}
+
+// "1 byte", "3 bytes"
+fn byte_count(count: usize) -> String {
+    if count == 1 {
+        "1 byte".to_string()
+    } else {
+        format!("{} bytes", count)
+    }
+}
+
+fn align_up(offset: usize, align: usize) -> usize {
+    offset.div_ceil(align) * align
+}
+
+/// Lays out `Name { field: Type, ... }` as #[repr(C)] would, from the
+/// sizes and alignments of registered types
+pub fn describe_struct(definition: &str) -> Result<TypeLayout, String> {
+    let usage = "expected `<Name> { <field>: <Type>, ... }`";
+    let (name, body) = definition.split_once('{').ok_or(usage)?;
+    let body = body.trim().strip_suffix('}').ok_or(usage)?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(usage.to_string());
+    }
+
+    let mut fields = Vec::new();
+    for field in body.split(',').map(str::trim).filter(|field| !field.is_empty()) {
+        let (field_name, type_name) = field
+            .split_once(':')
+            .ok_or_else(|| format!("expected `<field>: <Type>` but found `{}`", field))?;
+        let (field_name, type_name) = (field_name.trim(), type_name.trim());
+        if fields.iter().any(|(name, _)| name == field_name) {
+            return Err(format!("field `{}` is declared more than once", field_name));
+        }
+        let layout = find(type_name).ok_or_else(|| format!("unknown field type `{}`", type_name))?;
+        fields.push((field_name.to_string(), layout));
+    }
+
+    let place = |fields: &[&(String, TypeLayout)]| {
+        let mut offset = 0;
+        let mut placed = Vec::new();
+        for (field_name, field) in fields {
+            offset = align_up(offset, field.align);
+            placed.push((field_name.clone(), field.name.clone(), offset, field.size));
+            offset += field.size;
+        }
+        let align = fields.iter().map(|(_, field)| field.align).max().unwrap_or(1);
+        (placed, align_up(offset, align), align)
+    };
+
+    let declared: Vec<&(String, TypeLayout)> = fields.iter().collect();
+    let (placed, size, align) = place(&declared);
+    let mut layout = TypeLayout::new(name, "#[repr(C)] struct, described", size, align);
+    for (field_name, type_name, offset, field_size) in placed {
+        layout = layout.field(&field_name, &type_name, offset, field_size);
+    }
+
+    // Largest alignment first is what rustc usually does for a plain struct
+    let mut sorted = declared;
+    sorted.sort_by_key(|(_, field)| std::cmp::Reverse(field.align));
+    let (_, sorted_size, _) = place(&sorted);
+    if sorted_size < size {
+        layout = layout.note(&format!(
+            "ordered by alignment, largest first, the fields fit in {} bytes; without #[repr(C)] rustc is free to do that",
+            sorted_size
+        ));
+    }
+    Ok(layout)
+}
+
+/// One character per byte, in rows of 16 starting with the offset: a
+/// letter for each field, · for padding, T for a tag and █ when the parts
+/// aren't known
+pub fn byte_map(layout: &TypeLayout) -> Vec<String> {
+    let bytes = layout.bytes();
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(row, chunk)| {
+            let mut line = format!("{:>4}  ", row * 16);
+            for (i, byte) in chunk.iter().enumerate() {
+                if i == 8 {
+                    line.push(' ');
+                }
+                line.push(symbol(*byte));
+            }
+            line
+        })
+        .collect()
+}
+
+// Final sigma is left out, it looks too much like σ
+const GREEK: &str = "αβγδεζηθικλμνξοπρστυφχψω";
+
+pub fn symbol(byte: Byte) -> char {
+    match byte {
+        Byte::Field(i) if i < 26 => (b'a' + i as u8) as char,
+        Byte::Field(i) => GREEK.chars().nth(i - 26).unwrap_or('+'),
+        Byte::Padding => '·',
+        Byte::Tag => 'T',
+        Byte::Opaque => '█',
+    }
+}
+
+/// One line per field and gap, in offset order
+pub fn legend(layout: &TypeLayout) -> Vec<String> {
+    let bytes = layout.bytes();
+    let mut lines = Vec::new();
+    let mut start = 0;
+    while start < bytes.len() {
+        let kind = bytes[start];
+        let end = (start..bytes.len()).find(|&i| bytes[i] != kind).unwrap_or(bytes.len());
+        let what = match kind {
+            Byte::Field(i) => format!("{}: {}", layout.fields[i].name, layout.fields[i].type_name),
+            Byte::Padding => format!("padding ({})", byte_count(end - start)),
+            Byte::Tag => format!("tag and padding ({})", byte_count(end - start)),
+            Byte::Opaque => format!("{} ({})", layout.name, byte_count(end - start)),
+        };
+        let mut line = String::new();
+        write!(line, "{:>4}..{:<4} {} {}", start, end, symbol(kind), what).unwrap();
+        lines.push(line);
+        start = end;
+    }
+    lines
+}
+
+fn paint(text: &str, byte: Byte) -> ColoredString {
+    match byte {
+        Byte::Field(i) => match i % 4 {
+            0 => text.green(),
+            1 => text.cyan(),
+            2 => text.yellow(),
+            _ => text.magenta(),
+        },
+        Byte::Padding => text.red().dimmed(),
+        Byte::Tag => text.blue().bold(),
+        Byte::Opaque => text.purple(),
+    }
+}
+
+/// Prints the byte map, the legend and what the layout shows
+pub fn print(layout: &TypeLayout) {
+    println!("\n{}  {}", layout.name.bold(), layout.kind.dimmed());
+    let padding = layout.padding_bytes();
+    let mut summary = format!("size {}, align {}", byte_count(layout.size), layout.align);
+    if padding > 0 {
+        write!(summary, ", {} of padding ({}%)", byte_count(padding), padding * 100 / layout.size).unwrap();
+    }
+    println!("{}", summary);
+
+    let bytes = layout.bytes();
+    for (row, line) in byte_map(layout).iter().enumerate() {
+        let (offset, map) = line.split_at(6);
+        let mut byte = row * 16;
+        let painted: String = map
+            .chars()
+            .map(|c| {
+                if c == ' ' {
+                    return " ".to_string();
+                }
+                byte += 1;
+                paint(&c.to_string(), bytes[byte - 1]).to_string()
+            })
+            .collect();
+        println!("{}{}", offset.dimmed(), painted);
+    }
+    for (line, byte) in legend(layout).iter().zip(legend_kinds(layout)) {
+        println!("{}", paint(line, byte));
+    }
+
+    if let Some(niche) = &layout.niche {
+        println!("{} {}", "✨ Niche:".green().bold(), niche);
+    }
+    for note in &layout.notes {
+        println!("{} {}", "•".dimmed(), note);
+    }
+}
+
+// The kind of each legend line
+fn legend_kinds(layout: &TypeLayout) -> Vec<Byte> {
+    let mut kinds = layout.bytes();
+    kinds.dedup();
+    kinds
+}
+
+/// A table of every registered type
+pub fn print_registry() {
+    println!("{:<20} {:>5} {:>6} {:>8}", "Type".bold(), "Size".bold(), "Align".bold(), "Padding".bold());
+    for layout in registry() {
+        let niche = if layout.niche.is_some() { " ✨ niche".green().to_string() } else { String::new() };
+        println!("{:<20} {:>5} {:>6} {:>8}{}", layout.name, layout.size, layout.align, layout.padding_bytes(), niche);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repr_c_keeps_field_order_and_pads() {
+        let padded = find("Padded").unwrap();
+        assert_eq!((padded.size, padded.align), (12, 4));
+        let offsets: Vec<usize> = padded.fields.iter().map(|field| field.offset).collect();
+        assert_eq!(offsets, vec![0, 4, 8]);
+        assert_eq!(padded.padding(), vec![1..4, 10..12]);
+        assert_eq!(byte_map(&padded), vec!["   0  a···bbbb cc··"]);
+
+        // Without #[repr(C)] rustc may reorder the fields, and does
+        let reordered = find("Reordered").unwrap();
+        assert_eq!(reordered.size, 8);
+        assert_eq!(reordered.padding_bytes(), 1);
+    }
+
+    #[test]
+    fn test_niches_make_options_free() {
+        let boxed = find("Option<Box<i32>>").unwrap();
+        assert_eq!(boxed.size, size_of::<Box<i32>>());
+        assert!(boxed.niche.as_deref().unwrap().contains("null pointer"));
+        assert!(!boxed.tagged);
+
+        let number = find("Option< u32 >").unwrap();
+        assert_eq!(number.size, 8);
+        assert!(number.niche.is_none());
+        assert!(number.tagged);
+        let tag = number.bytes().iter().filter(|byte| **byte == Byte::Tag).count();
+        assert_eq!(tag, 4);
+        assert!(number.padding().is_empty());
+
+        assert_eq!(find("Option<Direction>").unwrap().size, 1);
+    }
+
+    #[test]
+    fn test_describe_struct() {
+        let packet = describe_struct("Packet { flag: bool, id: u64, len: u16 }").unwrap();
+        assert_eq!((packet.size, packet.align), (24, 8));
+        assert_eq!(packet.padding(), vec![1..8, 18..24]);
+        assert!(packet.notes[0].contains("fit in 16 bytes"));
+        assert_eq!(
+            legend(&packet),
+            vec![
+                "   0..1    a flag: bool",
+                "   1..8    · padding (7 bytes)",
+                "   8..16   b id: u64",
+                "  16..18   c len: u16",
+                "  18..24   · padding (6 bytes)",
+            ]
+        );
+
+        assert_eq!(describe_struct("Packet flag: bool").unwrap_err(), "expected `<Name> { <field>: <Type>, ... }`");
+        assert_eq!(describe_struct("P { x: Foo }").unwrap_err(), "unknown field type `Foo`");
+        assert_eq!(
+            describe_struct("Dup { a: u8, a: u8 }").unwrap_err(),
+            "field `a` is declared more than once"
+        );
+    }
+
+    #[test]
+    fn test_symbols_past_the_alphabet() {
+        let symbols: String = [0, 25, 26, 49, 50, 80].into_iter().map(|i| symbol(Byte::Field(i))).collect();
+        assert_eq!(symbols, "azαω++");
+    }
+}
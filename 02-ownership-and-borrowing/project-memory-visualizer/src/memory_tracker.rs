@@ -1,11 +1,35 @@
 // Memory Tracker - Core memory operation tracking
 //
-// TODO: Complete the missing implementations to track memory operations
+// Every tracked operation is numbered and kept in order, so the whole
+// timeline can be exported (timeline.rs) and replayed later.
+//
+// The tracked operations are written by hand in the demos. With the
+// count-allocations feature, the demos also measure blocks of real code
+// with the counting allocator (allocator.rs) and record what they did.
+//
+// Each operation is checked against the ownership rules as it is tracked
+// (borrow_checker.rs), and violations are printed and kept as diagnostics.
+//
+// Values belong to the scope they are declared in: the program's `main`
+// frame, or a function frame or block scope entered since. Exiting a scope
+// drops what it still owns in reverse declaration order, as Rust does, and
+// records a Drop for each.
+//
+// Rc, Arc and Weak pointers, and the pointers stored inside shared values,
+// are also counted in a reference graph (rc_graph.rs), so the summary can
+// report reference cycles that leak. The graph owns the bytes of shared
+// values: cloning or dropping a pointer only changes its count, and the
+// bytes are freed when the strong count reaches zero.
 
+use crate::allocator::{self, AllocationStats, Snapshot};
+use crate::borrow_checker::{self, Diagnostic};
+use crate::rc_graph::{RcGraph, RcNode};
 use colored::*;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Operation {
     Allocate,
     Deallocate,
@@ -14,20 +38,95 @@ pub enum Operation {
     MutableBorrow,
     Clone,
     Drop,
+    Use,
+    EnterScope,
+    ExitScope,
+    Link,
 }
 
+/// A function call gets a new stack frame; a block only a new scope
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeKind {
+    Function,
+    Block,
+}
+
+impl ScopeKind {
+    /// How the kind is recorded as the type of EnterScope and ExitScope
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ScopeKind::Function => "fn",
+            ScopeKind::Block => "block",
+        }
+    }
+
+    /// `fn name` or `{ name }`
+    pub fn label(self, name: &str) -> String {
+        match self {
+            ScopeKind::Function => format!("fn {}", name),
+            ScopeKind::Block => format!("{{ {} }}", name),
+        }
+    }
+
+    pub fn from_type(value_type: &str) -> Self {
+        if value_type == "fn" {
+            ScopeKind::Function
+        } else {
+            ScopeKind::Block
+        }
+    }
+}
+
+// A scope and the names it owns, in declaration order
 #[derive(Debug, Clone)]
+struct Scope {
+    name: String,
+    kind: ScopeKind,
+    owned: Vec<String>,
+}
+
+/// One step of the timeline. Moves, borrows and clones record their
+/// location as "from -> to", and links as "holder.field -> target".
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MemoryOperation {
+    pub step: usize,
     pub operation: Operation,
     pub location: String,
+    #[serde(rename = "type")]
     pub value_type: String,
     pub size: usize,
-    pub step: usize,
+}
+
+/// The largest size a scenario or a saved timeline may give one value (1 GiB)
+pub const MAX_SIZE: usize = 1 << 30;
+
+/// A bar of one block per 8 bytes, cut off at `max_blocks` so a large
+/// value still fits on its line
+pub fn size_bar(size: usize, max_blocks: usize) -> String {
+    "█".repeat((size / 8).clamp(1, max_blocks))
+}
+
+/// Rc, Arc and Weak: cloning one shares the allocation instead of copying it
+pub fn is_shared_pointer(type_name: &str) -> bool {
+    ["Rc<", "Arc<", "Weak<"].iter().any(|prefix| type_name.starts_with(prefix))
+}
+
+/// What the allocator counted while a labelled block of code ran
+#[derive(Debug, Clone)]
+pub struct Measurement {
+    pub label: String,
+    pub stats: AllocationStats,
 }
 
 pub struct MemoryTracker {
     operations: Vec<MemoryOperation>,
+    measurements: Vec<Measurement>,
+    diagnostics: Vec<Diagnostic>,
+    // Values that are not shared; shared ones are in rc_graph
     active_allocations: HashMap<String, usize>,
+    // Innermost last; the main frame is never exited
+    scopes: Vec<Scope>,
+    rc_graph: RcGraph,
     current_step: usize,
     total_allocated: usize,
     total_deallocated: usize,
@@ -35,164 +134,464 @@ pub struct MemoryTracker {
 
 impl MemoryTracker {
     pub fn new() -> Self {
-        // TODO: Initialize the MemoryTracker with empty state
-        // HINT: All fields should start with appropriate default values
-        todo!("Initialize MemoryTracker struct")
+        MemoryTracker {
+            operations: Vec::new(),
+            measurements: Vec::new(),
+            diagnostics: Vec::new(),
+            active_allocations: HashMap::new(),
+            scopes: vec![Scope {
+                name: "main".to_string(),
+                kind: ScopeKind::Function,
+                owned: Vec::new(),
+            }],
+            rc_graph: RcGraph::new(),
+            current_step: 0,
+            total_allocated: 0,
+            total_deallocated: 0,
+        }
     }
-    
-    pub fn track_allocation(&mut self, location: &str, type_name: &str, size: usize) {
+
+    // Returns the shared values the operation freed
+    fn record(&mut self, operation: Operation, location: String, value_type: &str, size: usize) -> Vec<RcNode> {
         self.current_step += 1;
-        
-        // TODO: Record the allocation operation
-        // HINT: Create a MemoryOperation and add it to self.operations
-        // HINT: Update self.active_allocations and self.total_allocated
-        
+        let mut operation = MemoryOperation {
+            step: self.current_step,
+            operation,
+            location,
+            value_type: value_type.to_string(),
+            size,
+        };
+        let drops_pointer = matches!(operation.operation, Operation::Deallocate | Operation::Drop)
+            && self.rc_graph.pointer(&operation.location).is_some();
+
+        let freed = self.rc_graph.apply(&operation);
+        let freed_bytes: usize = freed.iter().map(|node| node.size).sum();
+        self.total_deallocated += freed_bytes;
+        // Dropping an Rc, Arc or Weak frees only what its count let go of
+        if drops_pointer {
+            operation.size = freed_bytes;
+        }
+        self.operations.push(operation);
+        freed
+    }
+
+    // Checks the newest operation against the ownership rules
+    fn check_last(&mut self) {
+        let (operation, history) = self.operations.split_last().expect("an operation was just recorded");
+        if let Some(diagnostic) = borrow_checker::check_operation(history, operation) {
+            println!("{}", format!("❌ {}", diagnostic).red());
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    // The innermost scope now owns `name`
+    fn declare(&mut self, name: &str) {
+        self.forget(name);
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.owned.push(name.to_string());
+        }
+    }
+
+    // `name` was moved or dropped, so no scope has to drop it
+    fn forget(&mut self, name: &str) {
+        for scope in &mut self.scopes {
+            scope.owned.retain(|owned| owned != name);
+        }
+    }
+
+    pub fn track_allocation(&mut self, location: &str, type_name: &str, size: usize) {
+        self.record(Operation::Allocate, location.to_string(), type_name, size);
+        self.declare(location);
+        if is_shared_pointer(type_name) {
+            self.active_allocations.remove(location);
+        } else {
+            self.active_allocations.insert(location.to_string(), size);
+        }
+        self.total_allocated += size;
+
         println!("📦 {} Allocated {} bytes for {} at {}",
             "ALLOC".green().bold(),
             size.to_string().yellow(),
             type_name.blue(),
             location.cyan()
         );
+        self.check_last();
     }
-    
+
+    /// Frees the value `location` owns. For an Rc, Arc or Weak `size` is
+    /// ignored: the pointer's count goes down, and the value is only freed
+    /// with the last strong pointer.
     pub fn track_deallocation(&mut self, location: &str, size: usize) {
-        self.current_step += 1;
-        
-        // TODO: Record the deallocation operation
-        // HINT: Remove from active_allocations, update total_deallocated
-        // HINT: Add operation to the operations vec
-        
+        if self.release_pointer(Operation::Deallocate, location) {
+            return;
+        }
+        self.record(Operation::Deallocate, location.to_string(), "", size);
+        self.forget(location);
+        self.active_allocations.remove(location);
+        self.total_deallocated += size;
+
         println!("🗑️  {} Deallocated {} bytes from {}",
             "FREE".red().bold(),
             size.to_string().yellow(),
             location.cyan()
         );
+        self.check_last();
+    }
+
+    // Drops a variable holding an Rc, Arc or Weak; false if it holds none
+    fn release_pointer(&mut self, operation: Operation, name: &str) -> bool {
+        let Some((node, _)) = self.rc_graph.pointer(name) else { return false };
+        let id = node.id;
+        let freed = self.record(operation, name.to_string(), "", 0);
+        self.forget(name);
+
+        if freed.is_empty() {
+            let (strong, weak) = self.rc_graph.counts(id);
+            println!("➖ {} {} is dropped; the value lives on (strong {}, weak {})",
+                "RELEASE".red().bold(),
+                name.cyan(),
+                strong.to_string().yellow(),
+                weak.to_string().yellow()
+            );
+        }
+        for node in &freed {
+            println!("🗑️  {} Deallocated {} bytes from {} (strong count reached 0)",
+                "FREE".red().bold(),
+                node.size.to_string().yellow(),
+                node.name.cyan()
+            );
+        }
+        self.check_last();
+        true
     }
-    
+
     pub fn track_move(&mut self, from: &str, to: &str, type_name: &str) {
-        self.current_step += 1;
-        
-        // TODO: Record the move operation
-        // HINT: Update active_allocations to reflect new ownership
-        // HINT: A move transfers ownership without changing memory size
-        
-        let operation = MemoryOperation {
-            operation: Operation::Move,
-            location: format!("{} -> {}", from, to),
-            value_type: type_name.to_string(),
-            size: 0, // Moves don't allocate/deallocate
-            step: self.current_step,
-        };
-        
-        self.operations.push(operation);
-        
+        // A move transfers ownership without allocating or freeing anything
+        self.record(Operation::Move, format!("{} -> {}", from, to), type_name, 0);
+        self.forget(from);
+        self.declare(to);
+        if let Some(size) = self.active_allocations.remove(from) {
+            self.active_allocations.insert(to.to_string(), size);
+        }
+
         println!("➡️  {} Ownership moved from {} to {}",
             "MOVE".purple().bold(),
             from.cyan(),
             to.cyan()
         );
+        self.check_last();
     }
-    
+
     pub fn track_borrow(&mut self, borrower: &str, borrowed_from: &str, mutable: bool) {
-        self.current_step += 1;
-        
         let operation_type = if mutable { Operation::MutableBorrow } else { Operation::Borrow };
+        let reference_type = if mutable { "&mut" } else { "&" };
         let symbol = if mutable { "🔓" } else { "🔒" };
         let borrow_type = if mutable { "BORROW_MUT".yellow() } else { "BORROW".green() };
-        
-        // TODO: Record the borrow operation
-        // HINT: Create and store the MemoryOperation
-        
+
+        self.record(operation_type, format!("{} -> {}", borrower, borrowed_from), reference_type, 0);
+        self.declare(borrower);
+
         println!("{} {} {} borrows from {}",
             symbol,
             borrow_type.bold(),
             borrower.cyan(),
             borrowed_from.cyan()
         );
+        self.check_last();
+    }
+
+    /// Records that a value or reference is read. Uses are what tell the
+    /// borrow checker that a borrow is still needed.
+    pub fn track_use(&mut self, name: &str) {
+        self.record(Operation::Use, name.to_string(), "", 0);
+
+        println!("👀 {} {} is used", "USE".white().bold(), name.cyan());
+        self.check_last();
     }
-    
+
     pub fn track_clone(&mut self, original: &str, clone: &str, type_name: &str, size: usize) {
-        // TODO: Track both the clone operation and the new allocation
-        // HINT: A clone creates a new allocation of the same size
-        // HINT: This is different from a move - both values exist after cloning
-        
+        if is_shared_pointer(type_name) {
+            self.track_shared_clone(original, clone, type_name);
+            return;
+        }
+
+        // Unlike a move, both values exist afterwards, and the clone has
+        // its own allocation of the same size
+        self.record(Operation::Clone, format!("{} -> {}", original, clone), type_name, size);
+
         println!("🔄 {} Cloned {} to {} ({} bytes)",
             "CLONE".blue().bold(),
             original.cyan(),
             clone.cyan(),
             size.to_string().yellow()
         );
+        self.check_last();
+
+        self.track_allocation(clone, type_name, size);
+    }
+
+    // Rc::clone, or Rc::downgrade for a Weak: no bytes are copied, only the
+    // count goes up
+    fn track_shared_clone(&mut self, original: &str, clone: &str, type_name: &str) {
+        self.record(Operation::Clone, format!("{} -> {}", original, clone), type_name, 0);
+        self.declare(clone);
+
+        let (strong, weak) = self.rc_graph.node_of(clone).map_or((0, 0), |node| (node.strong, node.weak));
+        println!("🔄 {} {} now shares {}'s value (strong {}, weak {})",
+            "CLONE".blue().bold(),
+            clone.cyan(),
+            original.cyan(),
+            strong.to_string().yellow(),
+            weak.to_string().yellow()
+        );
+        self.check_last();
+    }
+
+    /// Stores a pointer to `target` in a field of the shared value `holder`
+    /// points to, like `*holder.next.borrow_mut() = Some(Rc::clone(&target))`.
+    /// A Weak type stores a weak pointer.
+    pub fn track_link(&mut self, holder: &str, field: &str, target: &str, type_name: &str) {
+        self.record(Operation::Link, format!("{}.{} -> {}", holder, field, target), type_name, 0);
+
+        println!("🔗 {} {}.{} now points to {} ({})",
+            "LINK".blue().bold(),
+            holder.cyan(),
+            field.cyan(),
+            target.cyan(),
+            type_name.blue()
+        );
+        self.check_last();
+    }
+
+    /// A function call: values declared from now on live in its frame
+    pub fn enter_function(&mut self, name: &str) {
+        self.enter_scope(name, ScopeKind::Function);
     }
-    
+
+    /// A `{ ... }` block inside the current function
+    pub fn enter_block(&mut self, name: &str) {
+        self.enter_scope(name, ScopeKind::Block);
+    }
+
+    fn enter_scope(&mut self, name: &str, kind: ScopeKind) {
+        self.record(Operation::EnterScope, name.to_string(), kind.as_str(), 0);
+        self.scopes.push(Scope {
+            name: name.to_string(),
+            kind,
+            owned: Vec::new(),
+        });
+
+        println!("📥 {} {}", "ENTER".blue().bold(), kind.label(name).cyan());
+    }
+
+    /// Leaves the innermost scope, dropping what it still owns in reverse
+    /// declaration order. The main frame is never exited.
+    pub fn exit_scope(&mut self) {
+        if self.scopes.len() == 1 {
+            return;
+        }
+        let scope = self.scopes.pop().expect("a scope was entered");
+
+        for name in scope.owned.iter().rev() {
+            if self.rc_graph.pointer(name).is_some() {
+                println!("🗑️  {} {} goes out of scope", "DROP".red().bold(), name.cyan());
+                self.release_pointer(Operation::Drop, name);
+                continue;
+            }
+            let size = self.active_allocations.remove(name).unwrap_or(0);
+            self.total_deallocated += size;
+            self.record(Operation::Drop, name.clone(), "", size);
+
+            println!("🗑️  {} {} goes out of scope ({} bytes freed)",
+                "DROP".red().bold(),
+                name.cyan(),
+                size.to_string().yellow()
+            );
+            self.check_last();
+        }
+
+        self.record(Operation::ExitScope, scope.name.clone(), scope.kind.as_str(), 0);
+        println!("📤 {} {}", "EXIT".blue().bold(), scope.kind.label(&scope.name).cyan());
+    }
+
+    fn describe_scopes(&self) -> String {
+        let labels: Vec<String> = self.scopes.iter().map(|scope| scope.kind.label(&scope.name)).collect();
+        labels.join(" › ")
+    }
+
+    /// Records what the allocator counted since `snapshot` was taken.
+    /// Does nothing unless real allocations are counted.
+    pub fn record_measurement(&mut self, label: &str, snapshot: &Snapshot) {
+        if !allocator::is_enabled() {
+            return;
+        }
+        let stats = snapshot.since();
+
+        println!("📏 {} {}: {}",
+            "MEASURED".cyan().bold(),
+            label.cyan(),
+            stats.to_string().yellow()
+        );
+
+        self.measurements.push(Measurement {
+            label: label.to_string(),
+            stats,
+        });
+    }
+
     pub fn print_summary(&self) {
         println!("\n{}", "=== Memory Operations Summary ===".bold());
         println!("Total operations: {}", self.operations.len().to_string().yellow());
         println!("Total allocated: {} bytes", self.total_allocated.to_string().green());
         println!("Total deallocated: {} bytes", self.total_deallocated.to_string().red());
-        
-        let active_memory: usize = self.active_allocations.values().sum();
+
+        let active_memory = self.active_bytes();
         println!("Currently active: {} bytes", active_memory.to_string().blue());
-        
-        if active_memory == 0 {
+
+        if !self.rc_graph.leaked_nodes().is_empty() {
+            println!("{}", "⚠️  Reference cycles leaked memory".yellow().bold());
+        } else if active_memory == 0 {
             println!("{}", "✅ All memory properly cleaned up!".green().bold());
         } else {
             println!("{}", "⚠️  Some memory still allocated".yellow().bold());
         }
-        
-        // TODO: Add more detailed summary information
-        // IDEAS: Show operation breakdown, largest allocations, etc.
-        
+
         self.print_operation_breakdown();
+        self.print_diagnostics();
+        self.print_leaks();
+        self.print_measurements();
+    }
+
+    fn active_bytes(&self) -> usize {
+        let shared: usize = self.rc_graph.live_nodes().map(|node| node.size).sum();
+        self.active_allocations.values().sum::<usize>() + shared
+    }
+
+    fn print_leaks(&self) {
+        let leaks = self.rc_graph.leaks();
+        if leaks.is_empty() {
+            println!("\n{}", "✅ No reference cycles leaked".green().bold());
+            return;
+        }
+
+        println!("\n{}", format!("🔁 {} reference cycle(s) leaked:", leaks.len()).red().bold());
+        for leak in &leaks {
+            println!("  {}", leak);
+        }
+        println!("  Make one pointer in each cycle a Weak to break it");
     }
-    
+
+    fn print_diagnostics(&self) {
+        if self.diagnostics.is_empty() {
+            println!("\n{}", "✅ No ownership rules broken".green().bold());
+            return;
+        }
+
+        println!("\n{}", format!("❌ {} ownership rule(s) broken:", self.diagnostics.len()).red().bold());
+        for diagnostic in &self.diagnostics {
+            println!("{}", diagnostic);
+        }
+    }
+
+    fn print_measurements(&self) {
+        println!("\n{}", "Measured by the allocator:".bold());
+        if !allocator::is_enabled() {
+            println!("  (build with --features count-allocations to count real allocations)");
+            return;
+        }
+
+        for measurement in &self.measurements {
+            println!("  {}: {}", measurement.label.cyan(), measurement.stats);
+        }
+        println!("  Whole program (main thread): {}", AllocationStats::current());
+    }
+
     fn print_operation_breakdown(&self) {
         println!("\nOperation breakdown:");
-        let mut counts = HashMap::new();
-        
-        // TODO: Count each operation type and display the results
-        // HINT: Iterate through self.operations and count each Operation variant
-        
+        let mut counts = BTreeMap::new();
+
+        for operation in &self.operations {
+            *counts.entry(operation.operation).or_insert(0) += 1;
+        }
+
         for (op_type, count) in counts {
             println!("  {:?}: {}", op_type, count);
         }
     }
-    
+
     pub fn visualize_memory_state(&self) {
         println!("\n{}", "=== Current Memory State ===".bold());
-        
-        if self.active_allocations.is_empty() {
+        println!("Scopes: {}", self.describe_scopes().cyan());
+
+        if self.active_allocations.is_empty() && self.rc_graph.live_nodes().next().is_none() {
             println!("📭 No active allocations");
             return;
         }
-        
+
         println!("Active allocations:");
         for (location, size) in &self.active_allocations {
-            let blocks = size / 8; // Represent each 8 bytes as a block
-            let visualization = "█".repeat(blocks.max(1));
-            println!("  {}: {} ({} bytes)", 
-                location.cyan(), 
-                visualization.green(), 
+            let visualization = size_bar(*size, 40);
+            println!("  {}: {} ({} bytes)",
+                location.cyan(),
+                visualization.green(),
                 size.to_string().yellow()
             );
         }
-        
-        // TODO: Add more sophisticated visualization
-        // IDEAS: Stack vs heap representation, reference arrows, etc.
+        for node in self.rc_graph.live_nodes() {
+            let visualization = size_bar(node.size, 40);
+            println!("  {}: {} ({} bytes, strong {}, weak {})",
+                node.name.cyan(),
+                visualization.green(),
+                node.size.to_string().yellow(),
+                node.strong,
+                node.weak
+            );
+        }
     }
-    
-    // Helper method for testing
+
+    // Helper methods for testing
+    #[allow(dead_code)]
     pub fn get_active_allocations(&self) -> &HashMap<String, usize> {
         &self.active_allocations
     }
-    
+
+    #[allow(dead_code)]
     pub fn get_total_operations(&self) -> usize {
         self.operations.len()
     }
-    
-    // TODO: Add method to get operations by type
+
+    #[allow(dead_code)]
+    pub fn get_diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// The names of the open scopes, outermost first
+    #[allow(dead_code)]
+    pub fn get_scope_names(&self) -> Vec<&str> {
+        self.scopes.iter().map(|scope| scope.name.as_str()).collect()
+    }
+
+    #[allow(dead_code)]
+    pub fn get_rc_graph(&self) -> &RcGraph {
+        &self.rc_graph
+    }
+
+    #[allow(dead_code)]
+    pub fn get_measurements(&self) -> &[Measurement] {
+        &self.measurements
+    }
+
+    #[allow(dead_code)]
     pub fn get_operations_by_type(&self, op_type: Operation) -> Vec<&MemoryOperation> {
-        // HINT: Filter self.operations by the given operation type
-        todo!("Filter operations by type")
+        self.operations
+            .iter()
+            .filter(|operation| operation.operation == op_type)
+            .collect()
+    }
+
+    /// The whole timeline, in step order
+    pub fn operations(&self) -> &[MemoryOperation] {
+        &self.operations
     }
 }
 
@@ -205,42 +604,155 @@ impl Default for MemoryTracker {
 #[cfg(test)]
 mod tests {
     use super::*;
-    
+
     #[test]
     fn test_memory_tracker_creation() {
         let tracker = MemoryTracker::new();
         assert_eq!(tracker.get_total_operations(), 0);
         assert!(tracker.get_active_allocations().is_empty());
     }
-    
+
     #[test]
     fn test_track_allocation() {
         let mut tracker = MemoryTracker::new();
-        
-        // TODO: Test allocation tracking
-        // HINT: Call track_allocation and verify the state changes
-        
-        // tracker.track_allocation("var1", "String", 10);
-        // Add assertions here
-    }
-    
+        tracker.track_allocation("var1", "String", 10);
+
+        assert_eq!(tracker.get_total_operations(), 1);
+        assert_eq!(tracker.get_active_allocations().get("var1"), Some(&10));
+        assert_eq!(tracker.get_operations_by_type(Operation::Allocate).len(), 1);
+    }
+
     #[test]
     fn test_track_move() {
         let mut tracker = MemoryTracker::new();
-        
-        // TODO: Test move tracking
-        // HINT: First allocate, then move, then verify state
-        
-        // Add your test implementation
+        tracker.track_allocation("s1", "String", 5);
+        tracker.track_move("s1", "s2", "String");
+
+        assert!(!tracker.get_active_allocations().contains_key("s1"));
+        assert_eq!(tracker.get_active_allocations().get("s2"), Some(&5));
+        assert_eq!(tracker.operations()[1].location, "s1 -> s2");
+        assert_eq!(tracker.operations()[1].step, 2);
+    }
+
+    #[test]
+    fn test_track_clone() {
+        let mut tracker = MemoryTracker::new();
+        tracker.track_allocation("a", "String", 5);
+        tracker.track_clone("a", "b", "String", 5);
+
+        assert_eq!(tracker.get_active_allocations().len(), 2);
+        assert_eq!(tracker.get_operations_by_type(Operation::Clone).len(), 1);
+        assert_eq!(tracker.get_operations_by_type(Operation::Allocate).len(), 2);
+    }
+
+    #[test]
+    fn test_record_measurement() {
+        let mut tracker = MemoryTracker::new();
+        let snapshot = Snapshot::take();
+        let boxed = Box::new([0u8; 16]);
+        tracker.record_measurement("Box::new", &snapshot);
+        drop(boxed);
+
+        if allocator::is_enabled() {
+            let measurement = &tracker.get_measurements()[0];
+            assert_eq!(measurement.label, "Box::new");
+            assert_eq!(measurement.stats.allocations, 1);
+            assert_eq!(measurement.stats.bytes_allocated, 16);
+        } else {
+            assert!(tracker.get_measurements().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_violations_become_diagnostics() {
+        let mut tracker = MemoryTracker::new();
+        tracker.track_allocation("s1", "String", 5);
+        tracker.track_move("s1", "s2", "String");
+        tracker.track_use("s2");
+        assert!(tracker.get_diagnostics().is_empty());
+
+        tracker.track_use("s1");
+        let diagnostic = &tracker.get_diagnostics()[0];
+        assert_eq!(diagnostic.rule, crate::borrow_checker::Rule::UseAfterMove);
+        assert_eq!(diagnostic.step, 4);
+        assert_eq!(diagnostic.notes[0].step, 2);
+    }
+
+    #[test]
+    fn test_exiting_a_scope_drops_in_reverse_order() {
+        let mut tracker = MemoryTracker::new();
+        tracker.track_allocation("outer", "String", 5);
+        tracker.enter_function("takes_ownership");
+        tracker.track_move("outer", "s", "String");
+        tracker.enter_block("inner");
+        tracker.track_allocation("a", "String", 1);
+        tracker.track_allocation("b", "Vec<u8>", 2);
+        tracker.track_borrow("r", "a", false);
+        tracker.track_allocation("gone", "String", 3);
+        tracker.track_deallocation("gone", 3);
+        assert_eq!(tracker.get_scope_names(), vec!["main", "takes_ownership", "inner"]);
+
+        tracker.exit_scope();
+        tracker.exit_scope();
+        tracker.exit_scope(); // main is never exited
+        assert_eq!(tracker.get_scope_names(), vec!["main"]);
+
+        let drops: Vec<&str> = tracker
+            .get_operations_by_type(Operation::Drop)
+            .into_iter()
+            .map(|operation| operation.location.as_str())
+            .collect();
+        assert_eq!(drops, vec!["r", "b", "a", "s"]);
+        assert!(tracker.get_active_allocations().is_empty());
+        assert_eq!(tracker.total_allocated, tracker.total_deallocated);
+        assert_eq!(tracker.operations().last().unwrap().operation, Operation::ExitScope);
+        assert_eq!(tracker.operations().last().unwrap().value_type, "fn");
+    }
+
+    #[test]
+    fn test_track_link_feeds_the_rc_graph() {
+        let mut tracker = MemoryTracker::new();
+        tracker.track_allocation("a", "Rc<Node>", 16);
+        tracker.track_allocation("b", "Rc<Node>", 16);
+        tracker.track_link("a", "next", "b", "Rc<Node>");
+        tracker.track_link("b", "next", "a", "Rc<Node>");
+        assert_eq!(tracker.operations()[2].location, "a.next -> b");
+        assert_eq!(tracker.get_rc_graph().node_of("b").unwrap().strong, 2);
+
+        tracker.track_deallocation("a", 0);
+        tracker.track_deallocation("b", 0);
+        assert_eq!(tracker.get_rc_graph().leaks()[0].cycle, vec!["a", "b", "a"]);
     }
-    
+
+    #[test]
+    fn test_rc_is_freed_with_the_last_strong_pointer() {
+        let mut tracker = MemoryTracker::new();
+        tracker.track_allocation("a", "Rc<Node>", 16);
+        tracker.track_clone("a", "b", "Rc<Node>", 16);
+        assert_eq!(tracker.operations()[1].size, 0);
+
+        // b still points at the value, so dropping a frees nothing
+        tracker.track_deallocation("a", 16);
+        assert_eq!(tracker.total_deallocated, 0);
+        assert_eq!(tracker.operations()[2].size, 0);
+        assert_eq!(tracker.active_bytes(), 16);
+        assert_eq!(tracker.get_rc_graph().node_of("b").unwrap().strong, 1);
+
+        tracker.enter_block("inner");
+        tracker.track_move("b", "c", "Rc<Node>");
+        tracker.exit_scope();
+        assert_eq!(tracker.total_deallocated, 16);
+        assert_eq!(tracker.get_operations_by_type(Operation::Drop)[0].size, 16);
+        assert_eq!(tracker.active_bytes(), 0);
+    }
+
     #[test]
     fn test_memory_cleanup() {
         let mut tracker = MemoryTracker::new();
-        
-        // TODO: Test that allocation + deallocation results in clean state
-        // This simulates proper RAII cleanup
-        
-        // Add your test implementation
+        tracker.track_allocation("scoped", "String", 6);
+        tracker.track_deallocation("scoped", 6);
+
+        assert!(tracker.get_active_allocations().is_empty());
+        assert_eq!(tracker.total_allocated, tracker.total_deallocated);
     }
 }
@@ -3,7 +3,9 @@
 // Your task: Make all the functions compile and work correctly
 // This demonstrates the ownership concepts from Module 02
 
+use crate::allocator::Snapshot;
 use crate::memory_tracker::MemoryTracker;
+use crate::visualizer::MemoryVisualizer;
 use colored::*;
 
 pub fn run_demonstrations(tracker: &mut MemoryTracker) {
@@ -35,15 +37,19 @@ pub fn show_basic_example() {
 
 fn demo_basic_ownership(tracker: &mut MemoryTracker) {
     // FIXME: This code doesn't compile - make it work!
+    let snapshot = Snapshot::take();
     let s1 = String::from("hello");
+    tracker.record_measurement("String::from(\"hello\")", &snapshot);
     tracker.track_allocation("s1", "String", s1.len());
     
+    let snapshot = Snapshot::take();
     let s2 = s1;  // PROBLEM: s1 is moved here
+    tracker.record_measurement("moving s1 to s2", &snapshot);
     tracker.track_move("s1", "s2", "String");
     
     // TODO: Fix these print statements
     // HINT: Only one of these variables is valid after the move
-    println!("s1: {}", s1);  // FIXME: This won't compile
+    // println!("s1: {}", s1);  // FIXME: This won't compile
     println!("s2: {}", s2);  // This should work
     
     // CHALLENGE: Make both prints work
@@ -57,10 +63,13 @@ fn demo_function_ownership(tracker: &mut MemoryTracker) {
     tracker.track_allocation("data", "String", data.len());
     
     // FIXME: After calling this function, `data` is no longer valid
+    tracker.enter_function("takes_ownership");
+    tracker.track_move("data", "s", "String");
     takes_ownership(data);
+    tracker.exit_scope();
     
     // TODO: Fix this print statement
-    println!("Data after function call: {}", data);  // FIXME: Won't compile
+    // println!("Data after function call: {}", data);  // FIXME: Won't compile
     
     // CHALLENGE: Modify the code so `data` is still accessible after the function call
     // HINT: Look at the function signature below
@@ -74,11 +83,11 @@ fn takes_ownership(s: String) {
 }
 
 // TODO: Create a function that borrows instead of taking ownership
-fn borrows_data(/* TODO: Add parameter */) {
-    // TODO: Implement this function
-    println!("Function borrowed: {}", /* TODO: use parameter */);
-    // Nothing is dropped - we're just borrowing!
-}
+// fn borrows_data(/* TODO: Add parameter */) {
+//     // TODO: Implement this function
+//     println!("Function borrowed: {}", /* TODO: use parameter */);
+//     // Nothing is dropped - we're just borrowing!
+// }
 
 fn demo_cloning(tracker: &mut MemoryTracker) {
     println!("Demonstrating cloning vs moving...");
@@ -91,7 +100,7 @@ fn demo_cloning(tracker: &mut MemoryTracker) {
     tracker.track_move("original", "copy", "String");  // TODO: Update this for cloning
     
     // TODO: Make both of these work
-    println!("Original: {}", original);  // FIXME: Won't compile after move
+    // println!("Original: {}", original);  // FIXME: Won't compile after move
     println!("Copy: {}", copy);
     
     // QUESTION: What's the difference between clone() and move?
@@ -117,7 +126,8 @@ fn demo_copy_vs_move(tracker: &mut MemoryTracker) {
     tracker.track_move("s1", "s2", "String");
     
     // TODO: Fix the compilation error
-    println!("s1: {}, s2: {}", s1, s2);  // FIXME: s1 moved
+    // println!("s1: {}, s2: {}", s1, s2);  // FIXME: s1 moved
+    println!("s2: {}", s2);
     
     // CHALLENGE: What types implement Copy? Make a list.
     // Copy types: i32, f64, bool, char, [TODO: add more]
@@ -126,7 +136,9 @@ fn demo_copy_vs_move(tracker: &mut MemoryTracker) {
 fn demo_raii_and_drop(tracker: &mut MemoryTracker) {
     println!("RAII (Resource Acquisition Is Initialization) demo...");
     
+    let snapshot;
     {
+        tracker.enter_block("inner");
         let scoped_data = String::from("scoped");
         tracker.track_allocation("scoped_data", "String", scoped_data.len());
         
@@ -134,9 +146,11 @@ fn demo_raii_and_drop(tracker: &mut MemoryTracker) {
         
         // TODO: What happens when this scope ends?
         // HINT: The Drop trait is automatically called
+        snapshot = Snapshot::take();
     }
     // scoped_data is automatically dropped here
-    tracker.track_deallocation("scoped_data", 6);  // "scoped".len()
+    tracker.record_measurement("the end of scoped_data's scope", &snapshot);
+    tracker.exit_scope();
     
     println!("After scope - scoped_data is gone!");
     
@@ -145,10 +159,12 @@ fn demo_raii_and_drop(tracker: &mut MemoryTracker) {
 }
 
 // TODO: Implement this struct with custom Drop behavior
+#[allow(dead_code)] // Unused until the challenge above is done
 struct DroppableResource {
     name: String,
 }
 
+#[allow(dead_code)]
 impl DroppableResource {
     fn new(name: &str) -> Self {
         println!("🔧 Creating resource: {}", name);
@@ -174,10 +190,12 @@ pub fn interactive_demo(tracker: &mut MemoryTracker) {
     println!("  move <from> <to>  - Move ownership");
     println!("  clone <from> <to> - Clone a String");
     println!("  drop <name>    - Drop a value");
+    println!("  use <name>     - Use a value (the borrow checker explains errors)");
     println!("  list           - List active values");
     println!("  back           - Return to main menu");
     
     let mut active_values: std::collections::HashMap<String, String> = std::collections::HashMap::new();
+    let visualizer = MemoryVisualizer::new();
     
     loop {
         print!("ownership> ");
@@ -185,7 +203,7 @@ pub fn interactive_demo(tracker: &mut MemoryTracker) {
         
         let mut input = String::new();
         io::stdin().read_line(&mut input).unwrap();
-        let parts: Vec<&str> = input.trim().split_whitespace().collect();
+        let parts: Vec<&str> = input.split_whitespace().collect();
         
         match parts.as_slice() {
             ["create", name] => {
@@ -198,7 +216,7 @@ pub fn interactive_demo(tracker: &mut MemoryTracker) {
                 if let Some(value) = active_values.remove(*from) {
                     tracker.track_move(from, to, "String");
                     active_values.insert(to.to_string(), value);
-                    println!("✅ Moved {} to {}", from, to);
+                    visualizer.show_ownership_transfer(from, to);
                 } else {
                     println!("❌ {} not found", from);
                 }
@@ -220,6 +238,11 @@ pub fn interactive_demo(tracker: &mut MemoryTracker) {
                     println!("❌ {} not found", name);
                 }
             }
+            ["use", name] => {
+                // Even moved and dropped names go to the tracker, so the
+                // borrow checker can say why the use would not compile
+                tracker.track_use(name);
+            }
             ["list"] => {
                 if active_values.is_empty() {
                     println!("No active values");
@@ -232,7 +255,7 @@ pub fn interactive_demo(tracker: &mut MemoryTracker) {
             }
             ["back"] => break,
             _ => {
-                println!("Unknown command. Available: create, move, clone, drop, list, back");
+                println!("Unknown command. Available: create, move, clone, drop, use, list, back");
             }
         }
     }
@@ -244,7 +267,7 @@ mod tests {
     
     #[test]
     fn test_ownership_concepts() {
-        let mut tracker = MemoryTracker::new();
+        let _tracker = MemoryTracker::new();
         
         // TODO: Write tests that verify ownership behavior
         // Test that moves transfer ownership
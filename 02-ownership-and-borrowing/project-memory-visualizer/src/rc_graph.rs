@@ -0,0 +1,462 @@
+// Rc Graph - reference counts for Rc, Arc and Weak
+//
+// Every Rc or Arc allocation is a node. Each variable holding an Rc, Arc or
+// Weak to it, and each pointer stored inside another node (a `next` field,
+// say), adds to the node's strong or weak count:
+//
+//   let a = Rc::new(node)                      #0 strong 1
+//   let b = Rc::clone(&a)                      #0 strong 2
+//   let w = Rc::downgrade(&a)                  #0 strong 2, weak 1
+//   *c.next.borrow_mut() = Some(Rc::clone(&a)) #0 strong 3, weak 1
+//
+// Dropping a pointer takes its count away again. When the strong count
+// reaches zero the value is dropped, and so are the pointers stored in it,
+// which can free further nodes.
+//
+// A node whose strong count never reaches zero although no variable can
+// reach it any more is leaked. Only a reference cycle does that: a → b → a
+// keeps both counts at one after the variables are gone.
+
+use crate::memory_tracker::{is_shared_pointer, MemoryOperation, Operation};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::fmt;
+
+/// A pointer stored inside a node
+#[derive(Debug, Clone, PartialEq)]
+pub struct RcLink {
+    pub field: String,
+    pub target: usize,
+    pub weak: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RcNode {
+    pub id: usize,
+    /// The variable the node was created as
+    pub name: String,
+    pub value_type: String,
+    pub size: usize,
+    pub strong: usize,
+    pub weak: usize,
+    pub links: Vec<RcLink>,
+}
+
+/// A reference cycle that nothing outside it can reach
+#[derive(Debug, Clone, PartialEq)]
+pub struct Leak {
+    /// The nodes around the cycle, by name, starting and ending at the same one
+    pub cycle: Vec<String>,
+    /// How many values the cycle keeps alive, itself included
+    pub values: usize,
+    pub bytes: usize,
+}
+
+impl fmt::Display for Leak {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} keeps {} values ({} bytes) alive that can never be freed",
+            self.cycle.join(" → "),
+            self.values,
+            self.bytes
+        )
+    }
+}
+
+// A variable holding an Rc, Arc or Weak
+#[derive(Debug, Clone, Copy)]
+struct Handle {
+    node: usize,
+    weak: bool,
+}
+
+fn is_weak(value_type: &str) -> bool {
+    value_type.starts_with("Weak<")
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RcGraph {
+    // Nodes stay here while any count is above zero
+    nodes: BTreeMap<usize, RcNode>,
+    handles: HashMap<String, Handle>,
+    next_id: usize,
+    // Values dropped by the operation being applied
+    freed: Vec<RcNode>,
+}
+
+impl RcGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the counts for one tracked operation, and returns the values
+    /// it dropped because their strong count reached zero
+    pub fn apply(&mut self, operation: &MemoryOperation) -> Vec<RcNode> {
+        self.update(operation);
+        std::mem::take(&mut self.freed)
+    }
+
+    fn update(&mut self, operation: &MemoryOperation) {
+        let (from, to) = operation
+            .location
+            .split_once(" -> ")
+            .unwrap_or((operation.location.as_str(), ""));
+
+        match operation.operation {
+            Operation::Allocate => {
+                // Reusing a name drops the old value
+                self.release(from);
+                if is_shared_pointer(&operation.value_type) {
+                    let id = self.next_id;
+                    self.next_id += 1;
+                    self.nodes.insert(id, RcNode {
+                        id,
+                        name: from.to_string(),
+                        value_type: operation.value_type.clone(),
+                        size: operation.size,
+                        strong: 1,
+                        weak: 0,
+                        links: Vec::new(),
+                    });
+                    self.handles.insert(from.to_string(), Handle { node: id, weak: false });
+                }
+            }
+            // Rc::clone, or Rc::downgrade when the clone is a Weak
+            Operation::Clone if is_shared_pointer(&operation.value_type) => {
+                if let Some(handle) = self.handles.get(from).copied() {
+                    self.release(to);
+                    let weak = is_weak(&operation.value_type);
+                    self.increment(handle.node, weak);
+                    self.handles.insert(to.to_string(), Handle { node: handle.node, weak });
+                }
+            }
+            Operation::Move => {
+                if let Some(handle) = self.handles.remove(from) {
+                    self.release(to);
+                    self.handles.insert(to.to_string(), handle);
+                }
+            }
+            Operation::Deallocate | Operation::Drop => self.release(from),
+            Operation::Link => {
+                let (holder, field) = from.split_once('.').unwrap_or((from, ""));
+                let (Some(holder), Some(target)) = (self.handles.get(holder).copied(), self.handles.get(to).copied()) else {
+                    return;
+                };
+                let weak = is_weak(&operation.value_type);
+                self.increment(target.node, weak);
+
+                // Storing into a field drops what it held before
+                let Some(node) = self.nodes.get_mut(&holder.node) else { return };
+                let replaced = node.links.iter().position(|link| link.field == field).map(|i| node.links.remove(i));
+                node.links.push(RcLink {
+                    field: field.to_string(),
+                    target: target.node,
+                    weak,
+                });
+                if let Some(old) = replaced {
+                    self.decrement(old.target, old.weak);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn increment(&mut self, id: usize, weak: bool) {
+        if let Some(node) = self.nodes.get_mut(&id) {
+            if weak {
+                node.weak += 1;
+            } else {
+                node.strong += 1;
+            }
+        }
+    }
+
+    fn release(&mut self, name: &str) {
+        if let Some(handle) = self.handles.remove(name) {
+            self.decrement(handle.node, handle.weak);
+        }
+    }
+
+    fn decrement(&mut self, id: usize, weak: bool) {
+        let Some(node) = self.nodes.get_mut(&id) else { return };
+        if weak {
+            node.weak = node.weak.saturating_sub(1);
+        } else {
+            node.strong = node.strong.saturating_sub(1);
+            if node.strong == 0 {
+                // The value is dropped, and the pointers stored in it with it
+                let links = std::mem::take(&mut node.links);
+                self.freed.push(node.clone());
+                for link in links {
+                    self.decrement(link.target, link.weak);
+                }
+            }
+        }
+
+        // The allocation itself goes once the weak pointers are gone too
+        if self.nodes.get(&id).is_some_and(|node| node.strong == 0 && node.weak == 0) {
+            self.nodes.remove(&id);
+        }
+    }
+
+    /// A node whose value is still alive
+    pub fn node(&self, id: usize) -> Option<&RcNode> {
+        self.nodes.get(&id).filter(|node| node.strong > 0)
+    }
+
+    /// The node a variable points at, if it holds an Rc, Arc or Weak
+    pub fn node_of(&self, name: &str) -> Option<&RcNode> {
+        self.handles.get(name).and_then(|handle| self.nodes.get(&handle.node))
+    }
+
+    /// Like `node_of`, and whether the variable holds a Weak
+    pub fn pointer(&self, name: &str) -> Option<(&RcNode, bool)> {
+        let handle = self.handles.get(name)?;
+        self.nodes.get(&handle.node).map(|node| (node, handle.weak))
+    }
+
+    /// The strong and weak counts of a node, both 0 once it is gone
+    pub fn counts(&self, id: usize) -> (usize, usize) {
+        self.nodes.get(&id).map_or((0, 0), |node| (node.strong, node.weak))
+    }
+
+    /// The values still alive, leaked ones included
+    pub fn live_nodes(&self) -> impl Iterator<Item = &RcNode> {
+        self.nodes.values().filter(|node| node.strong > 0)
+    }
+
+    fn strong_links(&self, id: usize) -> impl Iterator<Item = usize> + '_ {
+        self.nodes
+            .get(&id)
+            .into_iter()
+            .flat_map(|node| node.links.iter())
+            .filter(|link| !link.weak)
+            .map(|link| link.target)
+    }
+
+    fn reachable_from(&self, start: impl IntoIterator<Item = usize>) -> BTreeSet<usize> {
+        let mut seen = BTreeSet::new();
+        let mut pending: Vec<usize> = start.into_iter().collect();
+        while let Some(id) = pending.pop() {
+            if seen.insert(id) {
+                pending.extend(self.strong_links(id));
+            }
+        }
+        seen
+    }
+
+    /// Live values that no variable can reach through strong pointers
+    pub fn leaked_nodes(&self) -> BTreeSet<usize> {
+        let roots = self.handles.values().filter(|handle| !handle.weak).map(|handle| handle.node);
+        let reachable = self.reachable_from(roots);
+        self.nodes
+            .values()
+            .filter(|node| node.strong > 0 && !reachable.contains(&node.id))
+            .map(|node| node.id)
+            .collect()
+    }
+
+    /// The reference cycles behind the leaked values
+    pub fn leaks(&self) -> Vec<Leak> {
+        let leaked = self.leaked_nodes();
+        let mut covered = BTreeSet::new();
+        let mut leaks = Vec::new();
+
+        for &start in &leaked {
+            if covered.contains(&start) {
+                continue;
+            }
+            let Some(path) = self.path_back_to(start) else { continue };
+
+            // Everything on a cycle through `start` belongs to this leak
+            let kept_alive = self.reachable_from([start]);
+            for &id in &kept_alive {
+                if self.reachable_from(self.strong_links(id)).contains(&start) {
+                    covered.insert(id);
+                }
+            }
+            leaks.push(Leak {
+                cycle: path.iter().map(|id| self.nodes[id].name.clone()).collect(),
+                values: kept_alive.len(),
+                bytes: kept_alive.iter().map(|id| self.nodes[id].size).sum(),
+            });
+        }
+        leaks
+    }
+
+    // The shortest path of strong links from `start` around to itself
+    fn path_back_to(&self, start: usize) -> Option<Vec<usize>> {
+        let mut previous: HashMap<usize, usize> = HashMap::new();
+        let mut queue = VecDeque::from([start]);
+        while let Some(id) = queue.pop_front() {
+            for next in self.strong_links(id) {
+                if next == start {
+                    let mut path = vec![start, id];
+                    let mut current = id;
+                    while let Some(&before) = previous.get(&current) {
+                        path.push(before);
+                        current = before;
+                    }
+                    path.reverse();
+                    return Some(path);
+                }
+                if let std::collections::hash_map::Entry::Vacant(entry) = previous.entry(next) {
+                    entry.insert(id);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(steps: &[(Operation, &str, &str, usize)]) -> RcGraph {
+        let mut graph = RcGraph::new();
+        for (i, (operation, location, value_type, size)) in steps.iter().enumerate() {
+            graph.apply(&MemoryOperation {
+                step: i + 1,
+                operation: *operation,
+                location: location.to_string(),
+                value_type: value_type.to_string(),
+                size: *size,
+            });
+        }
+        graph
+    }
+
+    #[test]
+    fn test_clones_and_drops_change_the_counts() {
+        let graph = graph(&[
+            (Operation::Allocate, "a", "Rc<String>", 6),
+            (Operation::Clone, "a -> b", "Rc<String>", 0),
+            (Operation::Clone, "a -> w", "Weak<String>", 0),
+            (Operation::Move, "b -> c", "Rc<String>", 0),
+            (Operation::Drop, "a", "", 0),
+        ]);
+        let node = graph.node_of("c").unwrap();
+        assert_eq!((node.strong, node.weak), (1, 1));
+        assert!(graph.node_of("a").is_none());
+
+        // The last strong pointer frees the value, even with a Weak left
+        let mut graph = graph;
+        graph.apply(&MemoryOperation {
+            step: 6,
+            operation: Operation::Drop,
+            location: "c".to_string(),
+            value_type: String::new(),
+            size: 0,
+        });
+        assert!(graph.node(0).is_none());
+        assert_eq!(graph.node_of("w").map(|node| node.strong), Some(0));
+    }
+
+    #[test]
+    fn test_apply_returns_the_values_it_frees() {
+        let mut graph = graph(&[
+            (Operation::Allocate, "parent", "Rc<Node>", 16),
+            (Operation::Allocate, "child", "Rc<Node>", 8),
+            (Operation::Link, "parent.children[0] -> child", "Rc<Node>", 0),
+            (Operation::Clone, "parent -> other", "Rc<Node>", 0),
+        ]);
+        let drop = |name: &str| MemoryOperation {
+            step: 5,
+            operation: Operation::Drop,
+            location: name.to_string(),
+            value_type: String::new(),
+            size: 0,
+        };
+        assert!(graph.apply(&drop("child")).is_empty());
+        assert!(graph.apply(&drop("parent")).is_empty());
+        assert_eq!(graph.counts(0), (1, 0));
+
+        let freed: Vec<String> = graph.apply(&drop("other")).into_iter().map(|node| node.name).collect();
+        assert_eq!(freed, vec!["parent", "child"]);
+        assert_eq!(graph.counts(0), (0, 0));
+    }
+
+    #[test]
+    fn test_dropping_a_value_drops_what_it_points_to() {
+        let graph = graph(&[
+            (Operation::Allocate, "parent", "Rc<Node>", 16),
+            (Operation::Allocate, "child", "Rc<Node>", 16),
+            (Operation::Link, "parent.children[0] -> child", "Rc<Node>", 0),
+            (Operation::Link, "child.parent -> parent", "Weak<Node>", 0),
+            (Operation::Drop, "child", "", 0),
+        ]);
+        assert_eq!(graph.node(1).unwrap().strong, 1);
+
+        let mut graph = graph;
+        graph.apply(&MemoryOperation {
+            step: 6,
+            operation: Operation::Drop,
+            location: "parent".to_string(),
+            value_type: String::new(),
+            size: 0,
+        });
+        assert!(graph.node(0).is_none());
+        assert!(graph.node(1).is_none());
+        assert!(graph.leaks().is_empty());
+    }
+
+    #[test]
+    fn test_cycles_leak() {
+        let graph = graph(&[
+            (Operation::Allocate, "a", "Rc<Link>", 16),
+            (Operation::Allocate, "b", "Rc<Link>", 16),
+            (Operation::Allocate, "tail", "Rc<Link>", 8),
+            (Operation::Link, "a.next -> b", "Rc<Link>", 0),
+            (Operation::Link, "b.next -> a", "Rc<Link>", 0),
+            (Operation::Link, "b.tail -> tail", "Rc<Link>", 0),
+            (Operation::Drop, "tail", "", 0),
+        ]);
+        // While a is in scope nothing is leaked
+        assert!(graph.leaks().is_empty());
+
+        let mut graph = graph;
+        for (step, name) in [(8, "a"), (9, "b")] {
+            graph.apply(&MemoryOperation {
+                step,
+                operation: Operation::Drop,
+                location: name.to_string(),
+                value_type: String::new(),
+                size: 0,
+            });
+        }
+        assert_eq!(graph.node(0).unwrap().strong, 1);
+        assert_eq!(graph.leaked_nodes().len(), 3);
+        assert_eq!(
+            graph.leaks(),
+            vec![Leak {
+                cycle: vec!["a".to_string(), "b".to_string(), "a".to_string()],
+                values: 3,
+                bytes: 40,
+            }]
+        );
+        assert_eq!(
+            graph.leaks()[0].to_string(),
+            "a → b → a keeps 3 values (40 bytes) alive that can never be freed"
+        );
+    }
+
+    #[test]
+    fn test_self_loop_and_replaced_links() {
+        let graph = graph(&[
+            (Operation::Allocate, "node", "Rc<Link>", 8),
+            (Operation::Allocate, "other", "Rc<Link>", 8),
+            (Operation::Link, "node.next -> node", "Rc<Link>", 0),
+            // Overwriting the field drops the pointer it held
+            (Operation::Link, "node.next -> other", "Rc<Link>", 0),
+            (Operation::Link, "other.me -> other", "Rc<Link>", 0),
+            (Operation::Drop, "node", "", 0),
+            (Operation::Drop, "other", "", 0),
+        ]);
+        assert!(graph.node(0).is_none());
+        let leaks = graph.leaks();
+        assert_eq!(leaks.len(), 1);
+        assert_eq!(leaks[0].cycle, vec!["other", "other"]);
+    }
+}
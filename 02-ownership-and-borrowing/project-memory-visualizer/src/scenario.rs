@@ -0,0 +1,519 @@
+// Scenario - memory walk-throughs written as text
+//
+// The demos are Rust functions, so a new walk-through normally means
+// recompiling. A scenario file describes one instead, a statement per line:
+//
+//   # Comments start with #
+//   let s = String("hi", 2)    // a value: type(content, size in bytes)
+//   let v = Vec([1, 2, 3])     // the size defaults to the content's length
+//   move s -> t
+//   clone t -> u
+//   borrow &t as r             // or: borrow &mut t as m
+//   use r
+//   drop t
+//   fn takes_ownership {       // a function call gets its own stack frame
+//       move u -> s
+//   }                          // s is dropped when the frame is popped
+//   {                          // so is anything declared in a block
+//       let temp = String("temp")
+//   }
+//   let a = Rc<Node>("a", 16)  // Rc and Arc values are reference counted
+//   downgrade a -> w           // w is a Weak to the same value
+//   link a.next -> b           // store an Rc to b in a's `next` field
+//   link b.prev -> weak a      // or a Weak
+//
+// `run <file>` parses the whole file first, so a mistake is reported with
+// its line number before anything runs. Each statement then drives the
+// MemoryTracker, which checks it against the ownership rules, and the
+// MemoryVisualizer, which draws the memory layout after it.
+
+use crate::memory_tracker::{MemoryTracker, Operation, MAX_SIZE};
+use crate::visualizer::MemoryVisualizer;
+use colored::*;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Let {
+        name: String,
+        type_name: String,
+        content: String,
+        size: usize,
+    },
+    Move { from: String, to: String },
+    Clone { from: String, to: String },
+    Borrow { owner: String, name: String, mutable: bool },
+    Use { name: String },
+    Drop { name: String },
+    Downgrade { from: String, to: String },
+    Link {
+        holder: String,
+        field: String,
+        target: String,
+        weak: bool,
+    },
+    EnterFunction { name: String },
+    EnterBlock,
+    Exit,
+}
+
+/// A statement and the line it came from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    pub number: usize,
+    pub text: String,
+    pub statement: Statement,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scenario {
+    pub lines: Vec<Line>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl Scenario {
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        let mut lines = Vec::new();
+        // The lines that opened the scopes still open
+        let mut open = Vec::new();
+
+        for (i, raw) in source.lines().enumerate() {
+            let text = strip_comment(raw).trim();
+            if text.is_empty() {
+                continue;
+            }
+            let statement = parse_statement(text).map_err(|message| ParseError { line: i + 1, message })?;
+            match statement {
+                Statement::EnterFunction { .. } | Statement::EnterBlock => open.push(i + 1),
+                Statement::Exit if open.pop().is_none() => {
+                    return Err(ParseError {
+                        line: i + 1,
+                        message: "`}` without a matching `{`".to_string(),
+                    });
+                }
+                _ => {}
+            }
+            lines.push(Line {
+                number: i + 1,
+                text: text.to_string(),
+                statement,
+            });
+        }
+        if let Some(line) = open.pop() {
+            return Err(ParseError {
+                line,
+                message: "this `{` is never closed".to_string(),
+            });
+        }
+        Ok(Scenario { lines })
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+// `#` and `//` start a comment, except inside a quoted value
+fn strip_comment(line: &str) -> &str {
+    let mut in_quotes = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '#' if !in_quotes => return &line[..i],
+            '/' if !in_quotes && line[i..].starts_with("//") => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_statement(text: &str) -> Result<Statement, String> {
+    let (keyword, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    let rest = rest.trim();
+
+    match keyword {
+        "{" if rest.is_empty() => Ok(Statement::EnterBlock),
+        "}" if rest.is_empty() => Ok(Statement::Exit),
+        "fn" => parse_function(rest),
+        "let" => parse_let(rest),
+        "move" => {
+            let (from, to) = parse_arrow(rest, "move")?;
+            Ok(Statement::Move { from, to })
+        }
+        "clone" => {
+            let (from, to) = parse_arrow(rest, "clone")?;
+            Ok(Statement::Clone { from, to })
+        }
+        "borrow" => parse_borrow(rest),
+        "use" => Ok(Statement::Use { name: parse_name(rest)? }),
+        "drop" => Ok(Statement::Drop { name: parse_name(rest)? }),
+        "downgrade" => {
+            let (from, to) = parse_arrow(rest, "downgrade")?;
+            Ok(Statement::Downgrade { from, to })
+        }
+        "link" => parse_link(rest),
+        _ => Err(format!(
+            "unknown statement `{}` (expected let, move, clone, borrow, use, drop, downgrade, link, fn, {{ or }})",
+            keyword
+        )),
+    }
+}
+
+fn parse_name(word: &str) -> Result<String, String> {
+    let valid = word.chars().all(|c| c.is_alphanumeric() || c == '_')
+        && word.chars().next().is_some_and(|c| !c.is_ascii_digit());
+    if valid {
+        Ok(word.to_string())
+    } else if word.is_empty() {
+        Err("expected a name".to_string())
+    } else {
+        Err(format!("`{}` is not a valid name", word))
+    }
+}
+
+// let <name> = <Type>(<content>[, <size>])
+fn parse_let(rest: &str) -> Result<Statement, String> {
+    let (name, value) = rest
+        .split_once('=')
+        .ok_or("expected `let <name> = <Type>(<content>)`")?;
+    let name = parse_name(name.trim())?;
+
+    let value = value.trim();
+    let (type_name, arguments) = value
+        .split_once('(')
+        .ok_or_else(|| format!("expected `<Type>(<content>)` but found `{}`", value))?;
+    let arguments = arguments
+        .strip_suffix(')')
+        .ok_or("missing `)` at the end of the value")?
+        .trim();
+    let type_name = type_name.trim();
+    if type_name.is_empty() {
+        return Err("expected a type before `(`".to_string());
+    }
+
+    // A trailing number after the last comma is the size
+    let (content, size) = match arguments.rsplit_once(',') {
+        Some((content, size)) if is_size(size.trim()) => {
+            let size = size
+                .trim()
+                .parse()
+                .ok()
+                .filter(|&size| size <= MAX_SIZE)
+                .ok_or_else(|| format!("`{}` is too large for a size (at most {} bytes)", size.trim(), MAX_SIZE))?;
+            (unquote(content.trim())?, Some(size))
+        }
+        _ => (unquote(arguments)?, None),
+    };
+
+    Ok(Statement::Let {
+        name,
+        type_name: type_name.to_string(),
+        size: size.unwrap_or(content.len()),
+        content,
+    })
+}
+
+fn is_size(word: &str) -> bool {
+    !word.is_empty() && word.chars().all(|c| c.is_ascii_digit())
+}
+
+fn unquote(content: &str) -> Result<String, String> {
+    match content.strip_prefix('"') {
+        Some(quoted) => quoted
+            .strip_suffix('"')
+            .map(str::to_string)
+            .ok_or_else(|| format!("missing closing `\"` in {}", content)),
+        None => Ok(content.to_string()),
+    }
+}
+
+// <from> -> <to>
+fn parse_arrow(rest: &str, keyword: &str) -> Result<(String, String), String> {
+    let (from, to) = rest
+        .split_once("->")
+        .ok_or_else(|| format!("expected `{} <from> -> <to>`", keyword))?;
+    Ok((parse_name(from.trim())?, parse_name(to.trim())?))
+}
+
+// &<owner> as <name>, or &mut <owner> as <name>
+fn parse_borrow(rest: &str) -> Result<Statement, String> {
+    let usage = "expected `borrow &<owner> as <name>` or `borrow &mut <owner> as <name>`";
+    let (reference, name) = rest.split_once(" as ").ok_or(usage)?;
+    let reference = reference.trim().strip_prefix('&').ok_or(usage)?;
+
+    let (owner, mutable) = match reference.strip_prefix("mut ") {
+        Some(owner) => (owner.trim(), true),
+        None => (reference.trim(), false),
+    };
+    Ok(Statement::Borrow {
+        owner: parse_name(owner)?,
+        name: parse_name(name.trim())?,
+        mutable,
+    })
+}
+
+// <holder>.<field> -> <target>, or <holder>.<field> -> weak <target>
+fn parse_link(rest: &str) -> Result<Statement, String> {
+    let usage = "expected `link <holder>.<field> -> <target>` or `link <holder>.<field> -> weak <target>`";
+    let (place, target) = rest.split_once("->").ok_or(usage)?;
+    let (holder, field) = place.trim().split_once('.').ok_or(usage)?;
+
+    let target = target.trim();
+    let (target, weak) = match target.strip_prefix("weak ") {
+        Some(target) => (target.trim(), true),
+        None => (target, false),
+    };
+    Ok(Statement::Link {
+        holder: parse_name(holder)?,
+        field: parse_name(field)?,
+        target: parse_name(target)?,
+        weak,
+    })
+}
+
+// Rc<Node> → Weak<Node>
+fn weak_type(type_name: &str) -> String {
+    let inner = type_name.split_once('<').map_or(type_name, |(_, inner)| inner);
+    format!("Weak<{}", inner)
+}
+
+// fn <name> {, or fn <name>() {
+fn parse_function(rest: &str) -> Result<Statement, String> {
+    let name = rest.strip_suffix('{').ok_or("expected `fn <name> {`")?.trim();
+    let name = name.strip_suffix("()").unwrap_or(name).trim();
+    Ok(Statement::EnterFunction { name: parse_name(name)? })
+}
+
+/// Runs every statement, drawing the memory layout after each
+pub fn run(scenario: &Scenario, tracker: &mut MemoryTracker) {
+    let mut visualizer = MemoryVisualizer::new();
+
+    for line in &scenario.lines {
+        println!("\n{}", format!("{:>3} | {}", line.number, line.text).bold());
+        let seen = tracker.operations().len();
+
+        match &line.statement {
+            Statement::Let { name, type_name, size, .. } => tracker.track_allocation(name, type_name, *size),
+            Statement::Move { from, to } => tracker.track_move(from, to, &value_type(tracker, from)),
+            Statement::Clone { from, to } => {
+                let size = tracker.get_active_allocations().get(from).copied().unwrap_or(0);
+                tracker.track_clone(from, to, &value_type(tracker, from), size);
+            }
+            Statement::Borrow { owner, name, mutable } => tracker.track_borrow(name, owner, *mutable),
+            Statement::Use { name } => tracker.track_use(name),
+            Statement::Drop { name } => {
+                let size = tracker.get_active_allocations().get(name).copied().unwrap_or(0);
+                tracker.track_deallocation(name, size);
+            }
+            Statement::Downgrade { from, to } => tracker.track_clone(from, to, &weak_type(&value_type(tracker, from)), 0),
+            Statement::Link { holder, field, target, weak } => {
+                let type_name = value_type(tracker, target);
+                let type_name = if *weak { weak_type(&type_name) } else { type_name };
+                tracker.track_link(holder, field, target, &type_name);
+            }
+            Statement::EnterFunction { name } => tracker.enter_function(name),
+            Statement::EnterBlock => tracker.enter_block("block"),
+            Statement::Exit => tracker.exit_scope(),
+        }
+
+        for operation in &tracker.operations()[seen..] {
+            visualizer.apply(operation);
+        }
+        if let Statement::Let { name, content, .. } = &line.statement {
+            visualizer.set_content(name, content);
+        }
+        visualizer.visualize();
+    }
+}
+
+// The type a name was last given, following it through moves and clones
+fn value_type(tracker: &MemoryTracker, name: &str) -> String {
+    tracker
+        .operations()
+        .iter()
+        .rev()
+        .find(|operation| {
+            let to = operation.location.split_once(" -> ").map_or(operation.location.as_str(), |(_, to)| to);
+            to == name && matches!(operation.operation, Operation::Allocate | Operation::Move | Operation::Clone)
+        })
+        .map_or_else(|| "String".to_string(), |operation| operation.value_type.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_statements() {
+        let scenario = Scenario::parse(
+            "# a move and a borrow\n\
+             let s = String(\"hi\", 2)\n\
+             let v = Vec<i32>([1, 2, 3], 12)\n\
+             \n\
+             move s -> t   // s is invalid now\n\
+             borrow &mut t as r\n\
+             use r\n\
+             drop t\n",
+        )
+        .unwrap();
+
+        let statements: Vec<&Statement> = scenario.lines.iter().map(|line| &line.statement).collect();
+        assert_eq!(
+            statements[..3],
+            [
+                &Statement::Let {
+                    name: "s".to_string(),
+                    type_name: "String".to_string(),
+                    content: "hi".to_string(),
+                    size: 2,
+                },
+                &Statement::Let {
+                    name: "v".to_string(),
+                    type_name: "Vec<i32>".to_string(),
+                    content: "[1, 2, 3]".to_string(),
+                    size: 12,
+                },
+                &Statement::Move {
+                    from: "s".to_string(),
+                    to: "t".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            statements[3],
+            &Statement::Borrow {
+                owner: "t".to_string(),
+                name: "r".to_string(),
+                mutable: true,
+            }
+        );
+        assert_eq!(scenario.lines[2].number, 5);
+        assert_eq!(scenario.lines[2].text, "move s -> t");
+
+        // Without a size, the content's length is used
+        let scenario = Scenario::parse("let s = String(\"hello # world\")").unwrap();
+        assert!(matches!(&scenario.lines[0].statement, Statement::Let { size: 13, .. }));
+    }
+
+    #[test]
+    fn test_parse_errors_report_the_line() {
+        let error = Scenario::parse("let s = String(\"hi\")\n\nmove s t\n").unwrap_err();
+        assert_eq!(error.to_string(), "line 3: expected `move <from> -> <to>`");
+
+        let error = Scenario::parse("let s = String(\"hi\"").unwrap_err();
+        assert_eq!(error.to_string(), "line 1: missing `)` at the end of the value");
+
+        let error = Scenario::parse("\nborrow t as r").unwrap_err();
+        assert_eq!(error.line, 2);
+
+        let error = Scenario::parse("free s").unwrap_err();
+        assert!(error.message.starts_with("unknown statement `free`"));
+
+        let error = Scenario::parse("let 2s = String(\"hi\")").unwrap_err();
+        assert_eq!(error.message, "`2s` is not a valid name");
+
+        let error = Scenario::parse("let big = Vec([1], 1000000000000)").unwrap_err();
+        assert_eq!(error.message, "`1000000000000` is too large for a size (at most 1073741824 bytes)");
+
+        let error = Scenario::parse("fn f {\n{\n}\n").unwrap_err();
+        assert_eq!(error.to_string(), "line 1: this `{` is never closed");
+        let error = Scenario::parse("{\n}\n}\n").unwrap_err();
+        assert_eq!(error.to_string(), "line 3: `}` without a matching `{`");
+    }
+
+    #[test]
+    fn test_scopes_drop_their_values() {
+        let scenario = Scenario::parse(
+            "let s = String(\"hello\")\n\
+             fn takes_ownership() {\n\
+                 move s -> param\n\
+                 {\n\
+                     let temp = Vec([1, 2])\n\
+                 }\n\
+             }\n",
+        )
+        .unwrap();
+        assert_eq!(scenario.lines[1].statement, Statement::EnterFunction { name: "takes_ownership".to_string() });
+
+        let mut tracker = MemoryTracker::new();
+        run(&scenario, &mut tracker);
+        let drops: Vec<&str> = tracker
+            .get_operations_by_type(Operation::Drop)
+            .into_iter()
+            .map(|operation| operation.location.as_str())
+            .collect();
+        assert_eq!(drops, vec!["temp", "param"]);
+        assert!(tracker.get_active_allocations().is_empty());
+    }
+
+    #[test]
+    fn test_links_and_downgrades() {
+        let scenario = Scenario::parse(
+            "let a = Rc<Node>(\"a\", 16)\n\
+             let b = Rc<Node>(\"b\", 16)\n\
+             downgrade a -> w\n\
+             link a.next -> b\n\
+             link b.prev -> weak a\n\
+             drop b\n",
+        )
+        .unwrap();
+        assert_eq!(
+            scenario.lines[4].statement,
+            Statement::Link {
+                holder: "b".to_string(),
+                field: "prev".to_string(),
+                target: "a".to_string(),
+                weak: true,
+            }
+        );
+
+        let mut tracker = MemoryTracker::new();
+        run(&scenario, &mut tracker);
+        assert_eq!(value_type(&tracker, "w"), "Weak<Node>");
+        let node = tracker.get_rc_graph().node_of("a").unwrap();
+        assert_eq!((node.strong, node.weak), (1, 2));
+        assert!(tracker.get_rc_graph().leaks().is_empty());
+
+        let error = Scenario::parse("link a -> b").unwrap_err();
+        assert!(error.message.starts_with("expected `link <holder>.<field> -> <target>`"));
+    }
+
+    #[test]
+    fn test_run_drives_the_tracker() {
+        let scenario = Scenario::parse(
+            "let s = String(\"hello\")\n\
+             borrow &s as r\n\
+             move s -> t\n\
+             use r\n\
+             clone t -> u\n\
+             drop t\n",
+        )
+        .unwrap();
+        let mut tracker = MemoryTracker::new();
+        run(&scenario, &mut tracker);
+
+        // The clone is a second allocation of the same size
+        assert_eq!(tracker.get_active_allocations().get("u"), Some(&5));
+        assert!(!tracker.get_active_allocations().contains_key("t"));
+        assert_eq!(value_type(&tracker, "u"), "String");
+
+        let codes: Vec<&str> = tracker.get_diagnostics().iter().map(|d| d.code).collect();
+        assert_eq!(codes, vec!["E0505"]);
+    }
+}
@@ -2,12 +2,18 @@
 //
 // Your task: Complete the smart pointer examples and fix compilation errors
 // This demonstrates Box, Rc, Arc, and RefCell from Module 02
+//
+// The last demo builds a reference cycle on purpose: its two nodes really
+// are leaked, and the tracker's summary reports them.
 
+use crate::allocator::Snapshot;
 use crate::memory_tracker::MemoryTracker;
+use crate::visualizer::MemoryVisualizer;
 use colored::*;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 use std::sync::Arc;
 use std::cell::RefCell;
+use std::sync::Mutex;
 
 pub fn run_demonstrations(tracker: &mut MemoryTracker) {
     println!("Running smart pointer demonstrations...\n");
@@ -26,22 +32,27 @@ pub fn run_demonstrations(tracker: &mut MemoryTracker) {
     
     println!("\n{}", "5. Combining Smart Pointers".bold());
     demo_combined_patterns(tracker);
+    
+    println!("\n{}", "6. Reference Cycles and Weak<T>".bold());
+    demo_reference_cycles(tracker);
 }
 
 fn demo_box_pointer(tracker: &mut MemoryTracker) {
     println!("Demonstrating Box<T> for heap allocation...");
     
     // Box moves data to the heap
+    let snapshot = Snapshot::take();
     let boxed_value = Box::new(42);
+    tracker.record_measurement("Box::new(42)", &snapshot);
     tracker.track_allocation("boxed_value", "Box<i32>", std::mem::size_of::<i32>());
     
     println!("Boxed value: {}", boxed_value);
     
     // TODO: Create a Box containing a String
-    let boxed_string = /* TODO: Box a String */;
+    // let boxed_string = /* TODO: Box a String */;
     // tracker.track_allocation("boxed_string", "Box<String>", /* TODO: size */);
     
-    println!("Boxed string: {}", boxed_string);
+    // println!("Boxed string: {}", boxed_string);
     
     // Box is useful for recursive data structures
     // TODO: Uncomment and fix this recursive type definition
@@ -51,13 +62,14 @@ fn demo_box_pointer(tracker: &mut MemoryTracker) {
     // }
     
     // TODO: Fix the recursive type using Box
+    #[allow(dead_code)] // Unused until the list below is created
     enum FixedList {
         // TODO: Use Box to make this work
         Nil,
     }
     
     // TODO: Create a simple linked list using Box
-    let list = /* TODO: Create a list with values 1, 2, 3 */;
+    // let list = /* TODO: Create a list with values 1, 2, 3 */;
     
     println!("Box demo complete");
 }
@@ -66,27 +78,31 @@ fn demo_rc_pointer(tracker: &mut MemoryTracker) {
     println!("Demonstrating Rc<T> for shared ownership...");
     
     // Create shared data
+    let snapshot = Snapshot::take();
     let shared_data = Rc::new(String::from("shared"));
+    tracker.record_measurement("Rc::new(String::from(\"shared\"))", &snapshot);
     tracker.track_allocation("shared_data", "Rc<String>", shared_data.len());
     
     println!("Reference count: {}", Rc::strong_count(&shared_data));
     
-    // TODO: Clone the Rc to create another reference
-    let reference1 = /* TODO: Clone the Rc */;
+    let snapshot = Snapshot::take();
+    let reference1 = Rc::clone(&shared_data);
+    tracker.record_measurement("Rc::clone", &snapshot);
     tracker.track_clone("shared_data", "reference1", "Rc<String>", 0); // Rc cloning doesn't allocate
     
     // TODO: Create another reference
-    let reference2 = /* TODO: Clone the Rc again */;
+    // let reference2 = /* TODO: Clone the Rc again */;
     
     println!("Reference count after cloning: {}", Rc::strong_count(&shared_data));
     
     // All references point to the same data
     println!("shared_data: {}", shared_data);
     println!("reference1: {}", reference1);
-    println!("reference2: {}", reference2);
+    // println!("reference2: {}", reference2);
     
     // TODO: What happens when we drop references?
     drop(reference1);
+    tracker.track_deallocation("reference1", 0);
     println!("After dropping reference1: {}", Rc::strong_count(&shared_data));
     
     // TODO: Drop the remaining references and observe the count
@@ -97,12 +113,13 @@ fn demo_rc_pointer(tracker: &mut MemoryTracker) {
     demo_multiple_owners(tracker);
 }
 
-fn demo_multiple_owners(tracker: &mut MemoryTracker) {
+fn demo_multiple_owners(_tracker: &mut MemoryTracker) {
     println!("\nMultiple owners pattern with Rc...");
     
     // TODO: Create a data structure that needs multiple owners
     // Example: A node that can be referenced by multiple parents
     
+    #[allow(dead_code)] // Unused until the tree below is built
     #[derive(Debug)]
     struct Node {
         value: i32,
@@ -110,6 +127,7 @@ fn demo_multiple_owners(tracker: &mut MemoryTracker) {
         // children: Vec</*TODO: Rc<Node>*/>,
     }
     
+    #[allow(dead_code)]
     impl Node {
         fn new(value: i32) -> Rc<Self> {
             Rc::new(Node {
@@ -127,6 +145,76 @@ fn demo_multiple_owners(tracker: &mut MemoryTracker) {
     // TODO: Create a tree structure where nodes can have multiple parents
 }
 
+fn demo_reference_cycles(tracker: &mut MemoryTracker) {
+    println!("Demonstrating a reference cycle, and Weak<T> to avoid one...");
+    
+    struct Link {
+        name: &'static str,
+        next: RefCell<Option<Rc<Link>>>,
+    }
+    
+    // a → b → a: each keeps the other's strong count above zero
+    let a = Rc::new(Link { name: "a", next: RefCell::new(None) });
+    let b = Rc::new(Link { name: "b", next: RefCell::new(Some(Rc::clone(&a))) });
+    tracker.track_allocation("a", "Rc<Link>", std::mem::size_of::<Link>());
+    tracker.track_allocation("b", "Rc<Link>", std::mem::size_of::<Link>());
+    tracker.track_link("b", "next", "a", "Rc<Link>");
+    *a.next.borrow_mut() = Some(Rc::clone(&b));
+    tracker.track_link("a", "next", "b", "Rc<Link>");
+    
+    let next = a.next.borrow().as_ref().map_or("nothing", |link| link.name);
+    println!("{} points to {}; strong counts: a = {}, b = {}",
+        a.name,
+        next,
+        Rc::strong_count(&a),
+        Rc::strong_count(&b)
+    );
+    
+    // Dropping both leaves each count at 1, so neither is ever freed
+    drop(a);
+    tracker.track_deallocation("a", 0);
+    drop(b);
+    tracker.track_deallocation("b", 0);
+    println!("{}", "a and b are out of reach but still alive: leaked".red());
+    
+    // A child pointing back at its parent with a Weak doesn't keep it alive
+    struct TreeNode {
+        parent: RefCell<Weak<TreeNode>>,
+        children: RefCell<Vec<Rc<TreeNode>>>,
+    }
+    
+    let parent = Rc::new(TreeNode {
+        parent: RefCell::new(Weak::new()),
+        children: RefCell::new(Vec::new()),
+    });
+    let child = Rc::new(TreeNode {
+        parent: RefCell::new(Weak::new()),
+        children: RefCell::new(Vec::new()),
+    });
+    tracker.track_allocation("parent", "Rc<TreeNode>", std::mem::size_of::<TreeNode>());
+    tracker.track_allocation("child", "Rc<TreeNode>", std::mem::size_of::<TreeNode>());
+    parent.children.borrow_mut().push(Rc::clone(&child));
+    tracker.track_link("parent", "children[0]", "child", "Rc<TreeNode>");
+    *child.parent.borrow_mut() = Rc::downgrade(&parent);
+    tracker.track_link("child", "parent", "parent", "Weak<TreeNode>");
+    
+    println!("parent: strong {}, weak {}; child: strong {}, has parent: {}",
+        Rc::strong_count(&parent),
+        Rc::weak_count(&parent),
+        Rc::strong_count(&child),
+        child.parent.borrow().upgrade().is_some()
+    );
+    println!("parent has {} child", parent.children.borrow().len());
+    
+    // The child outlives its variable through the parent's Vec, and goes
+    // with the parent
+    drop(child);
+    tracker.track_deallocation("child", 0);
+    drop(parent);
+    tracker.track_deallocation("parent", std::mem::size_of::<TreeNode>());
+    println!("{}", "parent and child are both freed".green());
+}
+
 fn demo_refcell(tracker: &mut MemoryTracker) {
     println!("Demonstrating RefCell<T> for interior mutability...");
     
@@ -136,10 +224,8 @@ fn demo_refcell(tracker: &mut MemoryTracker) {
     
     println!("Initial value: {}", data.borrow());
     
-    // TODO: Modify the value through RefCell
-    // HINT: Use borrow_mut()
     {
-        let mut borrowed = /* TODO: Get mutable borrow */;
+        let mut borrowed = data.borrow_mut();
         *borrowed = 100;
     }  // Mutable borrow is dropped here
     
@@ -184,6 +270,7 @@ fn demo_interior_mutability_pattern(tracker: &mut MemoryTracker) {
     
     // TODO: Test the counter
     let counter = Counter::new();
+    tracker.track_allocation("counter", "Counter", std::mem::size_of::<Counter>());
     counter.increment();
     counter.increment();
     println!("Counter value: {}", counter.get());
@@ -198,8 +285,8 @@ fn demo_arc_pointer(tracker: &mut MemoryTracker) {
     
     println!("Arc reference count: {}", Arc::strong_count(&shared_data));
     
-    // TODO: Clone Arc for sharing between threads
-    let data_clone = /* TODO: Clone the Arc */;
+    let data_clone = Arc::clone(&shared_data);
+    tracker.track_clone("shared_data", "data_clone", "Arc<String>", 0);
     
     // Note: We can't actually spawn threads in this simple demo,
     // but in real code you would do something like:
@@ -221,17 +308,13 @@ fn demo_combined_patterns(tracker: &mut MemoryTracker) {
     println!("Demonstrating combined smart pointer patterns...");
     
     // Common pattern: Arc<Mutex<T>> for shared mutable state
-    use std::sync::Mutex;
-    
     let shared_counter = Arc::new(Mutex::new(0));
     tracker.track_allocation("shared_counter", "Arc<Mutex<i32>>", std::mem::size_of::<i32>());
     
-    // TODO: Clone for sharing
-    let counter_clone = /* TODO: Clone the Arc */;
+    let counter_clone = Arc::clone(&shared_counter);
     
-    // TODO: Modify through the mutex
     {
-        let mut guard = /* TODO: Lock the mutex */;
+        let mut guard = shared_counter.lock().unwrap();
         *guard += 1;
     }
     
@@ -251,8 +334,6 @@ fn demo_shared_data_structure(tracker: &mut MemoryTracker) {
     // TODO: Create a data structure that can be safely shared between threads
     // and allows mutation
     
-    use std::sync::Mutex;
-    
     #[derive(Debug)]
     struct SharedList {
         // TODO: Use appropriate smart pointers
@@ -267,7 +348,7 @@ fn demo_shared_data_structure(tracker: &mut MemoryTracker) {
         }
         
         // TODO: Add method to insert item
-        fn push(&self, item: i32) {
+        fn push(&self, _item: i32) {
             // TODO: Implementation using Mutex
         }
         
@@ -280,6 +361,7 @@ fn demo_shared_data_structure(tracker: &mut MemoryTracker) {
     
     // TODO: Test the shared list
     let list = SharedList::new();
+    tracker.track_allocation("list", "Arc<SharedList>", std::mem::size_of::<SharedList>());
     list.push(1);
     list.push(2);
     println!("List length: {}", list.len());
@@ -301,6 +383,7 @@ pub fn interactive_demo(tracker: &mut MemoryTracker) {
     let mut boxes: std::collections::HashMap<String, Box<i32>> = std::collections::HashMap::new();
     let mut rcs: std::collections::HashMap<String, Rc<i32>> = std::collections::HashMap::new();
     let mut refcells: std::collections::HashMap<String, RefCell<i32>> = std::collections::HashMap::new();
+    let visualizer = MemoryVisualizer::new();
     
     loop {
         print!("smart-pointers> ");
@@ -308,7 +391,7 @@ pub fn interactive_demo(tracker: &mut MemoryTracker) {
         
         let mut input = String::new();
         io::stdin().read_line(&mut input).unwrap();
-        let parts: Vec<&str> = input.trim().split_whitespace().collect();
+        let parts: Vec<&str> = input.split_whitespace().collect();
         
         match parts.as_slice() {
             ["box", value] => {
@@ -316,7 +399,7 @@ pub fn interactive_demo(tracker: &mut MemoryTracker) {
                     let name = format!("box_{}", boxes.len());
                     tracker.track_allocation(&name, "Box<i32>", 4);
                     boxes.insert(name.clone(), Box::new(val));
-                    println!("✅ Created {} with value {}", name, val);
+                    visualizer.show_smart_pointer_op("Box", "new", &format!("{} holds {} on the heap", name, val));
                 } else {
                     println!("❌ Invalid number: {}", value);
                 }
@@ -326,7 +409,7 @@ pub fn interactive_demo(tracker: &mut MemoryTracker) {
                     let name = format!("rc_{}", rcs.len());
                     tracker.track_allocation(&name, "Rc<i32>", 4);
                     rcs.insert(name.clone(), Rc::new(val));
-                    println!("✅ Created {} with value {}", name, val);
+                    visualizer.show_smart_pointer_op("Rc", "new", &format!("{} holds {} (ref count: 1)", name, val));
                 } else {
                     println!("❌ Invalid number: {}", value);
                 }
@@ -335,8 +418,9 @@ pub fn interactive_demo(tracker: &mut MemoryTracker) {
                 if let Some(rc) = rcs.get(*name) {
                     let clone_name = format!("{}_clone", name);
                     let cloned = Rc::clone(rc);
-                    println!("✅ Cloned {} to {} (ref count: {})", 
-                        name, clone_name, Rc::strong_count(&cloned));
+                    tracker.track_clone(name, &clone_name, "Rc<i32>", 0);
+                    visualizer.show_smart_pointer_op("Rc", "clone", &format!("{} and {} share one value (ref count: {})",
+                        name, clone_name, Rc::strong_count(&cloned)));
                     rcs.insert(clone_name, cloned);
                 } else {
                     println!("❌ Rc {} not found", name);
@@ -347,7 +431,7 @@ pub fn interactive_demo(tracker: &mut MemoryTracker) {
                     let name = format!("refcell_{}", refcells.len());
                     tracker.track_allocation(&name, "RefCell<i32>", 4);
                     refcells.insert(name.clone(), RefCell::new(val));
-                    println!("✅ Created {} with value {}", name, val);
+                    visualizer.show_smart_pointer_op("RefCell", "new", &format!("{} holds {}", name, val));
                 } else {
                     println!("❌ Invalid number: {}", value);
                 }
@@ -388,6 +472,18 @@ mod tests {
         assert_eq!(Rc::strong_count(&rc2), 2);
     }
     
+    #[test]
+    fn test_weak_does_not_keep_alive() {
+        let strong = Rc::new(5);
+        let weak = Rc::downgrade(&strong);
+        
+        assert_eq!(Rc::weak_count(&strong), 1);
+        assert_eq!(weak.upgrade().as_deref(), Some(&5));
+        
+        drop(strong);
+        assert!(weak.upgrade().is_none());
+    }
+    
     #[test]
     fn test_refcell_interior_mutability() {
         let cell = RefCell::new(5);
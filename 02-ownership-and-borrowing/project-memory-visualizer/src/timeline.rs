@@ -0,0 +1,249 @@
+// Timeline - saving, loading and replaying tracked operations
+//
+// A timeline is the tracker's operations in step order, saved as JSON so a
+// walk-through can be shared and reviewed later:
+//
+//   {
+//     "operations": [
+//       { "step": 1, "operation": "allocate", "location": "s1", "type": "String", "size": 5 },
+//       { "step": 2, "operation": "move", "location": "s1 -> s2", "type": "String", "size": 0 }
+//     ]
+//   }
+//
+// Replaying feeds the operations through MemoryVisualizer one step at a
+// time and draws the memory layout after each, with any ownership rule the
+// step breaks.
+
+use crate::borrow_checker;
+use crate::memory_tracker::{is_shared_pointer, MemoryOperation, MemoryTracker, Operation, ScopeKind, MAX_SIZE};
+use crate::rc_graph::RcGraph;
+use crate::visualizer::MemoryVisualizer;
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Timeline {
+    pub operations: Vec<MemoryOperation>,
+}
+
+impl Timeline {
+    pub fn from_tracker(tracker: &MemoryTracker) -> Self {
+        Timeline {
+            operations: tracker.operations().to_vec(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a timeline always serializes")
+    }
+
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        let timeline: Timeline =
+            serde_json::from_str(json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        // Steps must count up from 1 without gaps, as the tracker numbers them
+        for (i, operation) in timeline.operations.iter().enumerate() {
+            if operation.step != i + 1 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("expected step {} but found step {}", i + 1, operation.step),
+                ));
+            }
+            if operation.size > MAX_SIZE {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("step {} has a size of {} bytes, more than {}", operation.step, operation.size, MAX_SIZE),
+                ));
+            }
+        }
+        Ok(timeline)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_json() + "\n")
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+}
+
+/// One line saying what an operation did. `graph` has the reference counts
+/// from before it, which say whether dropping an Rc frees anything.
+pub fn describe(operation: &MemoryOperation, graph: &RcGraph) -> String {
+    let (from, to) = operation
+        .location
+        .split_once(" -> ")
+        .unwrap_or((operation.location.as_str(), ""));
+
+    if matches!(operation.operation, Operation::Deallocate | Operation::Drop) {
+        if let Some(count) = count_after_drop(graph, from) {
+            return format!("{} is dropped ({})", from, count);
+        }
+    }
+
+    match operation.operation {
+        Operation::Clone if is_shared_pointer(&operation.value_type) => match graph.node_of(from) {
+            Some(node) if operation.value_type.starts_with("Weak<") => {
+                format!("{} is a weak pointer to {}'s value (weak {})", to, from, node.weak + 1)
+            }
+            Some(node) => format!("{} shares {}'s value (strong {})", to, from, node.strong + 1),
+            None => format!("{} is cloned into {}", from, to),
+        },
+        Operation::Allocate => format!(
+            "{} allocates {} bytes for a {}",
+            from, operation.size, operation.value_type
+        ),
+        Operation::Deallocate => format!("{} is freed ({} bytes)", from, operation.size),
+        Operation::Drop => format!("{} goes out of scope and is dropped", from),
+        Operation::Move => format!("ownership moves from {} to {}; {} is no longer valid", from, to, from),
+        Operation::Borrow => format!("{} borrows {} (shared, read-only)", from, to),
+        Operation::MutableBorrow => format!("{} borrows {} mutably (exclusive)", from, to),
+        Operation::Clone => format!("{} is cloned into {}", from, to),
+        Operation::Use => format!("{} is used", from),
+        Operation::EnterScope => match ScopeKind::from_type(&operation.value_type) {
+            ScopeKind::Function => format!("{} is called and gets a new stack frame", from),
+            ScopeKind::Block => format!("the {} block begins", from),
+        },
+        Operation::ExitScope => match ScopeKind::from_type(&operation.value_type) {
+            ScopeKind::Function => format!("{} returns and its stack frame is popped", from),
+            ScopeKind::Block => format!("the {} block ends", from),
+        },
+        Operation::Link if operation.value_type.starts_with("Weak<") => {
+            format!("{} now holds a weak pointer to {}", from, to)
+        }
+        Operation::Link => format!("{} now points to {} and keeps it alive", from, to),
+    }
+}
+
+// The count left after dropping an Rc, Arc or Weak, like "strong 1", or
+// None unless something still points at the value
+fn count_after_drop(graph: &RcGraph, name: &str) -> Option<String> {
+    let (node, weak) = graph.pointer(name)?;
+    if weak {
+        Some(format!("weak {}", node.weak.saturating_sub(1)))
+    } else if node.strong > 1 {
+        Some(format!("strong {}", node.strong - 1))
+    } else {
+        None
+    }
+}
+
+/// Describes every operation, following the reference counts along
+pub fn describe_all(operations: &[MemoryOperation]) -> Vec<String> {
+    let mut graph = RcGraph::new();
+    operations
+        .iter()
+        .map(|operation| {
+            let description = describe(operation, &graph);
+            graph.apply(operation);
+            description
+        })
+        .collect()
+}
+
+/// Draws the memory layout after every step. With `pause`, waits for Enter
+/// between steps; typing q stops the replay.
+pub fn replay(timeline: &Timeline, pause: bool) -> io::Result<()> {
+    let mut visualizer = MemoryVisualizer::new();
+    let total = timeline.operations.len();
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+
+    let diagnostics = borrow_checker::check(&timeline.operations);
+    let descriptions = describe_all(&timeline.operations);
+
+    for (operation, description) in timeline.operations.iter().zip(&descriptions) {
+        visualizer.apply(operation);
+        let heading = format!("Step {} of {}: {:?}", operation.step, total, operation.operation);
+        visualizer.visualize_operation(&heading, description);
+        for diagnostic in diagnostics.iter().filter(|d| d.step == operation.step) {
+            println!("\n{}", format!("❌ {}", diagnostic).red());
+        }
+
+        if pause && operation.step < total {
+            print!("\n{}", "Press Enter for the next step (q to stop) ".dimmed());
+            io::stdout().flush()?;
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 || line.trim() == "q" {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_round_trip() {
+        let mut tracker = MemoryTracker::new();
+        tracker.track_allocation("s1", "String", 5);
+        tracker.track_move("s1", "s2", "String");
+        tracker.track_borrow("r", "s2", true);
+
+        let timeline = Timeline::from_tracker(&tracker);
+        let json = timeline.to_json();
+        assert!(json.contains(r#""operation": "mutable_borrow""#));
+        assert!(json.contains(r#""type": "String""#));
+        assert_eq!(Timeline::from_json(&json).unwrap(), timeline);
+    }
+
+    #[test]
+    fn test_from_json_rejects_bad_timelines() {
+        let error = Timeline::from_json(r#"{"operations": [{"step": 2}]}"#).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let out_of_order = r#"{"operations": [
+            {"step": 2, "operation": "allocate", "location": "s1", "type": "String", "size": 5}
+        ]}"#;
+        let error = Timeline::from_json(out_of_order).unwrap_err();
+        assert_eq!(error.to_string(), "expected step 1 but found step 2");
+
+        let too_large = r#"{"operations": [
+            {"step": 1, "operation": "allocate", "location": "v", "type": "Vec", "size": 1000000000000}
+        ]}"#;
+        let error = Timeline::from_json(too_large).unwrap_err();
+        assert_eq!(error.to_string(), "step 1 has a size of 1000000000000 bytes, more than 1073741824");
+    }
+
+    #[test]
+    fn test_describe() {
+        let operation = MemoryOperation {
+            step: 1,
+            operation: Operation::Move,
+            location: "s1 -> s2".to_string(),
+            value_type: "String".to_string(),
+            size: 0,
+        };
+        assert_eq!(describe(&operation, &RcGraph::new()), "ownership moves from s1 to s2; s1 is no longer valid");
+    }
+
+    #[test]
+    fn test_describe_follows_reference_counts() {
+        let mut tracker = MemoryTracker::new();
+        tracker.track_allocation("a", "Rc<Node>", 16);
+        tracker.track_clone("a", "b", "Rc<Node>", 0);
+        tracker.track_clone("a", "w", "Weak<Node>", 0);
+        tracker.track_deallocation("a", 16);
+        tracker.track_deallocation("w", 0);
+        tracker.track_deallocation("b", 16);
+
+        let descriptions = describe_all(tracker.operations());
+        assert_eq!(
+            descriptions[1..],
+            [
+                "b shares a's value (strong 2)",
+                "w is a weak pointer to a's value (weak 1)",
+                "a is dropped (strong 1)",
+                "w is dropped (weak 0)",
+                "b is freed (16 bytes)",
+            ]
+        );
+    }
+}
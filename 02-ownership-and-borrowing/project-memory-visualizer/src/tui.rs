@@ -0,0 +1,381 @@
+// TUI - a full-screen step-through of a timeline
+//
+// `--tui` opens this after a command has run, on the operations it
+// recorded (or the timeline it replayed):
+//
+//   ┌ Step 3 of 12 ──────────────────────────────────────────────┐
+//   │r borrows s2 (shared, read-only)                            │
+//   └────────────────────────────────────────────────────────────┘
+//   ┌ Stack ─────────────┐┌ Heap ─────────────┐┌ References ─────┐
+//   │ fn main            ││▶#0 String 5 bytes ││▶s2 → #0         │
+//   │▶  s2: String → #0  ││                   ││▶r ⇢ #0 (borrow) │
+//   │▶  r: & → #0        ││                   ││                 │
+//   └────────────────────┘└───────────────────┘└─────────────────┘
+//   ┌ Operations ────────────────────────────────────────────────┐
+//   │   2 ownership moves from s1 to s2; s1 is no longer valid   │
+//   │▶  3 r borrows s2 (shared, read-only)                       │
+//   └────────────────────────────────────────────────────────────┘
+//    ←/→ step   Home/End first/last   q quit
+//
+// Moving backwards replays the timeline from the start up to the chosen
+// step, so every step shows exactly what the visualizer draws there. The
+// items the current step touches are marked with ▶ and highlighted, and a
+// step that breaks an ownership rule shows its diagnostic at the bottom.
+
+use crate::borrow_checker::{self, Diagnostic};
+use crate::memory_tracker::MemoryOperation;
+use crate::timeline::describe_all;
+use crate::visualizer::{MemoryVisualizer, StackItem};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::execute;
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::backend::{Backend, CrosstermBackend};
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{Frame, Terminal};
+use std::io::{self, IsTerminal};
+
+const MARKER: &str = "▶";
+
+fn highlight() -> Style {
+    Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD | Modifier::REVERSED)
+}
+
+/// A timeline and the step being looked at
+pub struct App<'a> {
+    operations: &'a [MemoryOperation],
+    descriptions: Vec<String>,
+    diagnostics: Vec<Diagnostic>,
+    // How many operations have been applied; 0 is before the first one
+    step: usize,
+    visualizer: MemoryVisualizer,
+}
+
+impl<'a> App<'a> {
+    pub fn new(operations: &'a [MemoryOperation]) -> Self {
+        let mut app = App {
+            operations,
+            descriptions: describe_all(operations),
+            diagnostics: borrow_checker::check(operations),
+            step: 0,
+            visualizer: MemoryVisualizer::new(),
+        };
+        app.go_to(1);
+        app
+    }
+
+    /// Shows the layout after `step`, counting from 1
+    pub fn go_to(&mut self, step: usize) {
+        self.step = step.min(self.operations.len());
+        self.visualizer = MemoryVisualizer::new();
+        for operation in &self.operations[..self.step] {
+            self.visualizer.apply(operation);
+        }
+    }
+
+    /// Handles a key press; returns false to quit
+    pub fn handle_key(&mut self, key: KeyCode) -> bool {
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Right | KeyCode::Down | KeyCode::Char('l') | KeyCode::Char('j') | KeyCode::Char(' ') => {
+                self.go_to(self.step + 1)
+            }
+            KeyCode::Left | KeyCode::Up | KeyCode::Char('h') | KeyCode::Char('k') | KeyCode::Backspace => {
+                self.go_to(self.step.saturating_sub(1))
+            }
+            KeyCode::Home | KeyCode::Char('g') => self.go_to(0),
+            KeyCode::End | KeyCode::Char('G') => self.go_to(self.operations.len()),
+            _ => {}
+        }
+        true
+    }
+
+    fn current(&self) -> Option<&MemoryOperation> {
+        self.step.checked_sub(1).map(|i| &self.operations[i])
+    }
+
+    // The names the current step touches: both sides of a move, borrow,
+    // clone or link, with `a` standing for the field `a.next`
+    fn affected_names(&self) -> Vec<&str> {
+        let Some(operation) = self.current() else { return Vec::new() };
+        let (from, to) = operation
+            .location
+            .split_once(" -> ")
+            .unwrap_or((operation.location.as_str(), ""));
+        let holder = from.split(['[', '.']).next().unwrap_or(from);
+        [from, holder, to].into_iter().filter(|name| !name.is_empty()).collect()
+    }
+
+    fn is_affected(&self, item: &StackItem) -> bool {
+        self.affected_names().contains(&item.name.as_str())
+    }
+
+    // Heap items pointed at by an affected stack item
+    fn affected_heap(&self) -> Vec<usize> {
+        self.visualizer
+            .stack_items()
+            .iter()
+            .filter(|item| self.is_affected(item))
+            .filter_map(|item| item.points_to_heap)
+            .collect()
+    }
+}
+
+// A line, marked and highlighted when the current step touches it
+fn line(text: String, affected: bool, style: Style) -> Line<'static> {
+    if affected {
+        Line::from(Span::styled(format!("{}{}", MARKER, text), highlight()))
+    } else {
+        Line::from(Span::styled(format!(" {}", text), style))
+    }
+}
+
+fn pane(title: &str) -> Block<'_> {
+    Block::default().borders(Borders::ALL).title(format!(" {} ", title))
+}
+
+fn stack_lines(app: &App) -> Vec<Line<'static>> {
+    let visualizer = &app.visualizer;
+    let mut lines = Vec::new();
+    for (depth, scope) in visualizer.scopes().iter().enumerate() {
+        let indent = "  ".repeat(depth);
+        lines.push(line(format!("{}{}", indent, scope.label()), false, Style::default().fg(Color::Cyan)));
+        for item in visualizer.stack_items().iter().filter(|item| item.scope == depth) {
+            let pointer = item.points_to_heap.map(|id| format!(" → #{}", id)).unwrap_or_default();
+            let text = format!("{}  {}: {}{}", indent, item.name, item.value_type, pointer);
+            lines.push(line(text, app.is_affected(item), Style::default().fg(Color::Green)));
+        }
+    }
+    lines
+}
+
+fn heap_lines(app: &App) -> Vec<Line<'static>> {
+    let affected = app.affected_heap();
+    let mut lines = Vec::new();
+    for item in app.visualizer.heap_items() {
+        let heading = format!("#{} {} {} bytes", item.id, item.value_type, item.size);
+        lines.push(line(heading, affected.contains(&item.id), Style::default().fg(Color::Magenta)));
+
+        let size = format!("{} bytes", item.size);
+        if !item.content.is_empty() && item.content != size {
+            lines.push(line(format!("  {}", item.content), false, Style::default()));
+        }
+        if item.is_shared() {
+            lines.push(line(format!("  strong {}, weak {}", item.strong, item.weak), false, Style::default()));
+        }
+        for link in &item.links {
+            let weak = if link.weak { " (weak)" } else { "" };
+            lines.push(line(format!("  {} → #{}{}", link.field, link.target, weak), false, Style::default()));
+        }
+        if item.leaked {
+            lines.push(line("  leaked in a cycle".to_string(), false, Style::default().fg(Color::Red)));
+        }
+    }
+    if lines.is_empty() {
+        lines.push(line("(empty)".to_string(), false, Style::default().add_modifier(Modifier::DIM)));
+    }
+    lines
+}
+
+fn reference_lines(app: &App) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    for item in app.visualizer.stack_items() {
+        let Some(id) = item.points_to_heap else { continue };
+        let text = if item.is_reference() {
+            format!("{} ⇢ #{} (borrow)", item.name, id)
+        } else if item.is_weak() {
+            format!("{} ⇢ #{} (weak)", item.name, id)
+        } else {
+            format!("{} → #{}", item.name, id)
+        };
+        lines.push(line(text, app.is_affected(item), Style::default()));
+    }
+    for item in app.visualizer.heap_items() {
+        for link in &item.links {
+            let arrow = if link.weak { "⇢" } else { "→" };
+            lines.push(line(format!("#{}.{} {} #{}", item.id, link.field, arrow, link.target), false, Style::default()));
+        }
+    }
+    if lines.is_empty() {
+        lines.push(line("(none)".to_string(), false, Style::default().add_modifier(Modifier::DIM)));
+    }
+    lines
+}
+
+fn footer_lines(app: &App) -> Vec<Line<'static>> {
+    let diagnostics = app.diagnostics.iter().filter(|diagnostic| diagnostic.step == app.step);
+    let mut lines: Vec<Line> = diagnostics
+        .flat_map(|diagnostic| diagnostic.to_string().lines().map(str::to_string).collect::<Vec<_>>())
+        .map(|text| Line::from(Span::styled(format!(" {}", text), Style::default().fg(Color::Red))))
+        .collect();
+    lines.push(Line::from(Span::styled(
+        " ←/→ step   Home/End first/last   q quit",
+        Style::default().add_modifier(Modifier::DIM),
+    )));
+    lines
+}
+
+/// Draws one frame
+pub fn draw(frame: &mut Frame, app: &App) {
+    let footer = footer_lines(app);
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(6),
+            Constraint::Length(10),
+            Constraint::Length(footer.len() as u16),
+        ])
+        .split(frame.size());
+
+    let (title, description) = match app.step.checked_sub(1) {
+        Some(i) => (
+            format!("Step {} of {}", app.step, app.operations.len()),
+            app.descriptions[i].clone(),
+        ),
+        None => ("Before step 1".to_string(), "Nothing has happened yet".to_string()),
+    };
+    frame.render_widget(Paragraph::new(description).block(pane(&title)), rows[0]);
+
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(35), Constraint::Percentage(40), Constraint::Percentage(25)])
+        .split(rows[1]);
+    frame.render_widget(Paragraph::new(stack_lines(app)).block(pane("Stack")), columns[0]);
+    frame.render_widget(Paragraph::new(heap_lines(app)).block(pane("Heap")), columns[1]);
+    frame.render_widget(
+        Paragraph::new(reference_lines(app)).block(pane("References")).wrap(Wrap { trim: false }),
+        columns[2],
+    );
+
+    let broken: Vec<usize> = app.diagnostics.iter().map(|diagnostic| diagnostic.step).collect();
+    let items: Vec<ListItem> = app
+        .operations
+        .iter()
+        .zip(&app.descriptions)
+        .map(|(operation, description)| {
+            let mark = if broken.contains(&operation.step) { " ❌" } else { "" };
+            let text = format!("{:>3} {}{}", operation.step, description, mark);
+            let style = if mark.is_empty() { Style::default() } else { Style::default().fg(Color::Red) };
+            ListItem::new(text).style(style)
+        })
+        .collect();
+    let list = List::new(items)
+        .block(pane("Operations"))
+        .highlight_style(highlight())
+        .highlight_symbol(MARKER);
+    let mut state = ListState::default().with_selected(app.step.checked_sub(1));
+    frame.render_stateful_widget(list, rows[2], &mut state);
+
+    frame.render_widget(Paragraph::new(footer), rows[3]);
+}
+
+fn event_loop<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<()> {
+    loop {
+        terminal.draw(|frame| draw(frame, &app))?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && !app.handle_key(key.code) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Opens the full-screen step-through, and restores the terminal when it
+/// is closed
+pub fn run(operations: &[MemoryOperation]) -> io::Result<()> {
+    if !io::stdout().is_terminal() {
+        return Err(io::Error::other("the terminal UI needs an interactive terminal"));
+    }
+
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    let result = Terminal::new(CrosstermBackend::new(io::stdout()))
+        .and_then(|mut terminal| event_loop(&mut terminal, App::new(operations)));
+
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory_tracker::MemoryTracker;
+    use ratatui::backend::TestBackend;
+
+    fn operations() -> Vec<MemoryOperation> {
+        let mut tracker = MemoryTracker::new();
+        tracker.track_allocation("s1", "String", 5);
+        tracker.track_move("s1", "s2", "String");
+        tracker.track_borrow("r", "s2", false);
+        tracker.track_move("s2", "s3", "String");
+        tracker.track_use("r");
+        tracker.operations().to_vec()
+    }
+
+    fn screen(app: &App) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|frame| draw(frame, app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let width = buffer.area.width as usize;
+        let symbols: Vec<&str> = buffer.content.iter().map(|cell| cell.symbol()).collect();
+        symbols.chunks(width).map(|row| row.concat() + "\n").collect()
+    }
+
+    #[test]
+    fn test_keys_step_through_the_timeline() {
+        let operations = operations();
+        let mut app = App::new(&operations);
+        assert_eq!(app.step, 1);
+
+        assert!(app.handle_key(KeyCode::Right));
+        assert!(app.handle_key(KeyCode::Right));
+        assert_eq!(app.step, 3);
+        assert_eq!(app.visualizer.stack_items().len(), 2);
+
+        app.handle_key(KeyCode::Left);
+        assert_eq!(app.step, 2);
+        assert_eq!(app.visualizer.stack_items().len(), 1);
+
+        app.handle_key(KeyCode::End);
+        app.handle_key(KeyCode::Right);
+        assert_eq!(app.step, 5);
+        app.handle_key(KeyCode::Home);
+        assert_eq!(app.step, 0);
+        assert!(app.visualizer.stack_items().is_empty());
+
+        assert!(!app.handle_key(KeyCode::Char('q')));
+    }
+
+    #[test]
+    fn test_current_step_is_highlighted() {
+        let operations = operations();
+        let mut app = App::new(&operations);
+        app.go_to(3);
+        let screen = screen(&app);
+
+        assert!(screen.contains("Step 3 of 5"));
+        assert!(screen.contains("r borrows s2 (shared, read-only)"));
+        assert!(screen.contains("▶  r: & → #0"));
+        assert!(screen.contains("▶  s2: String → #0"));
+        assert!(screen.contains("▶#0 String 5 bytes"));
+        assert!(screen.contains("▶r ⇢ #0 (borrow)"));
+        assert!(screen.contains("▶  3 r borrows s2"));
+        assert!(screen.contains("q quit"));
+    }
+
+    #[test]
+    fn test_broken_rules_show_their_diagnostic() {
+        let operations = operations();
+        let mut app = App::new(&operations);
+        app.go_to(5);
+        let screen = screen(&app);
+
+        assert!(screen.contains("5 r is used ❌"));
+        assert!(screen.contains("error[E0505]"));
+        assert!(screen.contains("step 4:"));
+    }
+}
@@ -1,28 +1,92 @@
 // Memory Visualizer - ASCII art for memory operations
 //
-// TODO: Complete the visualization functions to show memory layout
+// Draws the stack, the heap and the references between them. `apply`
+// updates the picture one tracked operation at a time, which is how a saved
+// timeline is replayed.
+//
+// Clones of an Rc or Arc point at the same heap item. Its strong and weak
+// counts, the pointers stored in it and whether a cycle leaks it come from
+// a reference graph (rc_graph.rs); the item is freed when the graph drops
+// its value. diagram.rs draws the same layout as DOT and SVG.
+//
+// The stack is a set of nested scopes, starting with the `main` frame:
+// function calls push frames and blocks push scopes. Popping a scope frees
+// what is left in it, newest first.
 
+use crate::memory_tracker::{self, MemoryOperation, Operation, ScopeKind};
+use crate::rc_graph::RcGraph;
 use colored::*;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 pub struct MemoryVisualizer {
     stack_items: Vec<StackItem>,
     heap_items: Vec<HeapItem>,
+    scopes: Vec<Scope>,
+    rc_graph: RcGraph,
+    // Reference graph node → heap item ID
+    shared_items: BTreeMap<usize, usize>,
+    next_heap_id: usize,
+}
+
+/// A stack frame or block scope; the main frame comes first
+#[derive(Debug, Clone)]
+pub struct Scope {
+    pub name: String,
+    pub kind: ScopeKind,
+}
+
+impl Scope {
+    pub fn label(&self) -> String {
+        self.kind.label(&self.name)
+    }
 }
 
 #[derive(Debug, Clone)]
-struct StackItem {
-    name: String,
-    value_type: String,
-    points_to_heap: Option<usize>, // Index into heap_items
+pub struct StackItem {
+    pub name: String,
+    pub value_type: String,
+    pub points_to_heap: Option<usize>, // ID of a heap item
+    pub scope: usize,                  // Index into the open scopes
+}
+
+impl StackItem {
+    /// A reference borrows the heap data; it doesn't own it
+    pub fn is_reference(&self) -> bool {
+        self.value_type.starts_with('&')
+    }
+
+    pub fn is_weak(&self) -> bool {
+        self.value_type.starts_with("Weak<")
+    }
 }
 
 #[derive(Debug, Clone)]
-struct HeapItem {
-    id: usize,
-    value_type: String,
-    size: usize,
-    content: String,
+pub struct HeapItem {
+    pub id: usize,
+    pub value_type: String,
+    pub size: usize,
+    pub content: String,
+    /// Owners keeping the data alive: more than one for a cloned Rc or Arc
+    pub strong: usize,
+    pub weak: usize,
+    /// Pointers stored in the value, to other heap items
+    pub links: Vec<HeapLink>,
+    /// Kept alive by a reference cycle that nothing can reach any more
+    pub leaked: bool,
+}
+
+/// A pointer from one heap item to another, like a node's `next` field
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeapLink {
+    pub field: String,
+    pub target: usize,
+    pub weak: bool,
+}
+
+impl HeapItem {
+    pub fn is_shared(&self) -> bool {
+        memory_tracker::is_shared_pointer(&self.value_type)
+    }
 }
 
 impl MemoryVisualizer {
@@ -30,46 +94,88 @@ impl MemoryVisualizer {
         MemoryVisualizer {
             stack_items: Vec::new(),
             heap_items: Vec::new(),
+            scopes: vec![Scope {
+                name: "main".to_string(),
+                kind: ScopeKind::Function,
+            }],
+            rc_graph: RcGraph::new(),
+            shared_items: BTreeMap::new(),
+            next_heap_id: 0,
         }
     }
     
     pub fn add_stack_value(&mut self, name: &str, value_type: &str) {
-        // TODO: Add a stack-allocated value
         let item = StackItem {
             name: name.to_string(),
             value_type: value_type.to_string(),
             points_to_heap: None,
+            scope: self.current_scope(),
         };
         self.stack_items.push(item);
     }
     
+    fn current_scope(&self) -> usize {
+        self.scopes.len() - 1
+    }
+    
+    pub fn push_scope(&mut self, name: &str, kind: ScopeKind) {
+        self.scopes.push(Scope {
+            name: name.to_string(),
+            kind,
+        });
+    }
+    
+    /// Pops the innermost scope, freeing what is left in it newest first,
+    /// and returns the freed names. The main frame is never popped.
+    pub fn pop_scope(&mut self) -> Vec<String> {
+        if self.scopes.len() == 1 {
+            return Vec::new();
+        }
+        let scope = self.current_scope();
+        let names: Vec<String> = self
+            .stack_items
+            .iter()
+            .rev()
+            .filter(|item| item.scope == scope)
+            .map(|item| item.name.clone())
+            .collect();
+        for name in &names {
+            self.free(name);
+        }
+        self.scopes.pop();
+        names
+    }
+    
     pub fn add_heap_value(&mut self, name: &str, value_type: &str, size: usize, content: &str) -> usize {
-        // TODO: Add a heap-allocated value and return its ID
-        let id = self.heap_items.len();
+        // IDs are never reused, so they stay stable as items are freed
+        let id = self.next_heap_id;
+        self.next_heap_id += 1;
         let item = HeapItem {
             id,
             value_type: value_type.to_string(),
             size,
             content: content.to_string(),
+            strong: 1,
+            weak: 0,
+            links: Vec::new(),
+            leaked: false,
         };
         self.heap_items.push(item);
         
-        // TODO: Update stack item to point to this heap item
-        // HINT: Find the stack item with the given name and set points_to_heap
+        if let Some(stack_item) = self.stack_items.iter_mut().find(|item| item.name == name) {
+            stack_item.points_to_heap = Some(id);
+        }
         
         id
     }
     
     pub fn remove_stack_value(&mut self, name: &str) {
-        // TODO: Remove a stack value by name
         self.stack_items.retain(|item| item.name != name);
     }
     
     pub fn remove_heap_value(&mut self, id: usize) {
-        // TODO: Remove a heap value by ID
         self.heap_items.retain(|item| item.id != id);
         
-        // TODO: Update any stack items that pointed to this heap item
         for stack_item in &mut self.stack_items {
             if stack_item.points_to_heap == Some(id) {
                 stack_item.points_to_heap = None;
@@ -77,6 +183,151 @@ impl MemoryVisualizer {
         }
     }
     
+    /// Updates the layout for one tracked operation
+    pub fn apply(&mut self, operation: &MemoryOperation) {
+        let (from, to) = operation
+            .location
+            .split_once(" -> ")
+            .unwrap_or((operation.location.as_str(), ""));
+        self.rc_graph.apply(operation);
+        
+        match operation.operation {
+            Operation::Allocate => {
+                // Reusing a name replaces the old value
+                self.free(from);
+                self.add_stack_value(from, &operation.value_type);
+                let id = self.add_heap_value(from, &operation.value_type, operation.size, &format!("{} bytes", operation.size));
+                if let Some(node) = self.rc_graph.node_of(from) {
+                    self.shared_items.insert(node.id, id);
+                }
+            }
+            Operation::Deallocate | Operation::Drop => self.free(from),
+            Operation::Move => {
+                // The new owner takes over the same heap data, in the scope
+                // it is moved into
+                self.free(to);
+                let scope = self.current_scope();
+                if let Some(item) = self.stack_items.iter_mut().find(|item| item.name == from) {
+                    item.name = to.to_string();
+                    item.scope = scope;
+                }
+            }
+            Operation::Borrow | Operation::MutableBorrow => {
+                let target = self.heap_pointer(to);
+                self.remove_stack_value(from);
+                self.stack_items.push(StackItem {
+                    name: from.to_string(),
+                    value_type: operation.value_type.clone(),
+                    points_to_heap: target,
+                    scope: self.current_scope(),
+                });
+            }
+            // Rc::clone and Rc::downgrade share the heap data
+            Operation::Clone if memory_tracker::is_shared_pointer(&operation.value_type) => {
+                let target = self.heap_pointer(from);
+                self.free(to);
+                self.stack_items.push(StackItem {
+                    name: to.to_string(),
+                    value_type: operation.value_type.clone(),
+                    points_to_heap: target,
+                    scope: self.current_scope(),
+                });
+            }
+            // The allocation that follows a clone adds the copy, reading a
+            // value changes nothing, and links only change the counts
+            Operation::Clone | Operation::Use | Operation::Link => {}
+            Operation::EnterScope => self.push_scope(from, ScopeKind::from_type(&operation.value_type)),
+            // The tracker drops the scope's values first; anything left is
+            // freed here
+            Operation::ExitScope => {
+                self.pop_scope();
+            }
+        }
+        self.sync_shared_items();
+    }
+    
+    // Copies the counts, links and leaks of the reference graph onto the
+    // heap items, and frees the ones whose value it dropped
+    fn sync_shared_items(&mut self) {
+        let dropped: Vec<(usize, usize)> = self
+            .shared_items
+            .iter()
+            .filter(|(node, _)| self.rc_graph.node(**node).is_none())
+            .map(|(node, id)| (*node, *id))
+            .collect();
+        for (node, id) in dropped {
+            self.shared_items.remove(&node);
+            self.remove_heap_value(id);
+        }
+        
+        let leaked = self.rc_graph.leaked_nodes();
+        for (node_id, id) in self.shared_items.clone() {
+            let Some(node) = self.rc_graph.node(node_id).cloned() else { continue };
+            let links = node
+                .links
+                .iter()
+                .filter_map(|link| {
+                    Some(HeapLink {
+                        field: link.field.clone(),
+                        target: *self.shared_items.get(&link.target)?,
+                        weak: link.weak,
+                    })
+                })
+                .collect();
+            if let Some(item) = self.heap_item_mut(id) {
+                item.strong = node.strong;
+                item.weak = node.weak;
+                item.links = links;
+                item.leaked = leaked.contains(&node_id);
+            }
+        }
+    }
+    
+    pub fn stack_items(&self) -> &[StackItem] {
+        &self.stack_items
+    }
+    
+    pub fn scopes(&self) -> &[Scope] {
+        &self.scopes
+    }
+    
+    pub fn heap_items(&self) -> &[HeapItem] {
+        &self.heap_items
+    }
+    
+    fn heap_item_mut(&mut self, id: usize) -> Option<&mut HeapItem> {
+        self.heap_items.iter_mut().find(|item| item.id == id)
+    }
+    
+    /// Shows what a value holds instead of just its size
+    pub fn set_content(&mut self, name: &str, content: &str) {
+        if let Some(item) = self.heap_pointer(name).and_then(|id| self.heap_item_mut(id)) {
+            item.content = content.to_string();
+        }
+    }
+    
+    fn heap_pointer(&self, name: &str) -> Option<usize> {
+        self.stack_items
+            .iter()
+            .find(|item| item.name == name)
+            .and_then(|item| item.points_to_heap)
+    }
+    
+    // Removes a value, and the heap data it owns. Shared heap data is
+    // left to the reference graph, which frees it with its last owner.
+    fn free(&mut self, name: &str) {
+        let Some(item) = self.stack_items.iter().find(|item| item.name == name).cloned() else {
+            return;
+        };
+        self.remove_stack_value(name);
+        
+        let Some(id) = item.points_to_heap else { return };
+        if item.is_reference() || self.shared_items.values().any(|shared| *shared == id) {
+            return;
+        }
+        self.remove_heap_value(id);
+    }
+    
     pub fn visualize(&self) {
         println!("\n{}", "=== Memory Layout ===".bold());
         self.draw_stack();
@@ -84,25 +335,56 @@ impl MemoryVisualizer {
         self.draw_references();
     }
     
+    // Each scope is a box inside the one that encloses it:
+    //
+    //   ┌─ fn main ──────────────┐
+    //   │ s1       String  →     │
+    //   │ ┌─ { inner } ────────┐ │
+    //   │ │ s2       String  → │ │
+    //   │ └────────────────────┘ │
+    //   └────────────────────────┘
     fn draw_stack(&self) {
         println!("\n{}", "STACK".bold().blue());
-        println!("┌─────────────────────┐");
         
-        if self.stack_items.is_empty() {
-            println!("│ (empty)             │");
-        } else {
-            // TODO: Draw stack items from top to bottom
-            for (i, item) in self.stack_items.iter().enumerate().rev() {
+        let item_text = |item: &StackItem| format!("{:<8} {:<7} →", item.name, item.value_type);
+        let width = self
+            .scopes
+            .iter()
+            .enumerate()
+            .map(|(depth, scope)| 4 * depth + 6 + scope.label().chars().count())
+            .chain(self.stack_items.iter().map(|item| 4 * (item.scope + 1) + item_text(item).chars().count()))
+            .fold(23, usize::max);
+        
+        for (depth, scope) in self.scopes.iter().enumerate() {
+            let label = scope.label();
+            println!("{}┌─ {} {}┐{}",
+                "│ ".repeat(depth),
+                label.cyan(),
+                "─".repeat(width - 4 * depth - 5 - label.chars().count()),
+                " │".repeat(depth)
+            );
+            
+            let items: Vec<&StackItem> = self.stack_items.iter().filter(|item| item.scope == depth).collect();
+            if items.is_empty() && depth == 0 && self.scopes.len() == 1 {
+                println!("│ {:<1$} │", "(empty)", width - 4);
+            }
+            for item in items {
                 let pointer_symbol = if item.points_to_heap.is_some() { "→" } else { " " };
-                println!("│ {:<8} {:<7} {} │", 
-                    item.name.green(), 
-                    item.value_type.yellow(),
-                    pointer_symbol.red()
+                let padding = width - 4 * (depth + 1) - item_text(item).chars().count();
+                println!("{}{} {} {}{}{}",
+                    "│ ".repeat(depth + 1),
+                    format!("{:<8}", item.name).green(),
+                    format!("{:<7}", item.value_type).yellow(),
+                    pointer_symbol.red(),
+                    " ".repeat(padding),
+                    " │".repeat(depth + 1)
                 );
             }
         }
         
-        println!("└─────────────────────┘");
+        for depth in (0..self.scopes.len()).rev() {
+            println!("{}└{}┘{}", "│ ".repeat(depth), "─".repeat(width - 4 * depth - 2), " │".repeat(depth));
+        }
         println!(" {}", "Stack grows down ↓".dimmed());
     }
     
@@ -116,17 +398,38 @@ impl MemoryVisualizer {
             return;
         }
         
-        // TODO: Draw heap items with their sizes
-        for item in &self.heap_items {
-            let blocks = (item.size / 8).max(1); // Each block represents 8 bytes
-            let visualization = "█".repeat(blocks);
-            
-            println!("┌─────────────────────┐");
-            println!("│ ID: {:<3} Size: {:<6} │", item.id.to_string().cyan(), format!("{}B", item.size).yellow());
-            println!("│ Type: {:<12} │", item.value_type.blue());
-            println!("│ Data: {:<12} │", item.content.green());
-            println!("│ Mem:  {:<12} │", visualization.purple());
-            println!("└─────────────────────┘");
+        // Each row is a label and a coloured value; the boxes are as wide
+        // as the widest row, and never narrower than the empty box
+        let boxes: Vec<Vec<(String, ColoredString)>> = self.heap_items.iter().map(|item| {
+            // Each block represents 8 bytes, as many as fit in the box
+            let visualization = memory_tracker::size_bar(item.size, 12);
+            let mut rows = vec![
+                (format!("ID: {:<3} Size: ", item.id), format!("{}B", item.size).yellow()),
+                ("Type: ".to_string(), item.value_type.blue()),
+                ("Data: ".to_string(), item.content.green()),
+                ("Mem:  ".to_string(), visualization.purple()),
+            ];
+            if item.is_shared() {
+                rows.push(("Refs: ".to_string(), format!("{} strong, {} weak", item.strong, item.weak).cyan()));
+            }
+            for link in &item.links {
+                let weak = if link.weak { " (weak)" } else { "" };
+                rows.push((String::new(), format!("{} → #{}{}", link.field, link.target, weak).cyan()));
+            }
+            if item.leaked {
+                rows.push((String::new(), "⚠️ leaked in a cycle".red().bold()));
+            }
+            rows
+        }).collect();
+        let row_width = |(label, value): &(String, ColoredString)| label.chars().count() + value.chars().count();
+        let width = boxes.iter().flatten().map(row_width).max().unwrap_or(0).max(19);
+
+        for rows in &boxes {
+            println!("┌{}┐", "─".repeat(width + 2));
+            for row in rows {
+                println!("│ {}{}{} │", row.0, row.1, " ".repeat(width - row_width(row)));
+            }
+            println!("└{}┘", "─".repeat(width + 2));
         }
         
         println!(" {}", "Heap grows up ↑".dimmed());
@@ -147,6 +450,19 @@ impl MemoryVisualizer {
             }
         }
         
+        for heap_item in &self.heap_items {
+            for link in &heap_item.links {
+                println!("{} Heap[{}].{} → Heap[{}]{}",
+                    "🔗".yellow(),
+                    heap_item.id.to_string().cyan(),
+                    link.field.green(),
+                    link.target.to_string().cyan(),
+                    if link.weak { " (weak)".dimmed().to_string() } else { String::new() }
+                );
+                has_references = true;
+            }
+        }
+        
         if !has_references {
             println!("(no active references)");
         }
@@ -158,19 +474,16 @@ impl MemoryVisualizer {
         self.visualize();
     }
     
-    // TODO: Add method to show ownership transfer
     pub fn show_ownership_transfer(&self, from: &str, to: &str) {
         println!("\n{}", "=== Ownership Transfer ===".bold().red());
         println!("{} {} → {}", "MOVE:".bold(), from.green(), to.green());
         println!("{}", "Previous owner is now invalid".red().italic());
         
-        // TODO: Show visual representation of the transfer
         println!("Before: {} owns the data", from.green());
         println!("After:  {} owns the data", to.green());
         println!("        {} is no longer valid", from.strikethrough().red());
     }
     
-    // TODO: Add method to show borrowing
     pub fn show_borrowing(&self, owner: &str, borrower: &str, mutable: bool) {
         let borrow_type = if mutable { "MUTABLE BORROW" } else { "IMMUTABLE BORROW" };
         let symbol = if mutable { "🔓" } else { "🔒" };
@@ -190,12 +503,10 @@ impl MemoryVisualizer {
             println!("{}", "• Multiple immutable borrows allowed".green());
         }
         
-        // TODO: Show ASCII representation of borrowing
         println!("Owner:    {} ← still owns data", owner.green());
         println!("Borrower: {} ← temporary access", borrower.cyan());
     }
     
-    // TODO: Add method to show smart pointer operations
     pub fn show_smart_pointer_op(&self, pointer_type: &str, operation: &str, details: &str) {
         let symbol = match pointer_type {
             "Box" => "📦",
@@ -208,23 +519,20 @@ impl MemoryVisualizer {
         println!("\n{} {} {}", symbol, pointer_type.bold().purple(), operation.yellow());
         println!("{}", details.italic());
         
-        // TODO: Add specific visualizations for each smart pointer type
         match pointer_type {
-            "Box" => self.visualize_box_operation(operation, details),
-            "Rc" => self.visualize_rc_operation(operation, details),
-            "RefCell" => self.visualize_refcell_operation(operation, details),
+            "Box" => self.visualize_box_operation(operation),
+            "Rc" => self.visualize_rc_operation(operation),
+            "RefCell" => self.visualize_refcell_operation(operation),
             _ => {}
         }
     }
     
-    fn visualize_box_operation(&self, operation: &str, details: &str) {
-        // TODO: Show Box-specific visualization
+    fn visualize_box_operation(&self, operation: &str) {
         println!("Box operation: {}", operation);
         println!("Stack: [ptr] → Heap: [data]");
     }
     
-    fn visualize_rc_operation(&self, operation: &str, details: &str) {
-        // TODO: Show Rc reference counting
+    fn visualize_rc_operation(&self, operation: &str) {
         println!("Rc operation: {}", operation);
         if operation.contains("clone") {
             println!("Reference count increased");
@@ -232,8 +540,7 @@ impl MemoryVisualizer {
         }
     }
     
-    fn visualize_refcell_operation(&self, operation: &str, details: &str) {
-        // TODO: Show RefCell runtime borrowing
+    fn visualize_refcell_operation(&self, operation: &str) {
         println!("RefCell operation: {}", operation);
         if operation.contains("borrow") {
             println!("Runtime borrow check: ✅");
@@ -241,8 +548,15 @@ impl MemoryVisualizer {
     }
 }
 
-// TODO: Implement helper functions for complex visualizations
+impl Default for MemoryVisualizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
+// Nothing calls these yet; they are there for expanding
+// compare_with_csharp in main.rs
+#[allow(dead_code)]
 pub fn visualize_memory_comparison() {
     println!("{}", "=== Memory Management Comparison ===".bold());
     
@@ -272,6 +586,7 @@ pub fn visualize_memory_comparison() {
     println!("• No garbage collection needed");
 }
 
+#[allow(dead_code)]
 pub fn visualize_borrowing_rules() {
     println!("{}", "=== Borrowing Rules Visualization ===".bold());
     
@@ -292,16 +607,24 @@ pub fn visualize_borrowing_rules() {
     println!("❌ Cannot have both simultaneously");
 }
 
-// TODO: Add more visualization functions
+#[allow(dead_code)]
 pub fn visualize_smart_pointer_patterns() {
     println!("{}", "=== Smart Pointer Patterns ===".bold());
     
-    // TODO: Implement visualizations for:
-    // - Box<T> heap allocation
-    // - Rc<T> reference counting
-    // - Arc<T> thread-safe sharing
-    // - RefCell<T> interior mutability
-    // - Combined patterns like Rc<RefCell<T>>
+    println!("\n{}", "Box<T>: one owner, data on the heap".purple().bold());
+    println!("box ──→ [data]");
+    
+    println!("\n{}", "Rc<T>: shared ownership, counted".purple().bold());
+    println!("rc1 ──→ [count: 2 | data] ←── rc2");
+    
+    println!("\n{}", "Arc<T>: like Rc, with an atomic count for threads".purple().bold());
+    println!("thread 1: arc ──→ [count: 2 | data] ←── arc :thread 2");
+    
+    println!("\n{}", "RefCell<T>: borrow rules checked at runtime".purple().bold());
+    println!("cell ──→ [borrow flag | data]");
+    
+    println!("\n{}", "Rc<RefCell<T>>: shared and mutable".purple().bold());
+    println!("rc1 ──→ [count: 2 | borrow flag | data] ←── rc2");
 }
 
 #[cfg(test)]
@@ -324,5 +647,91 @@ mod tests {
         assert_eq!(visualizer.stack_items[0].name, "x");
     }
     
-    // TODO: Add more tests for visualization functionality
+    #[test]
+    fn test_add_heap_value_points_stack_at_it() {
+        let mut visualizer = MemoryVisualizer::new();
+        visualizer.add_stack_value("s", "String");
+        let id = visualizer.add_heap_value("s", "String", 5, "hello");
+        
+        assert_eq!(visualizer.stack_items[0].points_to_heap, Some(id));
+        
+        visualizer.remove_heap_value(id);
+        assert_eq!(visualizer.stack_items[0].points_to_heap, None);
+    }
+    
+    #[test]
+    fn test_apply_operations() {
+        let operation = |step, operation, location: &str, size| MemoryOperation {
+            step,
+            operation,
+            location: location.to_string(),
+            value_type: "String".to_string(),
+            size,
+        };
+        let mut visualizer = MemoryVisualizer::new();
+        
+        visualizer.apply(&operation(1, Operation::Allocate, "s1", 5));
+        visualizer.apply(&operation(2, Operation::Move, "s1 -> s2", 0));
+        visualizer.apply(&operation(3, Operation::Borrow, "r -> s2", 0));
+        assert_eq!(visualizer.stack_items.len(), 2);
+        assert_eq!(visualizer.stack_items[0].name, "s2");
+        assert_eq!(visualizer.stack_items[1].points_to_heap, visualizer.heap_pointer("s2"));
+        
+        visualizer.apply(&operation(4, Operation::Deallocate, "s2", 5));
+        assert!(visualizer.heap_items.is_empty());
+        assert_eq!(visualizer.stack_items.len(), 1);
+    }
+    
+    #[test]
+    fn test_shared_items_follow_the_rc_graph() {
+        let operation = |step, operation, location: &str, value_type: &str| MemoryOperation {
+            step,
+            operation,
+            location: location.to_string(),
+            value_type: value_type.to_string(),
+            size: 16,
+        };
+        let mut visualizer = MemoryVisualizer::new();
+        
+        visualizer.apply(&operation(1, Operation::Allocate, "a", "Rc<Node>"));
+        visualizer.apply(&operation(2, Operation::Allocate, "b", "Rc<Node>"));
+        visualizer.apply(&operation(3, Operation::Link, "a.next -> b", "Rc<Node>"));
+        visualizer.apply(&operation(4, Operation::Link, "b.prev -> a", "Weak<Node>"));
+        assert_eq!(visualizer.heap_items[1].strong, 2);
+        assert_eq!(visualizer.heap_items[0].links, vec![HeapLink {
+            field: "next".to_string(),
+            target: 1,
+            weak: false,
+        }]);
+        
+        // b lives on through a.next; dropping a frees both
+        visualizer.apply(&operation(5, Operation::Drop, "b", ""));
+        assert_eq!(visualizer.heap_items.len(), 2);
+        assert!(visualizer.heap_items.iter().all(|item| !item.leaked));
+        visualizer.apply(&operation(6, Operation::Drop, "a", ""));
+        assert!(visualizer.heap_items.is_empty());
+        
+        // IDs are not reused
+        visualizer.apply(&operation(7, Operation::Allocate, "c", "Rc<Node>"));
+        assert_eq!(visualizer.heap_items[0].id, 2);
+    }
+    
+    #[test]
+    fn test_pop_scope_frees_newest_first() {
+        let mut visualizer = MemoryVisualizer::new();
+        visualizer.add_stack_value("outer", "i32");
+        visualizer.push_scope("helper", ScopeKind::Function);
+        visualizer.add_stack_value("a", "String");
+        visualizer.add_heap_value("a", "String", 5, "hello");
+        visualizer.add_stack_value("b", "i32");
+        assert_eq!(visualizer.stack_items[1].scope, 1);
+        
+        assert_eq!(visualizer.pop_scope(), vec!["b", "a"]);
+        assert!(visualizer.heap_items.is_empty());
+        assert_eq!(visualizer.stack_items.len(), 1);
+        
+        // The main frame stays
+        assert!(visualizer.pop_scope().is_empty());
+        assert_eq!(visualizer.scopes().len(), 1);
+    }
 }
@@ -1,6 +1,8 @@
 // Integration tests for the Memory Visualizer
 //
 // These tests verify that the complete application works correctly
+// The original tests pass argument arrays by reference
+#![allow(clippy::needless_borrows_for_generic_args)]
 
 use assert_cmd::Command;
 use predicates::prelude::*;
@@ -68,6 +70,199 @@ fn test_verbose_flag() {
         .stdout(predicate::str::contains("Memory Operations Summary"));
 }
 
+#[test]
+fn test_export_and_replay() {
+    let path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("ownership-timeline.json");
+
+    let mut cmd = Command::cargo_bin("memory-visualizer").unwrap();
+    cmd.args(["ownership", "--export"])
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Saved"));
+
+    let json = std::fs::read_to_string(&path).unwrap();
+    assert!(json.contains(r#""operation": "move""#));
+    assert!(json.contains(r#""location": "s1 -> s2""#));
+
+    let mut cmd = Command::cargo_bin("memory-visualizer").unwrap();
+    cmd.arg("replay")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Step 1 of"))
+        .stdout(predicate::str::contains("ownership moves from s1 to s2"));
+}
+
+#[test]
+fn test_replay_missing_file() {
+    let mut cmd = Command::cargo_bin("memory-visualizer").unwrap();
+    cmd.args(["replay", "no-such-timeline.json"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot replay no-such-timeline.json"));
+}
+
+#[test]
+fn test_use_after_move_is_explained() {
+    let mut cmd = Command::cargo_bin("memory-visualizer").unwrap();
+    cmd.arg("interactive")
+        .write_stdin("ownership\ncreate a\nmove a b\nuse a\nback\nquit\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("error[E0382]: use of moved value: `a`"))
+        .stdout(predicate::str::contains("step 2: `a` is moved to `b` here"));
+}
+
+#[test]
+fn test_replay_reports_broken_rules() {
+    let path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("dangling-timeline.json");
+    std::fs::write(
+        &path,
+        r#"{"operations": [
+            {"step": 1, "operation": "allocate", "location": "temp", "type": "String", "size": 9},
+            {"step": 2, "operation": "borrow", "location": "r -> temp", "type": "String", "size": 0},
+            {"step": 3, "operation": "deallocate", "location": "temp", "type": "String", "size": 9},
+            {"step": 4, "operation": "use", "location": "r", "type": "String", "size": 0}
+        ]}"#,
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("memory-visualizer").unwrap();
+    cmd.arg("replay")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("error[E0597]: `temp` does not live long enough"));
+}
+
+#[test]
+fn test_run_scenario() {
+    let mut cmd = Command::cargo_bin("memory-visualizer").unwrap();
+    cmd.args(["run", "scenarios/broken_rules.txt"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("  7 | move s -> t"))
+        .stdout(predicate::str::contains("error[E0382]: use of moved value: `s`"))
+        .stdout(predicate::str::contains("error[E0597]: `temp` does not live long enough"));
+}
+
+#[test]
+fn test_run_reports_parse_errors() {
+    let path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("bad-scenario.txt");
+    std::fs::write(&path, "let s = String(\"hi\", 2)\nmove s t\n").unwrap();
+
+    let mut cmd = Command::cargo_bin("memory-visualizer").unwrap();
+    cmd.arg("run")
+        .arg(&path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("line 2: expected `move <from> -> <to>`"));
+}
+
+#[test]
+fn test_scopes_drop_in_reverse_order() {
+    let mut cmd = Command::cargo_bin("memory-visualizer").unwrap();
+    let output = cmd.args(["run", "scenarios/scopes.txt"]).assert().success().get_output().stdout.clone();
+    let output = String::from_utf8(output).unwrap();
+
+    let drops: Vec<&str> = output
+        .lines()
+        .filter(|line| line.contains("DROP"))
+        .map(|line| line.split_whitespace().nth(2).unwrap())
+        .collect();
+    assert_eq!(drops, vec!["numbers", "s", "r", "second", "first"]);
+    assert!(output.contains("┌─ fn takes_ownership"));
+}
+
+#[test]
+fn test_leaked_cycles_are_reported() {
+    let mut cmd = Command::cargo_bin("memory-visualizer").unwrap();
+    cmd.args(["run", "scenarios/rc_cycle.txt", "--verbose"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Refs: 2 strong, 1 weak"))
+        .stdout(predicate::str::contains("leaked in a cycle"))
+        .stdout(predicate::str::contains("1 reference cycle(s) leaked"))
+        .stdout(predicate::str::contains("a → b → a keeps 2 values (32 bytes) alive"));
+}
+
+#[test]
+fn test_diagrams_for_every_step() {
+    let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("scenario-diagrams");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let mut cmd = Command::cargo_bin("memory-visualizer").unwrap();
+    cmd.args(["run", "scenarios/move_and_borrow.txt", "--diagrams"])
+        .arg(&dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Saved 12 step diagrams"));
+
+    let dot = std::fs::read_to_string(dir.join("step-002.dot")).unwrap();
+    assert!(dot.contains("ownership moves from s1 to s2"));
+    assert!(dot.contains(r#"label="s2: String""#));
+    let svg = std::fs::read_to_string(dir.join("step-012.svg")).unwrap();
+    assert!(svg.starts_with("<svg"));
+}
+
+#[test]
+fn test_tui_needs_a_terminal() {
+    // The output is captured here, so there is no terminal to take over
+    let mut cmd = Command::cargo_bin("memory-visualizer").unwrap();
+    cmd.args(["ownership", "--tui"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Error: cannot open the terminal UI: the terminal UI needs an interactive terminal"));
+}
+
+#[test]
+fn test_inspect_layouts() {
+    let mut cmd = Command::cargo_bin("memory-visualizer").unwrap();
+    cmd.args(["inspect", "Padded", "Option<Box<i32>>"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("a···bbbb cc··"))
+        .stdout(predicate::str::contains("5 bytes of padding"))
+        .stdout(predicate::str::contains("None is the null pointer"));
+
+    let mut cmd = Command::cargo_bin("memory-visualizer").unwrap();
+    cmd.args(["inspect", "--struct", "Packet { flag: bool, id: u64 }"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1..8    · padding (7 bytes)"));
+
+    let mut cmd = Command::cargo_bin("memory-visualizer").unwrap();
+    cmd.args(["inspect", "Banana"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Error: cannot inspect Banana: unknown type"));
+}
+
+// Run with `cargo test --features count-allocations`
+#[cfg(feature = "count-allocations")]
+#[test]
+fn test_measured_allocations() {
+    let mut cmd = Command::cargo_bin("memory-visualizer").unwrap();
+    cmd.args(["ownership", "--verbose"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("String::from(\"hello\"): 1 allocations (5 bytes)"))
+        .stdout(predicate::str::contains("moving s1 to s2: 0 allocations (0 bytes)"))
+        .stdout(predicate::str::contains("Whole program (main thread)"));
+}
+
+#[cfg(not(feature = "count-allocations"))]
+#[test]
+fn test_measurements_need_the_feature() {
+    let mut cmd = Command::cargo_bin("memory-visualizer").unwrap();
+    cmd.args(["ownership", "--verbose"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("MEASURED").not())
+        .stdout(predicate::str::contains("--features count-allocations"));
+}
+
 // TODO: Add tests for interactive mode
 // Note: Interactive tests are more complex and might need special handling
 
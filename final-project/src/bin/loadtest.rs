@@ -1,168 +0,0 @@
-use std::sync::Arc;
-use std::time::{Duration, Instant};
-use tokio::net::TcpStream;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::time::sleep;
-
-#[derive(Debug)]
-struct LoadTestConfig {
-    target_url: String,
-    concurrent_connections: usize,
-    requests_per_connection: usize,
-    test_duration: Duration,
-}
-
-impl Default for LoadTestConfig {
-    fn default() -> Self {
-        LoadTestConfig {
-            target_url: "127.0.0.1:8080".to_string(),
-            concurrent_connections: 10,
-            requests_per_connection: 100,
-            test_duration: Duration::from_secs(30),
-        }
-    }
-}
-
-#[derive(Debug, Clone)]
-struct TestResult {
-    total_requests: usize,
-    successful_requests: usize,
-    failed_requests: usize,
-    average_response_time: Duration,
-    min_response_time: Duration,
-    max_response_time: Duration,
-}
-
-// FIXME: This load tester has several issues
-async fn send_request(target: &str) -> Result<Duration, Box<dyn std::error::Error>> {
-    let start = Instant::now();
-    
-    // TODO: Connection pooling is missing
-    let mut stream = TcpStream::connect(target).await?;
-    
-    let request = "GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
-    stream.write_all(request.as_bytes()).await?;
-    
-    // FIXME: This doesn't handle partial reads
-    let mut buffer = [0; 4096];
-    let _bytes_read = stream.read(&mut buffer).await?;
-    
-    // TODO: Parse response and check status code
-    
-    Ok(start.elapsed())
-}
-
-async fn run_connection_test(target: String, requests: usize) -> TestResult {
-    let mut results = Vec::new();
-    let mut successful = 0;
-    let mut failed = 0;
-    
-    for i in 0..requests {
-        match send_request(&target).await {
-            Ok(duration) => {
-                results.push(duration);
-                successful += 1;
-            }
-            Err(e) => {
-                eprintln!("Request {} failed: {}", i, e);
-                failed += 1;
-            }
-        }
-        
-        // TODO: Add configurable delay between requests
-        sleep(Duration::from_millis(10)).await;
-    }
-    
-    // FIXME: Statistics calculation is incomplete
-    let total_time: Duration = results.iter().sum();
-    let avg_time = if !results.is_empty() {
-        total_time / results.len() as u32
-    } else {
-        Duration::from_secs(0)
-    };
-    
-    let min_time = results.iter().min().copied().unwrap_or(Duration::from_secs(0));
-    let max_time = results.iter().max().copied().unwrap_or(Duration::from_secs(0));
-    
-    TestResult {
-        total_requests: requests,
-        successful_requests: successful,
-        failed_requests: failed,
-        average_response_time: avg_time,
-        min_response_time: min_time,
-        max_response_time: max_time,
-    }
-}
-
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let config = LoadTestConfig::default();
-    
-    println!("Starting load test...");
-    println!("Target: {}", config.target_url);
-    println!("Concurrent connections: {}", config.concurrent_connections);
-    println!("Requests per connection: {}", config.requests_per_connection);
-    
-    let start_time = Instant::now();
-    
-    // FIXME: This doesn't actually run connections concurrently!
-    let mut handles = Vec::new();
-    
-    for i in 0..config.concurrent_connections {
-        let target = config.target_url.clone();
-        let requests = config.requests_per_connection;
-        
-        // TODO: This should spawn concurrent tasks
-        let handle = tokio::spawn(async move {
-            println!("Starting connection {}", i);
-            run_connection_test(target, requests).await
-        });
-        
-        handles.push(handle);
-    }
-    
-    // Collect results
-    let mut total_successful = 0;
-    let mut total_failed = 0;
-    let mut all_response_times = Vec::new();
-    
-    for handle in handles {
-        let result = handle.await?;
-        total_successful += result.successful_requests;
-        total_failed += result.failed_requests;
-        
-        // TODO: Collect individual response times for better statistics
-        println!("Connection completed: {} successful, {} failed", 
-                 result.successful_requests, result.failed_requests);
-    }
-    
-    let total_time = start_time.elapsed();
-    let total_requests = total_successful + total_failed;
-    let requests_per_second = total_requests as f64 / total_time.as_secs_f64();
-    
-    println!("\n=== Load Test Results ===");
-    println!("Total requests: {}", total_requests);
-    println!("Successful: {}", total_successful);
-    println!("Failed: {}", total_failed);
-    println!("Success rate: {:.2}%", (total_successful as f64 / total_requests as f64) * 100.0);
-    println!("Requests per second: {:.2}", requests_per_second);
-    println!("Total time: {:?}", total_time);
-    
-    // TODO: Add more detailed statistics (percentiles, etc.)
-    
-    Ok(())
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    
-    #[test]
-    fn test_load_test_config() {
-        let config = LoadTestConfig::default();
-        assert_eq!(config.concurrent_connections, 10);
-        assert_eq!(config.requests_per_connection, 100);
-    }
-    
-    // TODO: Add integration tests for the load tester
-}
\ No newline at end of file
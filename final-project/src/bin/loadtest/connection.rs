@@ -0,0 +1,333 @@
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::time::{timeout_at, Instant};
+
+use crate::http::{self, HttpResponse, RequestError};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ConnectionStats {
+    /// TCP connections opened
+    pub opened: usize,
+    /// Requests sent on a connection that had already served a response
+    pub reused_requests: usize,
+    /// Idle connections the server had closed, detected on reuse and retried
+    pub stale_retries: usize,
+}
+
+// The server may have acted on a request before it closed the connection,
+// so only requests that are safe to repeat are sent again.
+fn is_idempotent(request: &[u8]) -> bool {
+    ["GET ", "HEAD ", "PUT ", "DELETE ", "OPTIONS ", "TRACE "]
+        .iter()
+        .any(|method| request.starts_with(method.as_bytes()))
+}
+
+impl ConnectionStats {
+    pub fn merge(&mut self, other: &ConnectionStats) {
+        self.opened += other.opened;
+        self.reused_requests += other.reused_requests;
+        self.stale_retries += other.stale_retries;
+    }
+}
+
+/// Responses read from one batch of requests, in order. If `error` is set,
+/// every request after the last response failed with it.
+#[derive(Debug)]
+pub struct BatchOutcome {
+    pub responses: Vec<(Duration, HttpResponse)>,
+    pub error: Option<RequestError>,
+}
+
+struct Connection {
+    stream: BufReader<TcpStream>,
+    served: usize,
+}
+
+/// Idle keep-alive connections to one target.
+///
+/// Closed-loop workers each own a pool, so their connection is never shared;
+/// open-loop requests check connections out of one pool shared by all tasks.
+/// With `keep_alive` off every batch gets a fresh connection.
+pub struct ConnectionPool {
+    target: String,
+    keep_alive: bool,
+    idle: Mutex<Vec<Connection>>,
+    opened: AtomicUsize,
+    reused_requests: AtomicUsize,
+    stale_retries: AtomicUsize,
+}
+
+impl ConnectionPool {
+    pub fn new(target: &str, keep_alive: bool) -> Self {
+        ConnectionPool {
+            target: target.to_string(),
+            keep_alive,
+            idle: Mutex::new(Vec::new()),
+            opened: AtomicUsize::new(0),
+            reused_requests: AtomicUsize::new(0),
+            stale_retries: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn stats(&self) -> ConnectionStats {
+        ConnectionStats {
+            opened: self.opened.load(Ordering::Relaxed),
+            reused_requests: self.reused_requests.load(Ordering::Relaxed),
+            stale_retries: self.stale_retries.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Writes `request` `depth` times back to back (pipelining when depth > 1)
+    /// and reads the responses. Latencies are measured from `start`; the whole
+    /// exchange, including connect, must finish within `limit` from now.
+    pub async fn send(
+        &self,
+        request: &[u8],
+        depth: usize,
+        start: Instant,
+        limit: Duration,
+    ) -> BatchOutcome {
+        let deadline = Instant::now() + limit;
+        let idle = if self.keep_alive { self.idle.lock().pop() } else { None };
+
+        if let Some(connection) = idle {
+            let outcome = self.exchange(connection, request, depth, start, deadline).await;
+            // A reused connection may have been closed by the server while it
+            // sat idle; that is not a server failure, so retry on a new one.
+            let stale = outcome.responses.is_empty()
+                && matches!(outcome.error, Some(RequestError::Reset(_)))
+                && is_idempotent(request);
+            if !stale {
+                return outcome;
+            }
+            self.stale_retries.fetch_add(1, Ordering::Relaxed);
+        }
+
+        match timeout_at(deadline, TcpStream::connect(&self.target)).await {
+            Ok(Ok(stream)) => {
+                self.opened.fetch_add(1, Ordering::Relaxed);
+                let connection = Connection {
+                    stream: BufReader::new(stream),
+                    served: 0,
+                };
+                self.exchange(connection, request, depth, start, deadline).await
+            }
+            Ok(Err(e)) => BatchOutcome {
+                responses: Vec::new(),
+                error: Some(e.into()),
+            },
+            Err(_) => BatchOutcome {
+                responses: Vec::new(),
+                error: Some(RequestError::Timeout),
+            },
+        }
+    }
+
+    async fn exchange(
+        &self,
+        mut connection: Connection,
+        request: &[u8],
+        depth: usize,
+        start: Instant,
+        deadline: Instant,
+    ) -> BatchOutcome {
+        let reused = connection.served > 0;
+        let mut outcome = BatchOutcome {
+            responses: Vec::with_capacity(depth),
+            error: None,
+        };
+
+        let batch = request.repeat(depth);
+        match timeout_at(deadline, connection.stream.write_all(&batch)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => {
+                outcome.error = Some(e.into());
+                return outcome;
+            }
+            Err(_) => {
+                outcome.error = Some(RequestError::Timeout);
+                return outcome;
+            }
+        }
+
+        let mut reusable = self.keep_alive;
+        for _ in 0..depth {
+            match timeout_at(deadline, http::read_response(&mut connection.stream)).await {
+                Ok(Ok(response)) => {
+                    connection.served += 1;
+                    reusable &= response.keep_alive;
+                    outcome.responses.push((start.elapsed(), response));
+                    if !reusable {
+                        break;
+                    }
+                }
+                Ok(Err(e)) => {
+                    outcome.error = Some(e);
+                    break;
+                }
+                Err(_) => {
+                    outcome.error = Some(RequestError::Timeout);
+                    break;
+                }
+            }
+        }
+
+        if reused {
+            self.reused_requests.fetch_add(outcome.responses.len(), Ordering::Relaxed);
+        }
+
+        if outcome.error.is_some() {
+            return outcome;
+        }
+        if outcome.responses.len() < depth {
+            // The server closed after answering only part of the pipeline
+            outcome.error = Some(RequestError::Reset(std::io::Error::new(
+                std::io::ErrorKind::ConnectionAborted,
+                "connection closed with pipelined requests outstanding",
+            )));
+        } else if reusable {
+            self.idle.lock().push(connection);
+        }
+
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    const OK: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok";
+    const REQUEST: &[u8] = b"GET / HTTP/1.1\r\nHost: test\r\n\r\n";
+
+    // Answers every request on a connection with OK until the client hangs up,
+    // or only the first `per_connection` requests if given.
+    async fn spawn_server(per_connection: Option<usize>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let mut answered = 0;
+                    let mut pending = Vec::new();
+                    let mut buffer = [0; 1024];
+                    loop {
+                        let read = match stream.read(&mut buffer).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => n,
+                        };
+                        pending.extend_from_slice(&buffer[..read]);
+                        while let Some(end) = pending.windows(4).position(|w| w == b"\r\n\r\n") {
+                            pending.drain(..end + 4);
+                            if per_connection == Some(answered) {
+                                return;
+                            }
+                            stream.write_all(OK).await.unwrap();
+                            answered += 1;
+                        }
+                    }
+                });
+            }
+        });
+
+        addr
+    }
+
+    async fn send(pool: &ConnectionPool, depth: usize) -> BatchOutcome {
+        pool.send(REQUEST, depth, Instant::now(), Duration::from_secs(5)).await
+    }
+
+    #[tokio::test]
+    async fn test_keep_alive_reuses_connection() {
+        let pool = ConnectionPool::new(&spawn_server(None).await, true);
+
+        for _ in 0..5 {
+            let outcome = send(&pool, 1).await;
+            assert!(outcome.error.is_none());
+            assert_eq!(outcome.responses.len(), 1);
+        }
+
+        let stats = pool.stats();
+        assert_eq!(stats.opened, 1);
+        assert_eq!(stats.reused_requests, 4);
+    }
+
+    #[tokio::test]
+    async fn test_fresh_connections() {
+        let pool = ConnectionPool::new(&spawn_server(None).await, false);
+
+        for _ in 0..3 {
+            assert!(send(&pool, 1).await.error.is_none());
+        }
+
+        assert_eq!(pool.stats().opened, 3);
+        assert_eq!(pool.stats().reused_requests, 0);
+    }
+
+    #[tokio::test]
+    async fn test_pipelining() {
+        let pool = ConnectionPool::new(&spawn_server(None).await, true);
+
+        let outcome = send(&pool, 8).await;
+        assert!(outcome.error.is_none());
+        assert_eq!(outcome.responses.len(), 8);
+        assert!(outcome.responses.windows(2).all(|w| w[0].0 <= w[1].0));
+        assert_eq!(pool.stats().opened, 1);
+    }
+
+    #[tokio::test]
+    async fn test_stale_connection_is_retried() {
+        // The server hangs up after one response without announcing it
+        let pool = ConnectionPool::new(&spawn_server(Some(1)).await, true);
+
+        assert!(send(&pool, 1).await.error.is_none());
+        let outcome = send(&pool, 1).await;
+        assert!(outcome.error.is_none(), "{:?}", outcome.error);
+
+        let stats = pool.stats();
+        assert_eq!(stats.opened, 2);
+        assert_eq!(stats.stale_retries, 1);
+    }
+
+    #[tokio::test]
+    async fn test_stale_post_is_not_retried() {
+        let pool = ConnectionPool::new(&spawn_server(Some(1)).await, true);
+        let post = b"POST /orders HTTP/1.1\r\nHost: test\r\nContent-Length: 0\r\n\r\n";
+
+        let send_post = || pool.send(post, 1, Instant::now(), Duration::from_secs(5));
+        assert!(send_post().await.error.is_none());
+        let outcome = send_post().await;
+        assert_eq!(
+            outcome.error.map(|e| e.category()),
+            Some(crate::http::ErrorCategory::Reset)
+        );
+        assert_eq!(pool.stats().stale_retries, 0);
+    }
+
+    #[test]
+    fn test_idempotent_methods() {
+        assert!(is_idempotent(REQUEST));
+        assert!(is_idempotent(b"DELETE /users/1 HTTP/1.1\r\n\r\n"));
+        assert!(!is_idempotent(b"POST /login HTTP/1.1\r\n\r\n"));
+        assert!(!is_idempotent(b"PATCH /users/1 HTTP/1.1\r\n\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_partial_pipeline_reports_reset() {
+        let pool = ConnectionPool::new(&spawn_server(Some(2)).await, true);
+
+        let outcome = send(&pool, 4).await;
+        assert_eq!(outcome.responses.len(), 2);
+        assert_eq!(
+            outcome.error.map(|e| e.category()),
+            Some(crate::http::ErrorCategory::Reset)
+        );
+    }
+}
@@ -0,0 +1,277 @@
+use hdrhistogram::Histogram;
+use std::time::Duration;
+
+// Latencies are recorded in microseconds. Three significant digits keeps
+// every recorded value within 0.1% of the true latency across the whole range.
+const LOWEST_TRACKABLE_US: u64 = 1;
+const HIGHEST_TRACKABLE_US: u64 = 60_000_000;
+const SIGNIFICANT_DIGITS: u8 = 3;
+
+// Upper bounds (in microseconds) of the buckets shown in the distribution report
+const BUCKET_BOUNDS_US: [u64; 12] = [
+    1_000, 2_000, 5_000, 10_000, 20_000, 50_000,
+    100_000, 200_000, 500_000, 1_000_000, 2_000_000, 5_000_000,
+];
+
+pub const REPORTED_PERCENTILES: [f64; 5] = [50.0, 90.0, 95.0, 99.0, 99.9];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencyBucket {
+    pub lower: Duration,
+    pub upper: Option<Duration>,
+    pub count: u64,
+}
+
+/// High-dynamic-range latency histogram.
+///
+/// Each worker records into its own histogram and the results are merged
+/// once the run is over, so no locking happens on the request path.
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram {
+    inner: Histogram<u64>,
+}
+
+impl LatencyHistogram {
+    pub fn new() -> Self {
+        let mut inner = Histogram::new_with_bounds(
+            LOWEST_TRACKABLE_US,
+            HIGHEST_TRACKABLE_US,
+            SIGNIFICANT_DIGITS,
+        )
+        .expect("histogram bounds are valid");
+        // Grow instead of clamping if a request takes longer than a minute
+        inner.auto(true);
+
+        LatencyHistogram { inner }
+    }
+
+    pub fn record(&mut self, latency: Duration) {
+        let micros = u64::try_from(latency.as_micros()).unwrap_or(u64::MAX);
+        let micros = micros.max(LOWEST_TRACKABLE_US);
+        if self.inner.record(micros).is_err() {
+            self.inner.saturating_record(micros);
+        }
+    }
+
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        self.inner
+            .add(&other.inner)
+            .expect("auto-resizing histograms can always be merged");
+    }
+
+    pub fn len(&self) -> u64 {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    pub fn min(&self) -> Duration {
+        if self.is_empty() {
+            return Duration::ZERO;
+        }
+        Duration::from_micros(self.inner.min())
+    }
+
+    pub fn max(&self) -> Duration {
+        Duration::from_micros(self.inner.max())
+    }
+
+    pub fn mean(&self) -> Duration {
+        Duration::from_secs_f64(self.inner.mean() / 1_000_000.0)
+    }
+
+    pub fn stdev(&self) -> Duration {
+        Duration::from_secs_f64(self.inner.stdev() / 1_000_000.0)
+    }
+
+    /// Latency at the given percentile (0.0 - 100.0)
+    pub fn percentile(&self, percentile: f64) -> Duration {
+        Duration::from_micros(self.inner.value_at_percentile(percentile))
+    }
+
+    /// Counts per fixed latency bucket, skipping empty buckets
+    pub fn distribution(&self) -> Vec<LatencyBucket> {
+        let mut buckets = Vec::new();
+        let mut lower = 0;
+
+        for &upper in BUCKET_BOUNDS_US.iter() {
+            let count = self.count_in(lower, upper);
+            if count > 0 {
+                buckets.push(LatencyBucket {
+                    lower: Duration::from_micros(lower),
+                    upper: Some(Duration::from_micros(upper)),
+                    count,
+                });
+            }
+            lower = upper;
+        }
+
+        let overflow = self.count_in(lower, u64::MAX);
+        if overflow > 0 {
+            buckets.push(LatencyBucket {
+                lower: Duration::from_micros(lower),
+                upper: None,
+                count: overflow,
+            });
+        }
+
+        buckets
+    }
+
+    /// Every distinct recorded value (at histogram precision) with its count
+    pub fn recorded(&self) -> Vec<(Duration, u64)> {
+        self.inner
+            .iter_recorded()
+            .map(|v| {
+                let value = self.inner.lowest_equivalent(v.value_iterated_to());
+                (Duration::from_micros(value), v.count_at_value())
+            })
+            .collect()
+    }
+
+    // Count of values in [lower, upper)
+    fn count_in(&self, lower: u64, upper: u64) -> u64 {
+        self.inner
+            .iter_recorded()
+            .filter(|v| {
+                let value = self.inner.lowest_equivalent(v.value_iterated_to());
+                value >= lower && value < upper
+            })
+            .map(|v| v.count_at_value())
+            .sum()
+    }
+
+    pub fn print_report(&self) {
+        println!("\n=== Latency ===");
+
+        if self.is_empty() {
+            println!("No successful requests recorded");
+            return;
+        }
+
+        println!("Samples: {}", self.len());
+        println!("Min:     {:?}", self.min());
+        println!("Mean:    {:?}", self.mean());
+        println!("Std dev: {:?}", self.stdev());
+        for p in REPORTED_PERCENTILES {
+            println!("{:<9}{:?}", format!("p{}:", p), self.percentile(p));
+        }
+        println!("Max:     {:?}", self.max());
+
+        println!("\nLatency distribution:");
+        let total = self.len() as f64;
+        for bucket in self.distribution() {
+            let share = bucket.count as f64 / total;
+            let bar = "█".repeat((share * 40.0).ceil() as usize);
+            let range = match bucket.upper {
+                Some(upper) => format!("{:?} - {:?}", bucket.lower, upper),
+                None => format!(">= {:?}", bucket.lower),
+            };
+            println!("  {:<18} {:>8} {:>6.2}% {}", range, bucket.count, share * 100.0, bar);
+        }
+    }
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn histogram_of(millis: impl IntoIterator<Item = u64>) -> LatencyHistogram {
+        let mut histogram = LatencyHistogram::new();
+        for ms in millis {
+            histogram.record(Duration::from_millis(ms));
+        }
+        histogram
+    }
+
+    fn assert_close(actual: Duration, expected: Duration) {
+        let diff = actual.as_secs_f64() - expected.as_secs_f64();
+        assert!(
+            diff.abs() <= expected.as_secs_f64() * 0.001,
+            "{:?} is not within 0.1% of {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_empty_histogram() {
+        let histogram = LatencyHistogram::new();
+        assert!(histogram.is_empty());
+        assert_eq!(histogram.min(), Duration::ZERO);
+        assert!(histogram.distribution().is_empty());
+    }
+
+    #[test]
+    fn test_percentiles() {
+        let histogram = histogram_of(1..=1000);
+
+        assert_eq!(histogram.len(), 1000);
+        assert_close(histogram.min(), Duration::from_millis(1));
+        assert_close(histogram.percentile(50.0), Duration::from_millis(500));
+        assert_close(histogram.percentile(99.0), Duration::from_millis(990));
+        assert!(histogram.percentile(99.9) >= Duration::from_millis(999));
+        assert_close(histogram.max(), Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn test_mean_and_stdev() {
+        let histogram = histogram_of([10, 20, 30]);
+
+        assert_close(histogram.mean(), Duration::from_millis(20));
+        // Population standard deviation of 10, 20, 30
+        assert_close(histogram.stdev(), Duration::from_micros(8165));
+    }
+
+    #[test]
+    fn test_merge_matches_single_histogram() {
+        let mut merged = histogram_of(1..=500);
+        merged.merge(&histogram_of(501..=1000));
+
+        let single = histogram_of(1..=1000);
+        assert_eq!(merged.len(), single.len());
+        for p in REPORTED_PERCENTILES {
+            assert_eq!(merged.percentile(p), single.percentile(p));
+        }
+    }
+
+    #[test]
+    fn test_recorded_values() {
+        let histogram = histogram_of([5, 5, 7]);
+        let recorded: Vec<(u128, u64)> = histogram
+            .recorded()
+            .into_iter()
+            .map(|(value, count)| (value.as_millis(), count))
+            .collect();
+        assert_eq!(recorded, vec![(5, 2), (7, 1)]);
+    }
+
+    #[test]
+    fn test_records_beyond_highest_trackable() {
+        let mut histogram = LatencyHistogram::new();
+        histogram.record(Duration::from_secs(120));
+        assert_close(histogram.max(), Duration::from_secs(120));
+    }
+
+    #[test]
+    fn test_distribution_buckets() {
+        let histogram = histogram_of([0, 1, 3, 3, 7000]);
+        let buckets = histogram.distribution();
+
+        assert_eq!(buckets.len(), 4);
+        assert_eq!(buckets[0].count, 1); // < 1ms
+        assert_eq!(buckets[1].count, 1); // 1ms - 2ms
+        assert_eq!(buckets[2].count, 2); // 2ms - 5ms
+        assert_eq!(buckets[3].upper, None);
+        assert_eq!(buckets[3].count, 1);
+        assert_eq!(buckets.iter().map(|b| b.count).sum::<u64>(), histogram.len());
+    }
+}
@@ -0,0 +1,449 @@
+use std::fmt;
+use std::io;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt};
+
+/// Largest body read into memory; anything bigger is reported as malformed
+/// rather than trusting the server's length
+pub const MAX_BODY_BYTES: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub reason: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    /// Whether the server left the connection open for another request
+    pub keep_alive: bool,
+}
+
+impl HttpResponse {
+    /// Case-insensitive header lookup
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Failure categories shown in the error breakdown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ErrorCategory {
+    ConnectRefused,
+    Timeout,
+    Reset,
+    BadStatus(u16),
+    UnexpectedBody,
+    Capture,
+    Malformed,
+    Io,
+}
+
+impl fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorCategory::ConnectRefused => write!(f, "connect refused"),
+            ErrorCategory::Timeout => write!(f, "timeout"),
+            ErrorCategory::Reset => write!(f, "connection reset"),
+            ErrorCategory::BadStatus(code) => write!(f, "bad status {}", code),
+            ErrorCategory::UnexpectedBody => write!(f, "unexpected body"),
+            ErrorCategory::Capture => write!(f, "capture failed"),
+            ErrorCategory::Malformed => write!(f, "malformed response"),
+            ErrorCategory::Io => write!(f, "other I/O error"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RequestError {
+    ConnectRefused(io::Error),
+    Timeout,
+    Reset(io::Error),
+    BadStatus(u16),
+    UnexpectedBody(String),
+    Capture(String),
+    Malformed(String),
+    Io(io::Error),
+}
+
+impl RequestError {
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            RequestError::ConnectRefused(_) => ErrorCategory::ConnectRefused,
+            RequestError::Timeout => ErrorCategory::Timeout,
+            RequestError::Reset(_) => ErrorCategory::Reset,
+            RequestError::BadStatus(code) => ErrorCategory::BadStatus(*code),
+            RequestError::UnexpectedBody(_) => ErrorCategory::UnexpectedBody,
+            RequestError::Capture(_) => ErrorCategory::Capture,
+            RequestError::Malformed(_) => ErrorCategory::Malformed,
+            RequestError::Io(_) => ErrorCategory::Io,
+        }
+    }
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RequestError::ConnectRefused(e) => write!(f, "connection refused: {}", e),
+            RequestError::Timeout => write!(f, "request timed out"),
+            RequestError::Reset(e) => write!(f, "connection reset: {}", e),
+            RequestError::BadStatus(code) => write!(f, "unexpected status code {}", code),
+            RequestError::UnexpectedBody(missing) => {
+                write!(f, "response body does not contain {:?}", missing)
+            }
+            RequestError::Capture(reason) => write!(f, "cannot capture variable: {}", reason),
+            RequestError::Malformed(reason) => write!(f, "malformed response: {}", reason),
+            RequestError::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RequestError {}
+
+impl From<io::Error> for RequestError {
+    fn from(error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::ConnectionRefused => RequestError::ConnectRefused(error),
+            io::ErrorKind::TimedOut => RequestError::Timeout,
+            io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::UnexpectedEof => RequestError::Reset(error),
+            _ => RequestError::Io(error),
+        }
+    }
+}
+
+/// What a response must look like to count as a success
+#[derive(Debug, Clone, Default)]
+pub struct Expectations {
+    /// Accepted status codes; any 2xx or 3xx when empty
+    pub statuses: Vec<u16>,
+    /// Substrings that must all appear in the body
+    pub body_contains: Vec<String>,
+}
+
+impl Expectations {
+    pub fn check(&self, response: &HttpResponse) -> Result<(), RequestError> {
+        let status_ok = if self.statuses.is_empty() {
+            (200..400).contains(&response.status)
+        } else {
+            self.statuses.contains(&response.status)
+        };
+        if !status_ok {
+            return Err(RequestError::BadStatus(response.status));
+        }
+
+        if !self.body_contains.is_empty() {
+            let body = String::from_utf8_lossy(&response.body);
+            if let Some(missing) = self.body_contains.iter().find(|s| !body.contains(s.as_str())) {
+                return Err(RequestError::UnexpectedBody(missing.clone()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Read one complete response: status line, headers and the body framed
+/// by `Transfer-Encoding: chunked`, `Content-Length`, or connection close.
+pub async fn read_response<R>(reader: &mut R) -> Result<HttpResponse, RequestError>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let status_line = read_line(reader).await?;
+    let (version, status, reason) = parse_status_line(&status_line)?;
+
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line(reader).await?;
+        if line.is_empty() {
+            break;
+        }
+        let (key, value) = line
+            .split_once(':')
+            .ok_or_else(|| RequestError::Malformed(format!("invalid header line {:?}", line)))?;
+        headers.push((key.trim().to_string(), value.trim().to_string()));
+    }
+
+    let mut response = HttpResponse {
+        status,
+        reason,
+        headers,
+        body: Vec::new(),
+        keep_alive: false,
+    };
+    // HTTP/1.1 connections are persistent unless the server says otherwise
+    response.keep_alive = match response.header("Connection").map(str::to_ascii_lowercase) {
+        Some(value) if value.contains("close") => false,
+        Some(value) if value.contains("keep-alive") => true,
+        _ => version == "HTTP/1.1",
+    };
+
+    // 1xx, 204 and 304 responses never carry a body
+    if (100..200).contains(&status) || status == 204 || status == 304 {
+        return Ok(response);
+    }
+
+    let chunked = response
+        .header("Transfer-Encoding")
+        .map(|v| v.to_ascii_lowercase().contains("chunked"))
+        .unwrap_or(false);
+
+    response.body = if chunked {
+        read_chunked_body(reader).await?
+    } else if let Some(length) = response.header("Content-Length") {
+        let length: usize = length
+            .parse()
+            .map_err(|_| RequestError::Malformed(format!("invalid Content-Length {:?}", length)))?;
+        check_body_size(length)?;
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).await?;
+        body
+    } else {
+        // Body runs until the server closes, so the connection is spent
+        response.keep_alive = false;
+        let mut body = Vec::new();
+        reader.take(MAX_BODY_BYTES as u64 + 1).read_to_end(&mut body).await?;
+        check_body_size(body.len())?;
+        body
+    };
+
+    Ok(response)
+}
+
+fn parse_status_line(line: &str) -> Result<(&str, u16, String), RequestError> {
+    let mut parts = line.splitn(3, ' ');
+    let version = parts.next().unwrap_or_default();
+    if !version.starts_with("HTTP/") {
+        return Err(RequestError::Malformed(format!("invalid status line {:?}", line)));
+    }
+
+    let status = parts
+        .next()
+        .and_then(|code| code.parse::<u16>().ok())
+        .filter(|code| (100..600).contains(code))
+        .ok_or_else(|| RequestError::Malformed(format!("invalid status code in {:?}", line)))?;
+    let reason = parts.next().unwrap_or_default().to_string();
+
+    Ok((version, status, reason))
+}
+
+async fn read_chunked_body<R>(reader: &mut R) -> Result<Vec<u8>, RequestError>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut body = Vec::new();
+
+    loop {
+        let size_line = read_line(reader).await?;
+        // Chunk extensions after ';' are allowed and ignored
+        let size_hex = size_line.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size_hex, 16)
+            .map_err(|_| RequestError::Malformed(format!("invalid chunk size {:?}", size_line)))?;
+
+        if size == 0 {
+            // Skip trailers up to the terminating empty line
+            while !read_line(reader).await?.is_empty() {}
+            return Ok(body);
+        }
+
+        let start = body.len();
+        check_body_size(start.saturating_add(size))?;
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..]).await?;
+
+        if !read_line(reader).await?.is_empty() {
+            return Err(RequestError::Malformed("chunk not terminated by CRLF".to_string()));
+        }
+    }
+}
+
+fn check_body_size(length: usize) -> Result<(), RequestError> {
+    if length > MAX_BODY_BYTES {
+        return Err(RequestError::Malformed(format!(
+            "body of {} bytes exceeds the {} byte limit",
+            length, MAX_BODY_BYTES
+        )));
+    }
+    Ok(())
+}
+
+// Reads a CRLF (or bare LF) terminated line without the terminator.
+// EOF before the terminator means the server hung up mid-response.
+async fn read_line<R>(reader: &mut R) -> Result<String, RequestError>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut line = Vec::new();
+    let read = reader.read_until(b'\n', &mut line).await?;
+    if read == 0 || line.last() != Some(&b'\n') {
+        return Err(RequestError::Reset(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "connection closed mid-response",
+        )));
+    }
+
+    line.pop();
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+
+    String::from_utf8(line).map_err(|_| RequestError::Malformed("non UTF-8 header data".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn parse(raw: &str) -> Result<HttpResponse, RequestError> {
+        let mut reader = raw.as_bytes();
+        read_response(&mut reader).await
+    }
+
+    #[tokio::test]
+    async fn test_content_length_body() {
+        let response = parse("HTTP/1.1 200 OK\r\nContent-Length: 5\r\nServer: test\r\n\r\nHelloIGNORED")
+            .await
+            .unwrap();
+
+        assert_eq!(response.status, 200);
+        assert_eq!(response.reason, "OK");
+        assert_eq!(response.header("server"), Some("test"));
+        assert_eq!(response.body, b"Hello");
+        assert!(response.keep_alive);
+    }
+
+    #[tokio::test]
+    async fn test_chunked_body() {
+        let raw = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+                   5\r\nHello\r\n7;ext=1\r\n, World\r\n0\r\nX-Trailer: yes\r\n\r\n";
+        let response = parse(raw).await.unwrap();
+
+        assert_eq!(response.body, b"Hello, World");
+    }
+
+    #[tokio::test]
+    async fn test_body_until_close() {
+        let response = parse("HTTP/1.0 500 Internal Server Error\r\n\r\nboom").await.unwrap();
+
+        assert_eq!(response.status, 500);
+        assert_eq!(response.body, b"boom");
+        assert!(!response.keep_alive);
+    }
+
+    #[tokio::test]
+    async fn test_connection_header() {
+        let close = parse("HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 0\r\n\r\n").await.unwrap();
+        assert!(!close.keep_alive);
+
+        let http10 = parse("HTTP/1.0 200 OK\r\nContent-Length: 0\r\n\r\n").await.unwrap();
+        assert!(!http10.keep_alive);
+
+        let http10_keep_alive = parse("HTTP/1.0 200 OK\r\nConnection: Keep-Alive\r\nContent-Length: 0\r\n\r\n")
+            .await
+            .unwrap();
+        assert!(http10_keep_alive.keep_alive);
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_responses() {
+        let raw = "HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\none\
+                   HTTP/1.1 404 Not Found\r\nContent-Length: 3\r\n\r\ntwo";
+        let mut reader = raw.as_bytes();
+
+        let first = read_response(&mut reader).await.unwrap();
+        let second = read_response(&mut reader).await.unwrap();
+        assert_eq!((first.status, first.body), (200, b"one".to_vec()));
+        assert_eq!((second.status, second.body), (404, b"two".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_no_body_statuses() {
+        let response = parse("HTTP/1.1 204 No Content\r\n\r\n").await.unwrap();
+        assert!(response.body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_malformed_responses() {
+        let cases = [
+            "garbage\r\n\r\n",
+            "HTTP/1.1 abc OK\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nno colon here\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: ten\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n",
+        ];
+
+        for raw in cases {
+            let error = parse(raw).await.unwrap_err();
+            assert_eq!(error.category(), ErrorCategory::Malformed, "{:?}", raw);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_is_rejected() {
+        let raw = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", MAX_BODY_BYTES + 1);
+        let error = parse(&raw).await.unwrap_err();
+        assert_eq!(error.category(), ErrorCategory::Malformed);
+
+        let raw = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nffffffffffff\r\n";
+        let error = parse(raw).await.unwrap_err();
+        assert_eq!(error.category(), ErrorCategory::Malformed);
+    }
+
+    #[tokio::test]
+    async fn test_truncated_response_is_reset() {
+        let error = parse("HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nshort").await.unwrap_err();
+        assert_eq!(error.category(), ErrorCategory::Reset);
+
+        let error = parse("HTTP/1.1 200 OK\r\nServer").await.unwrap_err();
+        assert_eq!(error.category(), ErrorCategory::Reset);
+    }
+
+    #[test]
+    fn test_io_error_classification() {
+        let refused = io::Error::from(io::ErrorKind::ConnectionRefused);
+        assert_eq!(RequestError::from(refused).category(), ErrorCategory::ConnectRefused);
+
+        let reset = io::Error::from(io::ErrorKind::ConnectionReset);
+        assert_eq!(RequestError::from(reset).category(), ErrorCategory::Reset);
+
+        let timed_out = io::Error::from(io::ErrorKind::TimedOut);
+        assert_eq!(RequestError::from(timed_out).category(), ErrorCategory::Timeout);
+
+        let other = io::Error::from(io::ErrorKind::PermissionDenied);
+        assert_eq!(RequestError::from(other).category(), ErrorCategory::Io);
+    }
+
+    #[test]
+    fn test_expectations() {
+        let response = HttpResponse {
+            status: 500,
+            reason: "Internal Server Error".to_string(),
+            headers: Vec::new(),
+            body: b"database unavailable".to_vec(),
+            keep_alive: true,
+        };
+
+        let default = Expectations::default();
+        assert_eq!(
+            default.check(&response).unwrap_err().category(),
+            ErrorCategory::BadStatus(500)
+        );
+
+        let allow_500 = Expectations {
+            statuses: vec![500],
+            body_contains: vec!["database".to_string()],
+        };
+        assert!(allow_500.check(&response).is_ok());
+
+        let wrong_body = Expectations {
+            statuses: vec![500],
+            body_contains: vec!["database".to_string(), "welcome".to_string()],
+        };
+        assert_eq!(
+            wrong_body.check(&response).unwrap_err().category(),
+            ErrorCategory::UnexpectedBody
+        );
+    }
+}
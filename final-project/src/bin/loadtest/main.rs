@@ -0,0 +1,1055 @@
+use clap::{Args, Parser, Subcommand};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Semaphore};
+use tokio::time::{sleep, sleep_until, Instant};
+
+mod connection;
+mod histogram;
+mod http;
+mod profile;
+mod report;
+mod scenario;
+
+use connection::{BatchOutcome, ConnectionPool, ConnectionStats};
+use histogram::LatencyHistogram;
+use http::{ErrorCategory, Expectations, RequestError};
+use profile::LoadProfile;
+use report::{RunReport, ThroughputSample};
+use scenario::ScenarioSet;
+
+#[derive(Debug, Parser)]
+#[command(name = "loadtest")]
+#[command(about = "Load tester for the Rust web server")]
+#[command(args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    run: LoadTestConfig,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Compare two --json result files; exits non-zero if the candidate regressed
+    Compare {
+        /// Result file of the reference run
+        baseline: PathBuf,
+
+        /// Result file of the run under test
+        candidate: PathBuf,
+
+        /// Allowed slowdown of throughput and latency metrics, in percent
+        #[arg(long, default_value_t = 10.0)]
+        threshold: f64,
+
+        /// Allowed rise in error rate, in percentage points
+        #[arg(long = "max-error-increase", default_value_t = 1.0)]
+        max_error_increase: f64,
+    },
+}
+
+#[derive(Debug, Args)]
+struct LoadTestConfig {
+    /// Server address as host:port
+    #[arg(default_value = "127.0.0.1:8080")]
+    target_url: String,
+
+    /// Number of concurrent connections
+    #[arg(short = 'c', long = "connections", default_value_t = 10)]
+    concurrent_connections: usize,
+
+    /// Requests sent by each connection (scenario iterations with --scenario)
+    #[arg(short = 'n', long = "requests", default_value_t = 100)]
+    requests_per_connection: usize,
+
+    /// Maximum run time; also the length of a --rate run
+    #[arg(long = "duration", value_parser = parse_duration, default_value = "30s")]
+    test_duration: Duration,
+
+    /// Open loop: send at this many requests per second regardless of response times
+    #[arg(long, conflicts_with_all = ["profile", "profile_file"])]
+    rate: Option<f64>,
+
+    /// Open loop with a load profile, e.g. "ramp:10:200:30s,hold:200:1m,spike:1000:5s"
+    #[arg(long, conflicts_with = "profile_file")]
+    profile: Option<String>,
+
+    /// Open loop with a load profile read from a file, one stage per line
+    #[arg(long = "profile-file")]
+    profile_file: Option<PathBuf>,
+
+    /// Run the multi-step scenarios in this JSON file instead of a single request
+    #[arg(long, conflicts_with_all = ["rate", "profile", "profile_file", "pipeline"])]
+    scenario: Option<PathBuf>,
+
+    /// Open loop: cap on requests in flight at once
+    #[arg(long = "max-in-flight", default_value_t = 1000)]
+    max_in_flight: usize,
+
+    /// Open a new connection for every request instead of reusing keep-alive connections
+    #[arg(long = "fresh-connections")]
+    fresh_connections: bool,
+
+    /// Closed loop: requests written back to back on a connection before reading responses
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..),
+          conflicts_with_all = ["rate", "profile", "profile_file", "fresh_connections"])]
+    pipeline: u16,
+
+    /// Run twice, with fresh and with pooled connections, and compare the results
+    #[arg(long = "compare-connections", conflicts_with = "fresh_connections")]
+    compare_connections: bool,
+
+    /// Request path
+    #[arg(long, default_value = "/")]
+    path: String,
+
+    /// Pause between requests on the same connection
+    #[arg(long, value_parser = parse_duration, default_value = "10ms")]
+    delay: Duration,
+
+    /// Per-request timeout, including connect
+    #[arg(long, value_parser = parse_duration, default_value = "5s")]
+    timeout: Duration,
+
+    /// Accepted status codes, comma separated (default: any 2xx or 3xx)
+    #[arg(long = "expect-status", value_delimiter = ',')]
+    expect_status: Vec<u16>,
+
+    /// Text the response body must contain (repeatable)
+    #[arg(long = "expect-body")]
+    expect_body: Vec<String>,
+
+    /// Write the full results as JSON (readable by `loadtest compare`)
+    #[arg(long, conflicts_with = "compare_connections")]
+    json: Option<PathBuf>,
+
+    /// Write the full results as CSV
+    #[arg(long, conflicts_with = "compare_connections")]
+    csv: Option<PathBuf>,
+}
+
+impl LoadTestConfig {
+    fn expectations(&self) -> Expectations {
+        Expectations {
+            statuses: self.expect_status.clone(),
+            body_contains: self.expect_body.clone(),
+        }
+    }
+
+    /// The open-loop load profile, if one was requested
+    fn load_profile(&self) -> Result<Option<LoadProfile>, String> {
+        if let Some(rps) = self.rate {
+            if !(rps.is_finite() && rps > 0.0) {
+                return Err(format!("invalid rate {}", rps));
+            }
+            return Ok(Some(LoadProfile::constant(rps, self.test_duration)));
+        }
+        if let Some(spec) = &self.profile {
+            return LoadProfile::parse(spec).map(Some);
+        }
+        if let Some(path) = &self.profile_file {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+            return LoadProfile::parse_file(&contents).map(Some);
+        }
+        Ok(None)
+    }
+
+    fn workload(&self) -> Result<Workload, String> {
+        if let Some(profile) = self.load_profile()? {
+            return Ok(Workload::OpenLoop(profile));
+        }
+        if let Some(path) = &self.scenario {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+            let scenarios = ScenarioSet::parse(&contents).map_err(|e| format!("{}: {}", path.display(), e))?;
+            return Ok(Workload::Scenarios(Arc::new(scenarios)));
+        }
+        Ok(Workload::ClosedLoop)
+    }
+
+    fn request(&self, keep_alive: bool) -> String {
+        let connection = if keep_alive { "keep-alive" } else { "close" };
+        format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: {}\r\n\r\n",
+            self.path, self.target_url, connection
+        )
+    }
+}
+
+impl Default for LoadTestConfig {
+    fn default() -> Self {
+        Cli::parse_from(["loadtest"]).run
+    }
+}
+
+/// What each run sends
+#[derive(Debug)]
+enum Workload {
+    /// One request repeated by every connection
+    ClosedLoop,
+    OpenLoop(LoadProfile),
+    /// Multi-step scenarios, one iteration at a time per connection
+    Scenarios(Arc<ScenarioSet>),
+}
+
+/// Parses durations such as `250ms`, `1.5s`, `2m` or `1h`; bare numbers are seconds
+fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+
+    let value: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration {:?}", input))?;
+    let seconds = match unit.trim() {
+        "ms" => value / 1000.0,
+        "" | "s" => value,
+        "m" => value * 60.0,
+        "h" => value * 3600.0,
+        other => return Err(format!("unknown duration unit {:?} (use ms, s, m or h)", other)),
+    };
+
+    Duration::try_from_secs_f64(seconds).map_err(|_| format!("duration {:?} is too long", input))
+}
+
+#[derive(Debug, Clone)]
+struct TestResult {
+    /// Start of the whole run; the timeline counts seconds from here
+    started: Instant,
+    total_requests: usize,
+    successful_requests: usize,
+    failed_requests: usize,
+    latencies: LatencyHistogram,
+    errors: BTreeMap<ErrorCategory, usize>,
+    connections: ConnectionStats,
+    timeline: Vec<ThroughputSample>,
+    /// Scenario runs only, keyed by (scenario, step) index
+    steps: BTreeMap<(usize, usize), StepStats>,
+}
+
+#[derive(Debug, Clone)]
+struct StepStats {
+    name: String,
+    requests: usize,
+    failed: usize,
+    latencies: LatencyHistogram,
+    errors: BTreeMap<ErrorCategory, usize>,
+}
+
+impl StepStats {
+    fn new(name: String) -> Self {
+        StepStats {
+            name,
+            requests: 0,
+            failed: 0,
+            latencies: LatencyHistogram::new(),
+            errors: BTreeMap::new(),
+        }
+    }
+    
+    fn merge(&mut self, other: &StepStats) {
+        self.requests += other.requests;
+        self.failed += other.failed;
+        self.latencies.merge(&other.latencies);
+        for (category, count) in &other.errors {
+            *self.errors.entry(*category).or_insert(0) += count;
+        }
+    }
+}
+
+impl TestResult {
+    fn new(started: Instant) -> Self {
+        TestResult {
+            started,
+            total_requests: 0,
+            successful_requests: 0,
+            failed_requests: 0,
+            latencies: LatencyHistogram::new(),
+            errors: BTreeMap::new(),
+            connections: ConnectionStats::default(),
+            timeline: Vec::new(),
+            steps: BTreeMap::new(),
+        }
+    }
+    
+    fn record(&mut self, latency: Duration, outcome: Result<(), RequestError>) {
+        match outcome {
+            Ok(()) => {
+                self.total_requests += 1;
+                self.latencies.record(latency);
+                self.successful_requests += 1;
+                self.current_second().successful += 1;
+            }
+            Err(e) => self.record_failure(e.category()),
+        }
+    }
+    
+    fn record_failure(&mut self, category: ErrorCategory) {
+        self.total_requests += 1;
+        *self.errors.entry(category).or_insert(0) += 1;
+        self.failed_requests += 1;
+        self.current_second().failed += 1;
+    }
+    
+    /// Records a scenario step both in the totals and in its own stats
+    fn record_step(
+        &mut self,
+        step: (usize, usize),
+        name: String,
+        latency: Duration,
+        outcome: Result<(), RequestError>,
+    ) {
+        let stats = self.steps.entry(step).or_insert_with(|| StepStats::new(name));
+        stats.requests += 1;
+        match &outcome {
+            Ok(()) => stats.latencies.record(latency),
+            Err(e) => {
+                stats.failed += 1;
+                *stats.errors.entry(e.category()).or_insert(0) += 1;
+            }
+        }
+        self.record(latency, outcome);
+    }
+    
+    // Timeline entry for the second in which a request is being recorded
+    fn current_second(&mut self) -> &mut ThroughputSample {
+        let second = self.started.elapsed().as_secs();
+        let sample = self.timeline_entry(second);
+        sample.requests += 1;
+        sample
+    }
+    
+    fn timeline_entry(&mut self, second: u64) -> &mut ThroughputSample {
+        while self.timeline.len() as u64 <= second {
+            let next = self.timeline.len() as u64;
+            self.timeline.push(ThroughputSample { second: next, ..Default::default() });
+        }
+        &mut self.timeline[second as usize]
+    }
+    
+    /// Records the `sent` requests of one batch: a result per response, and
+    /// the batch error for every request left without one.
+    fn record_batch(&mut self, outcome: BatchOutcome, sent: usize, expectations: &Expectations) {
+        let answered = outcome.responses.len();
+        for (latency, response) in outcome.responses {
+            self.record(latency, expectations.check(&response));
+        }
+        if let Some(error) = outcome.error {
+            for _ in answered..sent {
+                self.record_failure(error.category());
+            }
+        }
+    }
+    
+    fn merge(&mut self, other: &TestResult) {
+        self.total_requests += other.total_requests;
+        self.successful_requests += other.successful_requests;
+        self.failed_requests += other.failed_requests;
+        self.latencies.merge(&other.latencies);
+        for (category, count) in &other.errors {
+            *self.errors.entry(*category).or_insert(0) += count;
+        }
+        self.connections.merge(&other.connections);
+        for sample in &other.timeline {
+            let entry = self.timeline_entry(sample.second);
+            entry.requests += sample.requests;
+            entry.successful += sample.successful;
+            entry.failed += sample.failed;
+        }
+        for (key, stats) in &other.steps {
+            self.steps
+                .entry(*key)
+                .or_insert_with(|| StepStats::new(stats.name.clone()))
+                .merge(stats);
+        }
+    }
+}
+
+// Closed loop: each connection waits for a response before sending the next
+// request, so a slow server lowers the offered load.
+async fn run_connection_test(config: Arc<LoadTestConfig>, keep_alive: bool, started: Instant) -> TestResult {
+    let pool = ConnectionPool::new(&config.target_url, keep_alive);
+    let request = config.request(keep_alive);
+    let expectations = config.expectations();
+    let deadline = Instant::now() + config.test_duration;
+    let mut result = TestResult::new(started);
+    let mut remaining = config.requests_per_connection;
+    
+    while remaining > 0 && Instant::now() < deadline {
+        let depth = remaining.min(config.pipeline as usize);
+        let outcome = pool
+            .send(request.as_bytes(), depth, Instant::now(), config.timeout)
+            .await;
+        result.record_batch(outcome, depth, &expectations);
+        remaining -= depth;
+        
+        sleep(config.delay).await;
+    }
+    
+    result.connections = pool.stats();
+    result
+}
+
+// Scenarios: each iteration runs one scenario's steps in order on the
+// worker's connection, carrying captured variables from step to step. The
+// rest of an iteration is skipped once a step fails.
+async fn run_scenario_worker(
+    config: Arc<LoadTestConfig>,
+    scenarios: Arc<ScenarioSet>,
+    worker: usize,
+    keep_alive: bool,
+    started: Instant,
+) -> TestResult {
+    let pool = ConnectionPool::new(&config.target_url, keep_alive);
+    let defaults = config.expectations();
+    let deadline = Instant::now() + config.test_duration;
+    let mut result = TestResult::new(started);
+    
+    'iterations: for iteration in 0..config.requests_per_connection {
+        let index = scenarios.pick(worker, iteration);
+        let scenario = &scenarios.scenarios[index];
+        let mut vars = scenarios.variables.clone();
+        
+        for (step_index, step) in scenario.steps.iter().enumerate() {
+            if Instant::now() >= deadline {
+                break 'iterations;
+            }
+            
+            let (latency, outcome) = run_step(&pool, &config, step, keep_alive, &defaults, &mut vars).await;
+            let failed = outcome.is_err();
+            let name = format!("{}/{}", scenario.name, step.label());
+            result.record_step((index, step_index), name, latency, outcome);
+            if failed {
+                break;
+            }
+            
+            sleep(step.think.unwrap_or(config.delay)).await;
+        }
+    }
+    
+    result.connections = pool.stats();
+    result
+}
+
+async fn run_step(
+    pool: &ConnectionPool,
+    config: &LoadTestConfig,
+    step: &scenario::Step,
+    keep_alive: bool,
+    defaults: &Expectations,
+    vars: &mut std::collections::HashMap<String, String>,
+) -> (Duration, Result<(), RequestError>) {
+    let request = match step.request(&config.target_url, keep_alive, vars) {
+        Ok(request) => request,
+        Err(e) => return (Duration::ZERO, Err(e)),
+    };
+    
+    let mut outcome = pool.send(&request, 1, Instant::now(), config.timeout).await;
+    let Some((latency, response)) = outcome.responses.pop() else {
+        let error = outcome
+            .error
+            .unwrap_or_else(|| RequestError::Malformed("no response".to_string()));
+        return (Duration::ZERO, Err(error));
+    };
+    
+    let checked = step
+        .expectations(defaults)
+        .check(&response)
+        .and_then(|()| step.captures(&response));
+    match checked {
+        Ok(captured) => {
+            vars.extend(captured);
+            (latency, Ok(()))
+        }
+        Err(e) => (latency, Err(e)),
+    }
+}
+
+// Open loop: requests go out on the profile's schedule no matter how slowly
+// the server answers. Latency is measured from the intended send time, so
+// time spent queued behind a slow server is counted (no coordinated omission).
+async fn run_open_loop(config: Arc<LoadTestConfig>, profile: &LoadProfile, keep_alive: bool) -> TestResult {
+    let pool = Arc::new(ConnectionPool::new(&config.target_url, keep_alive));
+    let request = Arc::new(config.request(keep_alive));
+    let expectations = config.expectations();
+    let in_flight = Arc::new(Semaphore::new(config.max_in_flight.max(1)));
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let start = Instant::now();
+    
+    let collector = tokio::spawn(async move {
+        let mut result = TestResult::new(start);
+        while let Some(outcome) = rx.recv().await {
+            result.record_batch(outcome, 1, &expectations);
+        }
+        result
+    });
+    
+    for offset in profile.send_times() {
+        let intended = start + offset;
+        sleep_until(intended).await;
+        
+        let permit = Arc::clone(&in_flight)
+            .acquire_owned()
+            .await
+            .expect("semaphore is never closed");
+        let limit = config.timeout;
+        let pool = Arc::clone(&pool);
+        let request = Arc::clone(&request);
+        let tx = tx.clone();
+        
+        tokio::spawn(async move {
+            let outcome = pool.send(request.as_bytes(), 1, intended, limit).await;
+            let _ = tx.send(outcome);
+            drop(permit);
+        });
+    }
+    drop(tx);
+    
+    let mut result = collector.await.expect("result collector panicked");
+    result.connections = pool.stats();
+    result
+}
+
+async fn run(
+    config: &Arc<LoadTestConfig>,
+    workload: &Workload,
+    keep_alive: bool,
+) -> Result<(TestResult, Duration), tokio::task::JoinError> {
+    let start_time = Instant::now();
+    
+    if let Workload::OpenLoop(profile) = workload {
+        let result = run_open_loop(Arc::clone(config), profile, keep_alive).await;
+        return Ok((result, start_time.elapsed()));
+    }
+    
+    let mut handles = Vec::new();
+    
+    for i in 0..config.concurrent_connections {
+        let config = Arc::clone(config);
+        let scenarios = match workload {
+            Workload::Scenarios(scenarios) => Some(Arc::clone(scenarios)),
+            _ => None,
+        };
+        
+        let handle = tokio::spawn(async move {
+            println!("Starting connection {}", i);
+            match scenarios {
+                Some(scenarios) => run_scenario_worker(config, scenarios, i, keep_alive, start_time).await,
+                None => run_connection_test(config, keep_alive, start_time).await,
+            }
+        });
+        
+        handles.push(handle);
+    }
+    
+    // All tasks are already running; awaiting in order only collects them
+    let mut total = TestResult::new(start_time);
+    for handle in handles {
+        let result = handle.await?;
+        println!("Connection completed: {} successful, {} failed", 
+                 result.successful_requests, result.failed_requests);
+        total.merge(&result);
+    }
+    
+    Ok((total, start_time.elapsed()))
+}
+
+fn print_results(total: &TestResult, total_time: Duration) {
+    let requests_per_second = total.total_requests as f64 / total_time.as_secs_f64();
+    
+    println!("\n=== Load Test Results ===");
+    println!("Total requests: {}", total.total_requests);
+    println!("Successful: {}", total.successful_requests);
+    println!("Failed: {}", total.failed_requests);
+    println!("Success rate: {:.2}%", (total.successful_requests as f64 / total.total_requests as f64) * 100.0);
+    println!("Requests per second: {:.2}", requests_per_second);
+    println!("Total time: {:?}", total_time);
+    
+    let connections = &total.connections;
+    println!("\n=== Connections ===");
+    println!("Opened: {}", connections.opened);
+    println!("Requests on reused connections: {} ({:.2}%)",
+             connections.reused_requests,
+             connections.reused_requests as f64 / total.total_requests.max(1) as f64 * 100.0);
+    println!("Stale keep-alive retries: {}", connections.stale_retries);
+    
+    print_error_breakdown(&total.errors, total.failed_requests);
+    print_step_stats(&total.steps);
+    total.latencies.print_report();
+}
+
+fn print_step_stats(steps: &BTreeMap<(usize, usize), StepStats>) {
+    if steps.is_empty() {
+        return;
+    }
+    let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+    
+    println!("\n=== Steps ===");
+    println!("  {:<32} {:>9} {:>8} {:>10} {:>10} {:>10}", "step", "requests", "failed", "mean ms", "p50 ms", "p99 ms");
+    for stats in steps.values() {
+        println!("  {:<32} {:>9} {:>8} {:>10.3} {:>10.3} {:>10.3}",
+                 stats.name, stats.requests, stats.failed, ms(stats.latencies.mean()),
+                 ms(stats.latencies.percentile(50.0)), ms(stats.latencies.percentile(99.0)));
+        for (category, count) in &stats.errors {
+            println!("    {:<30} {:>9}", category.to_string(), count);
+        }
+    }
+}
+
+fn print_connection_comparison(fresh: &(TestResult, Duration), pooled: &(TestResult, Duration)) {
+    let rps = |(result, time): &(TestResult, Duration)| result.total_requests as f64 / time.as_secs_f64();
+    let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+    
+    println!("\n=== Fresh vs Pooled Connections ===");
+    println!("{:<22} {:>12} {:>12}", "", "fresh", "pooled");
+    println!("{:<22} {:>12.2} {:>12.2}", "Requests per second", rps(fresh), rps(pooled));
+    println!("{:<22} {:>12} {:>12}", "Connections opened", fresh.0.connections.opened, pooled.0.connections.opened);
+    println!("{:<22} {:>12} {:>12}", "Failed requests", fresh.0.failed_requests, pooled.0.failed_requests);
+    println!("{:<22} {:>12.3} {:>12.3}", "Mean latency (ms)", ms(fresh.0.latencies.mean()), ms(pooled.0.latencies.mean()));
+    for p in histogram::REPORTED_PERCENTILES {
+        println!("{:<22} {:>12.3} {:>12.3}", format!("p{} latency (ms)", p),
+                 ms(fresh.0.latencies.percentile(p)), ms(pooled.0.latencies.percentile(p)));
+    }
+}
+
+fn build_report(
+    config: &LoadTestConfig,
+    workload: &Workload,
+    keep_alive: bool,
+    result: &TestResult,
+    total_time: Duration,
+) -> RunReport {
+    let micros = |d: Duration| d.as_micros() as u64;
+    let latencies = &result.latencies;
+    
+    RunReport {
+        config: report::RunConfig {
+            target: config.target_url.clone(),
+            path: config.path.clone(),
+            mode: match workload {
+                Workload::ClosedLoop => "closed",
+                Workload::OpenLoop(_) => "open",
+                Workload::Scenarios(_) => "scenarios",
+            }
+            .to_string(),
+            connections: config.concurrent_connections,
+            requests_per_connection: config.requests_per_connection,
+            profile: match workload {
+                Workload::OpenLoop(profile) => Some(profile.to_string()),
+                _ => None,
+            },
+            scenario: config.scenario.as_ref().map(|path| path.display().to_string()),
+            pipeline: config.pipeline,
+            keep_alive,
+            timeout_ms: config.timeout.as_millis() as u64,
+        },
+        summary: report::Summary {
+            total_requests: result.total_requests,
+            successful_requests: result.successful_requests,
+            failed_requests: result.failed_requests,
+            duration_secs: total_time.as_secs_f64(),
+            requests_per_second: result.total_requests as f64 / total_time.as_secs_f64(),
+        },
+        latency: report::LatencyReport {
+            samples: latencies.len(),
+            min_us: micros(latencies.min()),
+            mean_us: latencies.mean().as_secs_f64() * 1_000_000.0,
+            stdev_us: latencies.stdev().as_secs_f64() * 1_000_000.0,
+            max_us: micros(latencies.max()),
+            percentiles: histogram::REPORTED_PERCENTILES
+                .iter()
+                .map(|&p| (format!("p{}", p), micros(latencies.percentile(p))))
+                .collect(),
+            buckets: latencies
+                .distribution()
+                .into_iter()
+                .map(|b| report::BucketReport {
+                    lower_us: micros(b.lower),
+                    upper_us: b.upper.map(micros),
+                    count: b.count,
+                })
+                .collect(),
+            histogram: latencies
+                .recorded()
+                .into_iter()
+                .map(|(value, count)| report::HistogramEntry { value_us: micros(value), count })
+                .collect(),
+        },
+        errors: result
+            .errors
+            .iter()
+            .map(|(category, count)| (category.to_string(), *count))
+            .collect(),
+        connections: report::ConnectionReport {
+            opened: result.connections.opened,
+            reused_requests: result.connections.reused_requests,
+            stale_retries: result.connections.stale_retries,
+        },
+        timeline: result.timeline.clone(),
+        steps: result
+            .steps
+            .values()
+            .map(|stats| report::StepReport {
+                name: stats.name.clone(),
+                requests: stats.requests,
+                failed: stats.failed,
+                mean_us: stats.latencies.mean().as_secs_f64() * 1_000_000.0,
+                percentiles: histogram::REPORTED_PERCENTILES
+                    .iter()
+                    .map(|&p| (format!("p{}", p), micros(stats.latencies.percentile(p))))
+                    .collect(),
+                errors: stats
+                    .errors
+                    .iter()
+                    .map(|(category, count)| (category.to_string(), *count))
+                    .collect(),
+            })
+            .collect(),
+    }
+}
+
+fn export(report: &RunReport, config: &LoadTestConfig) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(path) = &config.json {
+        report.write_json(path)?;
+        println!("\nResults written to {}", path.display());
+    }
+    if let Some(path) = &config.csv {
+        report.write_csv(path)?;
+        println!("\nResults written to {}", path.display());
+    }
+    Ok(())
+}
+
+fn compare_results(
+    baseline: &Path,
+    candidate: &Path,
+    threshold: f64,
+    max_error_increase: f64,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let baseline_report = RunReport::load_json(baseline)?;
+    let candidate_report = RunReport::load_json(candidate)?;
+    
+    println!("Baseline:  {}", baseline.display());
+    println!("Candidate: {}", candidate.display());
+    println!("Threshold: {}% (error rate: +{} points)\n", threshold, max_error_increase);
+    
+    let comparison = report::compare(&baseline_report, &candidate_report, threshold, max_error_increase);
+    comparison.print();
+    Ok(comparison.passed())
+}
+
+fn print_error_breakdown(errors: &BTreeMap<ErrorCategory, usize>, total_failed: usize) {
+    if errors.is_empty() {
+        return;
+    }
+    
+    println!("\n=== Errors ===");
+    for (category, count) in errors {
+        let share = *count as f64 / total_failed as f64 * 100.0;
+        println!("  {:<20} {:>8} {:>6.2}%", category.to_string(), count, share);
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+    if let Some(Command::Compare { baseline, candidate, threshold, max_error_increase }) = cli.command {
+        if !compare_results(&baseline, &candidate, threshold, max_error_increase)? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    
+    let config = Arc::new(cli.run);
+    let workload = config.workload()?;
+    
+    println!("Starting load test...");
+    println!("Target: {}", config.target_url);
+    match &workload {
+        Workload::OpenLoop(profile) => {
+            println!("Mode: open loop");
+            println!("Load profile: {}", profile);
+            println!("Scheduled requests: {} over {:?}", profile.total_requests(), profile.duration());
+        }
+        Workload::Scenarios(scenarios) => {
+            println!("Mode: scenarios");
+            for scenario in &scenarios.scenarios {
+                println!("Scenario {:?}: weight {}, {} steps", scenario.name, scenario.weight, scenario.steps.len());
+            }
+            println!("Concurrent connections: {}", config.concurrent_connections);
+            println!("Iterations per connection: {}", config.requests_per_connection);
+        }
+        Workload::ClosedLoop => {
+            println!("Mode: closed loop");
+            println!("Concurrent connections: {}", config.concurrent_connections);
+            println!("Requests per connection: {}", config.requests_per_connection);
+            if config.pipeline > 1 {
+                println!("Pipeline depth: {}", config.pipeline);
+            }
+        }
+    }
+    
+    if config.compare_connections {
+        println!("\n--- Run 1: fresh connection per request ---");
+        let fresh = run(&config, &workload, false).await?;
+        print_results(&fresh.0, fresh.1);
+        
+        println!("\n--- Run 2: pooled keep-alive connections ---");
+        let pooled = run(&config, &workload, true).await?;
+        print_results(&pooled.0, pooled.1);
+        
+        print_connection_comparison(&fresh, &pooled);
+    } else {
+        let keep_alive = !config.fresh_connections;
+        let (total, total_time) = run(&config, &workload, keep_alive).await?;
+        print_results(&total, total_time);
+        
+        let report = build_report(&config, &workload, keep_alive, &total, total_time);
+        export(&report, &config)?;
+    }
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn config_from<const N: usize>(args: [&str; N]) -> LoadTestConfig {
+        Cli::parse_from(args).run
+    }
+    
+    #[test]
+    fn test_load_test_config() {
+        let config = LoadTestConfig::default();
+        assert_eq!(config.concurrent_connections, 10);
+        assert_eq!(config.requests_per_connection, 100);
+        assert!(config.expect_status.is_empty());
+    }
+    
+    #[test]
+    fn test_expectation_flags() {
+        let config = config_from([
+            "loadtest", "--expect-status", "200,301", "--expect-body", "Welcome",
+        ]);
+        let expectations = config.expectations();
+        assert_eq!(expectations.statuses, vec![200, 301]);
+        assert_eq!(expectations.body_contains, vec!["Welcome".to_string()]);
+    }
+    
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_duration("1.5s"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("2"), Ok(Duration::from_secs(2)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert!(parse_duration("fast").is_err());
+        assert!(parse_duration("5d").is_err());
+    }
+    
+    #[test]
+    fn test_parse_duration_rejects_overflow() {
+        assert_eq!(
+            parse_duration("99999999999999999999h"),
+            Err("duration \"99999999999999999999h\" is too long".to_string())
+        );
+    }
+    
+    #[test]
+    fn test_load_profile_selection() {
+        let closed = LoadTestConfig::default();
+        assert_eq!(closed.load_profile(), Ok(None));
+        
+        let rate = config_from(["loadtest", "--rate", "50", "--duration", "10s"]);
+        assert_eq!(
+            rate.load_profile(),
+            Ok(Some(LoadProfile::constant(50.0, Duration::from_secs(10))))
+        );
+        
+        let profile = config_from(["loadtest", "--profile", "ramp:1:10:5s"]);
+        assert_eq!(profile.load_profile().unwrap().unwrap().total_requests(), 28);
+        
+        assert!(Cli::try_parse_from(["loadtest", "--rate", "5", "--profile", "hold:1:1s"]).is_err());
+        assert!(config_from(["loadtest", "--rate", "0"]).load_profile().is_err());
+    }
+    
+    #[test]
+    fn test_result_merge() {
+        let mut a = TestResult::new(Instant::now());
+        a.record(Duration::from_millis(5), Ok(()));
+        a.record(Duration::from_millis(7), Err(RequestError::BadStatus(500)));
+        
+        let mut b = TestResult::new(Instant::now());
+        b.record(Duration::from_millis(9), Ok(()));
+        b.record(Duration::from_millis(1), Err(RequestError::BadStatus(500)));
+        b.record(Duration::from_millis(1), Err(RequestError::Timeout));
+        
+        a.merge(&b);
+        assert_eq!(a.total_requests, 5);
+        assert_eq!(a.successful_requests, 2);
+        assert_eq!(a.failed_requests, 3);
+        assert_eq!(a.latencies.len(), 2);
+        assert_eq!(a.errors[&ErrorCategory::BadStatus(500)], 2);
+        assert_eq!(a.errors[&ErrorCategory::Timeout], 1);
+    }
+    
+    #[test]
+    fn test_record_batch_counts_unanswered_requests() {
+        let response = http::HttpResponse {
+            status: 200,
+            reason: "OK".to_string(),
+            headers: Vec::new(),
+            body: Vec::new(),
+            keep_alive: true,
+        };
+        let outcome = BatchOutcome {
+            responses: vec![(Duration::from_millis(1), response)],
+            error: Some(RequestError::Timeout),
+        };
+        
+        let mut result = TestResult::new(Instant::now());
+        result.record_batch(outcome, 4, &Expectations::default());
+        assert_eq!(result.successful_requests, 1);
+        assert_eq!(result.errors[&ErrorCategory::Timeout], 3);
+    }
+    
+    #[test]
+    fn test_connection_flags() {
+        let config = config_from(["loadtest", "--pipeline", "8"]);
+        assert_eq!(config.pipeline, 8);
+        assert!(config.request(true).contains("Connection: keep-alive"));
+        assert!(config.request(false).contains("Connection: close"));
+        
+        assert!(Cli::try_parse_from(["loadtest", "--pipeline", "0"]).is_err());
+        assert!(Cli::try_parse_from(["loadtest", "--pipeline", "4", "--rate", "10"]).is_err());
+        assert!(Cli::try_parse_from(["loadtest", "--compare-connections", "--fresh-connections"]).is_err());
+    }
+    
+    #[test]
+    fn test_compare_subcommand() {
+        let cli = Cli::parse_from(["loadtest", "compare", "base.json", "new.json", "--threshold", "5"]);
+        match cli.command {
+            Some(Command::Compare { baseline, candidate, threshold, max_error_increase }) => {
+                assert_eq!(baseline, PathBuf::from("base.json"));
+                assert_eq!(candidate, PathBuf::from("new.json"));
+                assert_eq!(threshold, 5.0);
+                assert_eq!(max_error_increase, 1.0);
+            }
+            None => panic!("expected the compare subcommand"),
+        }
+        
+        let run = Cli::parse_from(["loadtest", "10.0.0.1:80", "--json", "out.json"]);
+        assert!(run.command.is_none());
+        assert_eq!(run.run.json, Some(PathBuf::from("out.json")));
+        assert!(Cli::try_parse_from(["loadtest", "--compare-connections", "--csv", "out.csv"]).is_err());
+    }
+    
+    #[test]
+    fn test_timeline_buckets_by_second() {
+        let now = Instant::now();
+        let mut late = TestResult::new(now - Duration::from_millis(2500));
+        late.record(Duration::from_millis(1), Ok(()));
+        late.record(Duration::from_millis(1), Err(RequestError::Timeout));
+        
+        let mut result = TestResult::new(now);
+        result.record(Duration::from_millis(1), Ok(()));
+        result.merge(&late);
+        
+        let counts: Vec<(usize, usize, usize)> = result
+            .timeline
+            .iter()
+            .map(|s| (s.requests, s.successful, s.failed))
+            .collect();
+        assert_eq!(counts, vec![(1, 1, 0), (0, 0, 0), (2, 1, 1)]);
+    }
+    
+    #[test]
+    fn test_scenario_flag() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scenario.json");
+        std::fs::write(&path, r#"{"scenarios": [{"name": "home", "steps": [{"path": "/"}]}]}"#).unwrap();
+        let path = path.to_str().unwrap();
+        
+        let config = config_from(["loadtest", "--scenario", path]);
+        assert!(matches!(config.workload(), Ok(Workload::Scenarios(_))));
+        assert!(matches!(config_from(["loadtest"]).workload(), Ok(Workload::ClosedLoop)));
+        assert!(Cli::try_parse_from(["loadtest", "--scenario", path, "--rate", "10"]).is_err());
+        assert!(Cli::try_parse_from(["loadtest", "--scenario", path, "--pipeline", "2"]).is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_scenario_carries_captured_variables() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        
+        // /login hands out user 42; only /users/42 with the issued token succeeds
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let mut buffer = [0; 1024];
+                    loop {
+                        let read = match stream.read(&mut buffer).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => n,
+                        };
+                        let request = String::from_utf8_lossy(&buffer[..read]).to_string();
+                        let response = if request.starts_with("POST /login ") {
+                            "HTTP/1.1 200 OK\r\nX-Token: t0k\r\nContent-Length: 11\r\n\r\n{\"id\": 42}\n"
+                        } else if request.starts_with("GET /users/42 ") && request.contains("Bearer t0k") {
+                            "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok"
+                        } else {
+                            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n"
+                        };
+                        stream.write_all(response.as_bytes()).await.unwrap();
+                    }
+                });
+            }
+        });
+        
+        let scenarios = ScenarioSet::parse(r#"{"scenarios": [
+            {"name": "user", "steps": [
+                {"name": "login", "method": "POST", "path": "/login", "body": "",
+                 "capture": {"token": {"header": "X-Token"}, "id": {"json": "id"}}},
+                {"name": "profile", "path": "/users/{id}", "headers": {"Authorization": "Bearer {token}"}},
+                {"name": "missing", "path": "/nope"},
+                {"name": "skipped", "path": "/users/{id}"}
+            ]}
+        ]}"#).unwrap();
+        let config = Arc::new(config_from(["loadtest", &addr, "-n", "3", "--delay", "0ms"]));
+        
+        let result = run_scenario_worker(config, Arc::new(scenarios), 0, true, Instant::now()).await;
+        let steps: Vec<(&str, usize, usize)> = result
+            .steps
+            .values()
+            .map(|s| (s.name.as_str(), s.requests, s.failed))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![("user/login", 3, 0), ("user/profile", 3, 0), ("user/missing", 3, 3)]
+        );
+        assert_eq!(result.total_requests, 9);
+        assert_eq!(result.errors[&ErrorCategory::BadStatus(404)], 3);
+        assert_eq!(result.connections.opened, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_open_loop_keeps_schedule_when_server_is_down() {
+        // Nothing listens on the discard port, so every request fails fast;
+        // the dispatcher must still issue exactly the scheduled number.
+        let config = Arc::new(config_from(["loadtest", "127.0.0.1:9"]));
+        let profile = LoadProfile::constant(100.0, Duration::from_millis(100));
+        
+        let result = run_open_loop(config, &profile, true).await;
+        assert_eq!(result.total_requests, 10);
+        assert_eq!(result.failed_requests, 10);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,264 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::parse_duration;
+
+/// One segment of a load profile. The request rate changes linearly from
+/// `start_rps` to `end_rps` over `duration`; constant stages have equal rates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stage {
+    pub start_rps: f64,
+    pub end_rps: f64,
+    pub duration: Duration,
+}
+
+impl Stage {
+    fn constant(rps: f64, duration: Duration) -> Self {
+        Stage {
+            start_rps: rps,
+            end_rps: rps,
+            duration,
+        }
+    }
+
+    /// Number of requests the stage issues (area under the rate curve)
+    fn requests(&self) -> f64 {
+        (self.start_rps + self.end_rps) / 2.0 * self.duration.as_secs_f64()
+    }
+
+    // Time into the stage at which `n` requests have been issued
+    fn time_of_request(&self, n: f64) -> f64 {
+        let a = self.start_rps;
+        let slope = (self.end_rps - self.start_rps) / self.duration.as_secs_f64();
+
+        // Solve a*t + slope/2 * t^2 = n for t
+        if slope.abs() < 1e-9 {
+            n / a
+        } else {
+            (-a + (a * a + 2.0 * slope * n).sqrt()) / slope
+        }
+    }
+}
+
+/// Target request rate over time for open-loop runs.
+///
+/// Stages are written `kind:args` on the command line (comma separated)
+/// or `kind args` in a profile file (one per line, `#` comments):
+///
+/// ```text
+/// hold:100:30s          constant 100 rps for 30s
+/// ramp:10:200:1m        linear ramp from 10 to 200 rps over a minute
+/// step:50:200:4:15s     4 steps from 50 to 200 rps, 15s each
+/// spike:1000:5s         burst at 1000 rps for 5s
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadProfile {
+    stages: Vec<Stage>,
+}
+
+impl LoadProfile {
+    pub fn constant(rps: f64, duration: Duration) -> Self {
+        LoadProfile {
+            stages: vec![Stage::constant(rps, duration)],
+        }
+    }
+
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut stages = Vec::new();
+        for stage in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let tokens: Vec<&str> = stage.split(':').collect();
+            stages.extend(parse_stage(&tokens).map_err(|e| format!("{:?}: {}", stage, e))?);
+        }
+        Self::from_stages(stages)
+    }
+
+    pub fn parse_file(contents: &str) -> Result<Self, String> {
+        let mut stages = Vec::new();
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            stages.extend(parse_stage(&tokens).map_err(|e| format!("line {}: {}", number + 1, e))?);
+        }
+        Self::from_stages(stages)
+    }
+
+    fn from_stages(stages: Vec<Stage>) -> Result<Self, String> {
+        if stages.is_empty() {
+            return Err("load profile has no stages".to_string());
+        }
+        Ok(LoadProfile { stages })
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.stages.iter().map(|s| s.duration).sum()
+    }
+
+    pub fn total_requests(&self) -> usize {
+        self.stages.iter().map(Stage::requests).sum::<f64>().ceil() as usize
+    }
+
+    /// Intended send offsets (from the start of the run) of every request.
+    /// They depend only on the profile, never on how fast the server answers.
+    pub fn send_times(&self) -> SendTimes<'_> {
+        SendTimes {
+            stages: &self.stages,
+            stage: 0,
+            stage_offset: 0.0,
+            issued_before_stage: 0.0,
+            next: 0,
+        }
+    }
+}
+
+impl fmt::Display for LoadProfile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, stage) in self.stages.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            if stage.start_rps == stage.end_rps {
+                write!(f, "{} rps for {:?}", stage.start_rps, stage.duration)?;
+            } else {
+                write!(f, "{} -> {} rps over {:?}", stage.start_rps, stage.end_rps, stage.duration)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct SendTimes<'a> {
+    stages: &'a [Stage],
+    stage: usize,
+    stage_offset: f64,
+    issued_before_stage: f64,
+    next: u64,
+}
+
+impl Iterator for SendTimes<'_> {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Duration> {
+        while let Some(stage) = self.stages.get(self.stage) {
+            let in_stage = self.next as f64 - self.issued_before_stage;
+            if in_stage < stage.requests() {
+                self.next += 1;
+                let offset = self.stage_offset + stage.time_of_request(in_stage);
+                return Some(Duration::from_secs_f64(offset));
+            }
+
+            self.issued_before_stage += stage.requests();
+            self.stage_offset += stage.duration.as_secs_f64();
+            self.stage += 1;
+        }
+        None
+    }
+}
+
+fn parse_stage(tokens: &[&str]) -> Result<Vec<Stage>, String> {
+    match tokens {
+        ["hold", rps, duration] | ["spike", rps, duration] => {
+            Ok(vec![Stage::constant(parse_rate(rps)?, parse_duration(duration)?)])
+        }
+        ["ramp", from, to, duration] => Ok(vec![Stage {
+            start_rps: parse_rate(from)?,
+            end_rps: parse_rate(to)?,
+            duration: parse_duration(duration)?,
+        }]),
+        ["step", from, to, steps, duration] => {
+            let (from, to) = (parse_rate(from)?, parse_rate(to)?);
+            let steps: usize = steps
+                .parse()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| format!("invalid step count {:?}", steps))?;
+            let duration = parse_duration(duration)?;
+
+            let increment = if steps > 1 { (to - from) / (steps - 1) as f64 } else { 0.0 };
+            Ok((0..steps)
+                .map(|i| Stage::constant(from + increment * i as f64, duration))
+                .collect())
+        }
+        [kind, ..] if ["hold", "spike", "ramp", "step"].contains(kind) => {
+            Err(format!("wrong number of arguments for {:?}", kind))
+        }
+        [kind, ..] => Err(format!("unknown stage {:?} (expected hold, ramp, step or spike)", kind)),
+        [] => Err("empty stage".to_string()),
+    }
+}
+
+fn parse_rate(input: &str) -> Result<f64, String> {
+    input
+        .parse::<f64>()
+        .ok()
+        .filter(|rps| rps.is_finite() && *rps >= 0.0)
+        .ok_or_else(|| format!("invalid rate {:?}", input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(times: impl Iterator<Item = Duration>) -> Vec<f64> {
+        times.map(|t| (t.as_secs_f64() * 1000.0).round() / 1000.0).collect()
+    }
+
+    #[test]
+    fn test_constant_rate_send_times() {
+        let profile = LoadProfile::constant(4.0, Duration::from_secs(1));
+
+        assert_eq!(profile.total_requests(), 4);
+        assert_eq!(secs(profile.send_times()), vec![0.0, 0.25, 0.5, 0.75]);
+    }
+
+    #[test]
+    fn test_ramp_send_times() {
+        // 0 -> 4 rps over 2s: n requests have been issued after sqrt(n) seconds
+        let profile = LoadProfile::parse("ramp:0:4:2s").unwrap();
+
+        assert_eq!(secs(profile.send_times()), vec![0.0, 1.0, 1.414, 1.732]);
+    }
+
+    #[test]
+    fn test_stages_are_consecutive() {
+        let profile = LoadProfile::parse("hold:2:1s, spike:10:500ms").unwrap();
+
+        assert_eq!(profile.duration(), Duration::from_millis(1500));
+        assert_eq!(
+            secs(profile.send_times()),
+            vec![0.0, 0.5, 1.0, 1.1, 1.2, 1.3, 1.4]
+        );
+    }
+
+    #[test]
+    fn test_step_expands_to_constant_stages() {
+        let profile = LoadProfile::parse("step:10:40:4:5s").unwrap();
+        let rates: Vec<f64> = profile.stages.iter().map(|s| s.start_rps).collect();
+
+        assert_eq!(rates, vec![10.0, 20.0, 30.0, 40.0]);
+        assert_eq!(profile.duration(), Duration::from_secs(20));
+    }
+
+    #[test]
+    fn test_profile_file() {
+        let contents = "# warm up\nramp 0 100 10s\n\nhold 100 30s  # steady state\nspike 500 2s\n";
+        let profile = LoadProfile::parse_file(contents).unwrap();
+
+        assert_eq!(profile.stages.len(), 3);
+        assert_eq!(profile.duration(), Duration::from_secs(42));
+    }
+
+    #[test]
+    fn test_invalid_profiles() {
+        assert!(LoadProfile::parse("").is_err());
+        assert!(LoadProfile::parse("hold:100").is_err());
+        assert!(LoadProfile::parse("hold:-5:1s").is_err());
+        assert!(LoadProfile::parse("wave:1:2:3s").is_err());
+        assert!(LoadProfile::parse("step:1:10:0:1s").is_err());
+
+        let error = LoadProfile::parse_file("hold 10 1s\nramp 1 2").unwrap_err();
+        assert!(error.starts_with("line 2"), "{}", error);
+    }
+}
@@ -0,0 +1,477 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// Everything recorded about one run, in the shape written by `--json`
+/// and read back by `loadtest compare`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunReport {
+    pub config: RunConfig,
+    pub summary: Summary,
+    pub latency: LatencyReport,
+    pub errors: BTreeMap<String, usize>,
+    pub connections: ConnectionReport,
+    pub timeline: Vec<ThroughputSample>,
+    /// Per-step stats of scenario runs
+    #[serde(default)]
+    pub steps: Vec<StepReport>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunConfig {
+    pub target: String,
+    pub path: String,
+    pub mode: String,
+    pub connections: usize,
+    pub requests_per_connection: usize,
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub scenario: Option<String>,
+    pub pipeline: u16,
+    pub keep_alive: bool,
+    pub timeout_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    pub total_requests: usize,
+    pub successful_requests: usize,
+    pub failed_requests: usize,
+    pub duration_secs: f64,
+    pub requests_per_second: f64,
+}
+
+impl Summary {
+    /// Failed requests as a percentage of all requests
+    pub fn error_rate(&self) -> f64 {
+        if self.total_requests == 0 {
+            return 0.0;
+        }
+        self.failed_requests as f64 / self.total_requests as f64 * 100.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LatencyReport {
+    pub samples: u64,
+    pub min_us: u64,
+    pub mean_us: f64,
+    pub stdev_us: f64,
+    pub max_us: u64,
+    /// Keyed by percentile, e.g. "p99.9"
+    pub percentiles: BTreeMap<String, u64>,
+    pub buckets: Vec<BucketReport>,
+    /// Every distinct recorded value (at histogram precision) and its count
+    pub histogram: Vec<HistogramEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BucketReport {
+    pub lower_us: u64,
+    pub upper_us: Option<u64>,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistogramEntry {
+    pub value_us: u64,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionReport {
+    pub opened: usize,
+    pub reused_requests: usize,
+    pub stale_retries: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepReport {
+    /// `scenario/step`
+    pub name: String,
+    pub requests: usize,
+    pub failed: usize,
+    pub mean_us: f64,
+    pub percentiles: BTreeMap<String, u64>,
+    pub errors: BTreeMap<String, usize>,
+}
+
+/// Requests completed during one second of the run
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ThroughputSample {
+    pub second: u64,
+    pub requests: usize,
+    pub successful: usize,
+    pub failed: usize,
+}
+
+impl RunReport {
+    pub fn write_json(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn load_json(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| format!("{} is not a loadtest result: {}", path.display(), e).into())
+    }
+
+    pub fn write_csv(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(path, self.to_csv())?;
+        Ok(())
+    }
+
+    /// Long-format CSV (`section,key,metric,value`) so every part of the
+    /// report fits in one file that spreadsheets and pandas can pivot.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("section,key,metric,value\n");
+        let mut row = |section: &str, key: &str, metric: &str, value: String| {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{}",
+                csv_field(section),
+                csv_field(key),
+                csv_field(metric),
+                csv_field(&value)
+            );
+        };
+
+        let config = &self.config;
+        row("config", "", "target", config.target.clone());
+        row("config", "", "path", config.path.clone());
+        row("config", "", "mode", config.mode.clone());
+        row("config", "", "connections", config.connections.to_string());
+        row("config", "", "requests_per_connection", config.requests_per_connection.to_string());
+        row("config", "", "profile", config.profile.clone().unwrap_or_default());
+        row("config", "", "scenario", config.scenario.clone().unwrap_or_default());
+        row("config", "", "pipeline", config.pipeline.to_string());
+        row("config", "", "keep_alive", config.keep_alive.to_string());
+        row("config", "", "timeout_ms", config.timeout_ms.to_string());
+
+        let summary = &self.summary;
+        row("summary", "", "total_requests", summary.total_requests.to_string());
+        row("summary", "", "successful_requests", summary.successful_requests.to_string());
+        row("summary", "", "failed_requests", summary.failed_requests.to_string());
+        row("summary", "", "duration_secs", format!("{:.6}", summary.duration_secs));
+        row("summary", "", "requests_per_second", format!("{:.3}", summary.requests_per_second));
+
+        let latency = &self.latency;
+        row("latency", "", "samples", latency.samples.to_string());
+        row("latency", "", "min_us", latency.min_us.to_string());
+        row("latency", "", "mean_us", format!("{:.3}", latency.mean_us));
+        row("latency", "", "stdev_us", format!("{:.3}", latency.stdev_us));
+        row("latency", "", "max_us", latency.max_us.to_string());
+        for (percentile, micros) in &latency.percentiles {
+            row("percentile", percentile, "latency_us", micros.to_string());
+        }
+        for bucket in &latency.buckets {
+            let upper = bucket.upper_us.map(|u| u.to_string()).unwrap_or_else(|| "inf".to_string());
+            row("bucket", &format!("{}-{}", bucket.lower_us, upper), "count", bucket.count.to_string());
+        }
+        for entry in &latency.histogram {
+            row("histogram", &entry.value_us.to_string(), "count", entry.count.to_string());
+        }
+
+        for (category, count) in &self.errors {
+            row("error", category, "count", count.to_string());
+        }
+
+        let connections = &self.connections;
+        row("connections", "", "opened", connections.opened.to_string());
+        row("connections", "", "reused_requests", connections.reused_requests.to_string());
+        row("connections", "", "stale_retries", connections.stale_retries.to_string());
+
+        for sample in &self.timeline {
+            let second = sample.second.to_string();
+            row("timeline", &second, "requests", sample.requests.to_string());
+            row("timeline", &second, "successful", sample.successful.to_string());
+            row("timeline", &second, "failed", sample.failed.to_string());
+        }
+
+        for step in &self.steps {
+            row("step", &step.name, "requests", step.requests.to_string());
+            row("step", &step.name, "failed", step.failed.to_string());
+            row("step", &step.name, "mean_us", format!("{:.3}", step.mean_us));
+            for (percentile, micros) in &step.percentiles {
+                row("step", &step.name, &format!("{}_us", percentile), micros.to_string());
+            }
+            for (category, count) in &step.errors {
+                row("step_error", &step.name, category, count.to_string());
+            }
+        }
+
+        csv
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Better {
+    Higher,
+    Lower,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricDelta {
+    pub name: String,
+    pub baseline: f64,
+    pub candidate: f64,
+    pub regressed: bool,
+}
+
+impl MetricDelta {
+    /// Relative change in percent; `None` when the baseline is zero
+    pub fn change_percent(&self) -> Option<f64> {
+        if self.baseline == 0.0 {
+            return None;
+        }
+        Some((self.candidate - self.baseline) / self.baseline * 100.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub deltas: Vec<MetricDelta>,
+}
+
+impl Comparison {
+    pub fn passed(&self) -> bool {
+        self.deltas.iter().all(|d| !d.regressed)
+    }
+
+    pub fn print(&self) {
+        println!(
+            "{:<24} {:>14} {:>14} {:>10}  status",
+            "metric", "baseline", "candidate", "change"
+        );
+        for delta in &self.deltas {
+            let change = match delta.change_percent() {
+                Some(change) => format!("{:+.2}%", change),
+                None => "n/a".to_string(),
+            };
+            let status = if delta.regressed { "REGRESSED" } else { "ok" };
+            println!(
+                "{:<24} {:>14.3} {:>14.3} {:>10}  {}",
+                delta.name, delta.baseline, delta.candidate, change, status
+            );
+        }
+
+        if self.passed() {
+            println!("\nPASS: no regressions beyond the thresholds");
+        } else {
+            let failed: Vec<&str> = self
+                .deltas
+                .iter()
+                .filter(|d| d.regressed)
+                .map(|d| d.name.as_str())
+                .collect();
+            println!("\nFAIL: regressed {}", failed.join(", "));
+        }
+    }
+}
+
+/// Compares a candidate run against a baseline. Throughput and latency
+/// regress when they get worse by more than `threshold_percent`; the error
+/// rate regresses when it rises by more than `max_error_increase` points.
+pub fn compare(
+    baseline: &RunReport,
+    candidate: &RunReport,
+    threshold_percent: f64,
+    max_error_increase: f64,
+) -> Comparison {
+    let mut deltas = Vec::new();
+
+    let mut relative = |name: &str, better: Better, base: f64, cand: f64| {
+        let worse_by = match better {
+            Better::Higher => base - cand,
+            Better::Lower => cand - base,
+        };
+        let regressed = base > 0.0 && worse_by / base * 100.0 > threshold_percent;
+        deltas.push(MetricDelta {
+            name: name.to_string(),
+            baseline: base,
+            candidate: cand,
+            regressed,
+        });
+    };
+
+    relative(
+        "requests_per_second",
+        Better::Higher,
+        baseline.summary.requests_per_second,
+        candidate.summary.requests_per_second,
+    );
+    relative(
+        "mean_latency_ms",
+        Better::Lower,
+        baseline.latency.mean_us / 1000.0,
+        candidate.latency.mean_us / 1000.0,
+    );
+    for (name, base) in &baseline.latency.percentiles {
+        if let Some(cand) = candidate.latency.percentiles.get(name) {
+            relative(
+                &format!("{}_latency_ms", name),
+                Better::Lower,
+                *base as f64 / 1000.0,
+                *cand as f64 / 1000.0,
+            );
+        }
+    }
+    relative(
+        "max_latency_ms",
+        Better::Lower,
+        baseline.latency.max_us as f64 / 1000.0,
+        candidate.latency.max_us as f64 / 1000.0,
+    );
+
+    let (base_errors, cand_errors) = (baseline.summary.error_rate(), candidate.summary.error_rate());
+    deltas.push(MetricDelta {
+        name: "error_rate_percent".to_string(),
+        baseline: base_errors,
+        candidate: cand_errors,
+        regressed: cand_errors - base_errors > max_error_increase,
+    });
+
+    Comparison { deltas }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(rps: f64, p99_us: u64, failed: usize) -> RunReport {
+        RunReport {
+            config: RunConfig {
+                target: "127.0.0.1:8080".to_string(),
+                path: "/".to_string(),
+                mode: "closed".to_string(),
+                connections: 10,
+                requests_per_connection: 100,
+                profile: None,
+                scenario: None,
+                pipeline: 1,
+                keep_alive: true,
+                timeout_ms: 5000,
+            },
+            summary: Summary {
+                total_requests: 1000,
+                successful_requests: 1000 - failed,
+                failed_requests: failed,
+                duration_secs: 1000.0 / rps,
+                requests_per_second: rps,
+            },
+            latency: LatencyReport {
+                samples: (1000 - failed) as u64,
+                min_us: 100,
+                mean_us: 500.0,
+                stdev_us: 50.0,
+                max_us: p99_us * 2,
+                percentiles: BTreeMap::from([
+                    ("p50".to_string(), 500),
+                    ("p99".to_string(), p99_us),
+                ]),
+                buckets: vec![BucketReport { lower_us: 0, upper_us: Some(1000), count: 1000 }],
+                histogram: vec![HistogramEntry { value_us: 500, count: 1000 }],
+            },
+            errors: BTreeMap::from([("bad status 500".to_string(), failed)]),
+            connections: ConnectionReport { opened: 10, reused_requests: 990, stale_retries: 0 },
+            timeline: vec![ThroughputSample { second: 0, requests: 1000, successful: 1000, failed: 0 }],
+            steps: vec![StepReport {
+                name: "shopper/login".to_string(),
+                requests: 1000,
+                failed,
+                mean_us: 500.0,
+                percentiles: BTreeMap::from([("p99".to_string(), p99_us)]),
+                errors: BTreeMap::from([("bad status 500".to_string(), failed)]),
+            }],
+        }
+    }
+
+    fn regressed(comparison: &Comparison) -> Vec<&str> {
+        comparison
+            .deltas
+            .iter()
+            .filter(|d| d.regressed)
+            .map(|d| d.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run.json");
+        let original = report(1000.0, 2000, 3);
+
+        original.write_json(&path).unwrap();
+        assert_eq!(RunReport::load_json(&path).unwrap(), original);
+    }
+
+    #[test]
+    fn test_load_rejects_other_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("other.json");
+        std::fs::write(&path, r#"{"hello": "world"}"#).unwrap();
+
+        let error = RunReport::load_json(&path).unwrap_err().to_string();
+        assert!(error.contains("not a loadtest result"), "{}", error);
+    }
+
+    #[test]
+    fn test_csv_sections() {
+        let csv = report(1000.0, 2000, 3).to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines[0], "section,key,metric,value");
+        assert!(lines.contains(&"config,,target,127.0.0.1:8080"));
+        assert!(lines.contains(&"percentile,p99,latency_us,2000"));
+        assert!(lines.contains(&"bucket,0-1000,count,1000"));
+        assert!(lines.contains(&"error,bad status 500,count,3"));
+        assert!(lines.contains(&"timeline,0,requests,1000"));
+        assert!(lines.contains(&"step,shopper/login,p99_us,2000"));
+        assert!(lines.contains(&"step_error,shopper/login,bad status 500,3"));
+        assert!(lines.iter().all(|l| l.split(',').count() == 4));
+    }
+
+    #[test]
+    fn test_csv_escaping() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("ramp, hold"), "\"ramp, hold\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn test_compare_within_threshold() {
+        let comparison = compare(&report(1000.0, 2000, 0), &report(950.0, 2100, 0), 10.0, 1.0);
+        assert!(comparison.passed(), "{:?}", regressed(&comparison));
+    }
+
+    #[test]
+    fn test_compare_detects_regressions() {
+        let comparison = compare(&report(1000.0, 2000, 0), &report(800.0, 3000, 50), 10.0, 1.0);
+
+        assert!(!comparison.passed());
+        assert_eq!(
+            regressed(&comparison),
+            vec!["requests_per_second", "p99_latency_ms", "max_latency_ms", "error_rate_percent"]
+        );
+    }
+
+    #[test]
+    fn test_improvements_never_regress() {
+        let comparison = compare(&report(1000.0, 2000, 10), &report(5000.0, 500, 0), 0.0, 0.0);
+        assert!(comparison.passed());
+
+        let p99 = comparison.deltas.iter().find(|d| d.name == "p99_latency_ms").unwrap();
+        assert_eq!(p99.change_percent(), Some(-75.0));
+    }
+}
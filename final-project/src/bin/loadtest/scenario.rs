@@ -0,0 +1,535 @@
+use serde::{Deserialize, Deserializer};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write as _;
+use std::time::Duration;
+
+use crate::http::{Expectations, HttpResponse, RequestError};
+use crate::parse_duration;
+
+/// Scenario file as written on disk (JSON):
+///
+/// ```text
+/// {
+///   "variables": { "password": "hunter2" },
+///   "scenarios": [
+///     {
+///       "name": "shopper",
+///       "weight": 3,
+///       "steps": [
+///         { "name": "login", "method": "POST", "path": "/login",
+///           "body": "user=alice&password={password}",
+///           "capture": { "token": { "header": "X-Token" }, "id": { "json": "user.id" } },
+///           "think": "500ms" },
+///         { "name": "profile", "path": "/users/{id}",
+///           "headers": { "Authorization": "Bearer {token}" } }
+///       ]
+///     }
+///   ]
+/// }
+/// ```
+///
+/// `{name}` in a path, header value or body is replaced by the variable of
+/// that name; braces around anything that is not an identifier (such as a
+/// JSON body) are left alone.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScenarioFile {
+    #[serde(default)]
+    variables: BTreeMap<String, String>,
+    scenarios: Vec<Scenario>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    pub name: String,
+    /// Relative share of iterations that run this scenario
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Step {
+    pub name: Option<String>,
+    #[serde(default = "default_method")]
+    pub method: String,
+    pub path: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub body: Option<String>,
+    /// Accepted status codes; falls back to --expect-status
+    #[serde(default)]
+    pub expect_status: Vec<u16>,
+    /// Required body text; falls back to --expect-body
+    #[serde(default)]
+    pub expect_body: Vec<String>,
+    /// Variables to set from the response
+    #[serde(default)]
+    pub capture: BTreeMap<String, Capture>,
+    /// Pause after the step; falls back to --delay
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub think: Option<Duration>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Capture {
+    /// Value of a response header
+    Header(String),
+    /// Field of a JSON body, as a dot-separated path (`items.0.id`)
+    Json(String),
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+fn deserialize_duration<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let input = String::deserialize(deserializer)?;
+    parse_duration(&input).map(Some).map_err(serde::de::Error::custom)
+}
+
+impl Step {
+    /// Name shown in per-step stats: the given name or `METHOD path`
+    pub fn label(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => format!("{} {}", self.method, self.path),
+        }
+    }
+
+    pub fn expectations(&self, defaults: &Expectations) -> Expectations {
+        Expectations {
+            statuses: if self.expect_status.is_empty() {
+                defaults.statuses.clone()
+            } else {
+                self.expect_status.clone()
+            },
+            body_contains: if self.expect_body.is_empty() {
+                defaults.body_contains.clone()
+            } else {
+                self.expect_body.clone()
+            },
+        }
+    }
+
+    /// The raw request with every `{variable}` substituted
+    pub fn request(
+        &self,
+        host: &str,
+        keep_alive: bool,
+        vars: &HashMap<String, String>,
+    ) -> Result<Vec<u8>, RequestError> {
+        let connection = if keep_alive { "keep-alive" } else { "close" };
+        let mut request = format!(
+            "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: {}\r\n",
+            self.method,
+            render(&self.path, vars)?,
+            host,
+            connection
+        );
+        for (name, value) in &self.headers {
+            let _ = write!(request, "{}: {}\r\n", name, render(value, vars)?);
+        }
+
+        let body = match &self.body {
+            Some(body) => render(body, vars)?,
+            None => String::new(),
+        };
+        if self.body.is_some() {
+            let _ = write!(request, "Content-Length: {}\r\n", body.len());
+        }
+        request.push_str("\r\n");
+        request.push_str(&body);
+
+        Ok(request.into_bytes())
+    }
+
+    /// Variables captured from a successful response
+    pub fn captures(&self, response: &HttpResponse) -> Result<Vec<(String, String)>, RequestError> {
+        let mut json = None;
+        let mut captured = Vec::with_capacity(self.capture.len());
+
+        for (variable, capture) in &self.capture {
+            let value = match capture {
+                Capture::Header(name) => response
+                    .header(name)
+                    .map(str::to_string)
+                    .ok_or_else(|| RequestError::Capture(format!("no {} header", name)))?,
+                Capture::Json(path) => {
+                    if json.is_none() {
+                        let parsed = serde_json::from_slice::<serde_json::Value>(&response.body)
+                            .map_err(|e| RequestError::Capture(format!("body is not JSON: {}", e)))?;
+                        json = Some(parsed);
+                    }
+                    json_field(json.as_ref().unwrap(), path)
+                        .ok_or_else(|| RequestError::Capture(format!("no {:?} in JSON body", path)))?
+                }
+            };
+            captured.push((variable.clone(), value));
+        }
+
+        Ok(captured)
+    }
+}
+
+fn json_field(value: &serde_json::Value, path: &str) -> Option<String> {
+    let mut current = value;
+    for key in path.split('.') {
+        current = match current {
+            serde_json::Value::Array(items) => items.get(key.parse::<usize>().ok()?)?,
+            other => other.get(key)?,
+        };
+    }
+
+    match current {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Null => None,
+        other => Some(other.to_string()),
+    }
+}
+
+// Byte ranges and names of the `{identifier}` placeholders in a template
+fn placeholders(template: &str) -> Vec<(usize, usize, &str)> {
+    let mut found = Vec::new();
+    let mut rest = 0;
+
+    while let Some(open) = template[rest..].find('{').map(|i| i + rest) {
+        let Some(close) = template[open..].find('}').map(|i| i + open) else {
+            break;
+        };
+        let name = &template[open + 1..close];
+        let is_identifier = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+        if is_identifier {
+            found.push((open, close + 1, name));
+            rest = close + 1;
+        } else {
+            rest = open + 1;
+        }
+    }
+
+    found
+}
+
+fn render(template: &str, vars: &HashMap<String, String>) -> Result<String, RequestError> {
+    let mut output = String::with_capacity(template.len());
+    let mut last = 0;
+
+    for (start, end, name) in placeholders(template) {
+        let value = vars
+            .get(name)
+            .ok_or_else(|| RequestError::Capture(format!("variable {:?} is not set", name)))?;
+        output.push_str(&template[last..start]);
+        output.push_str(value);
+        last = end;
+    }
+    output.push_str(&template[last..]);
+
+    Ok(output)
+}
+
+/// Validated scenarios plus the order in which iterations pick them
+#[derive(Debug, Clone)]
+pub struct ScenarioSet {
+    pub variables: HashMap<String, String>,
+    pub scenarios: Vec<Scenario>,
+    schedule: Schedule,
+}
+
+// Longest cycle worth laying out in advance; heavier mixes draw from
+// running totals of the weights instead
+const MAX_SCHEDULE: u64 = 10_000;
+
+#[derive(Debug, Clone)]
+enum Schedule {
+    /// One full cycle of smooth weighted round robin
+    Smooth(Vec<usize>),
+    /// Where each scenario's share of the cycle ends
+    Cumulative(Vec<u64>),
+}
+
+impl ScenarioSet {
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let file: ScenarioFile =
+            serde_json::from_str(contents).map_err(|e| format!("invalid scenario file: {}", e))?;
+        if file.scenarios.is_empty() {
+            return Err("scenario file has no scenarios".to_string());
+        }
+
+        let mut names = HashSet::new();
+        for scenario in &file.scenarios {
+            if !names.insert(scenario.name.as_str()) {
+                return Err(format!("duplicate scenario {:?}", scenario.name));
+            }
+            validate(scenario, &file.variables)?;
+        }
+
+        let schedule = weighted_schedule(&file.scenarios);
+        Ok(ScenarioSet {
+            variables: file.variables.into_iter().collect(),
+            scenarios: file.scenarios,
+            schedule,
+        })
+    }
+
+    /// Scenario index for a worker's nth iteration. Workers start at
+    /// different points of the schedule so the mix holds across workers.
+    pub fn pick(&self, worker: usize, iteration: usize) -> usize {
+        match &self.schedule {
+            Schedule::Smooth(order) => order[(worker + iteration) % order.len()],
+            Schedule::Cumulative(ends) => {
+                let total = *ends.last().expect("at least one scenario");
+                let position = (worker as u64 + iteration as u64) % total;
+                ends.partition_point(|&end| end <= position)
+            }
+        }
+    }
+}
+
+fn validate(scenario: &Scenario, globals: &BTreeMap<String, String>) -> Result<(), String> {
+    let context = |step: &Step| format!("scenario {:?}, step {:?}", scenario.name, step.label());
+
+    if scenario.weight == 0 {
+        return Err(format!("scenario {:?} has weight 0", scenario.name));
+    }
+    if scenario.steps.is_empty() {
+        return Err(format!("scenario {:?} has no steps", scenario.name));
+    }
+
+    // Every placeholder must be a global or captured by an earlier step
+    let mut known: HashSet<&str> = globals.keys().map(String::as_str).collect();
+    for step in &scenario.steps {
+        let templates = std::iter::once(step.path.as_str())
+            .chain(step.headers.values().map(String::as_str))
+            .chain(step.body.as_deref());
+        for template in templates {
+            for (_, _, name) in placeholders(template) {
+                if !known.contains(name) {
+                    return Err(format!("{}: variable {:?} is never set", context(step), name));
+                }
+            }
+        }
+        if !step.path.starts_with('/') {
+            return Err(format!("{}: path must start with '/'", context(step)));
+        }
+        known.extend(step.capture.keys().map(String::as_str));
+    }
+
+    Ok(())
+}
+
+// Smooth weighted round robin: weights 3 and 1 give [0, 0, 1, 0] rather
+// than [0, 0, 0, 1], so short runs still see a representative mix. Weights
+// are reduced by their gcd first, so 300 and 100 mix the same as 3 and 1.
+fn weighted_schedule(scenarios: &[Scenario]) -> Schedule {
+    let divisor = scenarios.iter().map(|s| s.weight as u64).fold(0, gcd);
+    let weights: Vec<u64> = scenarios.iter().map(|s| s.weight as u64 / divisor).collect();
+    let total: u64 = weights.iter().sum();
+
+    if total > MAX_SCHEDULE {
+        let ends = weights
+            .iter()
+            .scan(0, |end, weight| {
+                *end += weight;
+                Some(*end)
+            })
+            .collect();
+        return Schedule::Cumulative(ends);
+    }
+
+    let mut current = vec![0i64; scenarios.len()];
+    let mut schedule = Vec::with_capacity(total as usize);
+    for _ in 0..total {
+        for (credit, weight) in current.iter_mut().zip(&weights) {
+            *credit += *weight as i64;
+        }
+        let (chosen, _) = current
+            .iter()
+            .enumerate()
+            .max_by_key(|&(i, credit)| (*credit, std::cmp::Reverse(i)))
+            .expect("at least one scenario");
+        current[chosen] -= total as i64;
+        schedule.push(chosen);
+    }
+
+    Schedule::Smooth(schedule)
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHOP: &str = r#"{
+        "variables": { "password": "hunter2" },
+        "scenarios": [
+            {
+                "name": "shopper",
+                "weight": 3,
+                "steps": [
+                    { "name": "login", "method": "POST", "path": "/login",
+                      "body": "user=alice&password={password}",
+                      "capture": { "token": { "header": "X-Token" }, "id": { "json": "user.id" } },
+                      "think": "250ms" },
+                    { "path": "/users/{id}", "headers": { "Authorization": "Bearer {token}" },
+                      "expect_status": [200] }
+                ]
+            },
+            { "name": "browser", "steps": [ { "path": "/" } ] }
+        ]
+    }"#;
+
+    fn response(headers: &[(&str, &str)], body: &str) -> HttpResponse {
+        HttpResponse {
+            status: 200,
+            reason: "OK".to_string(),
+            headers: headers.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            body: body.as_bytes().to_vec(),
+            keep_alive: true,
+        }
+    }
+
+    #[test]
+    fn test_parse_scenario_file() {
+        let set = ScenarioSet::parse(SHOP).unwrap();
+        let login = &set.scenarios[0].steps[0];
+
+        assert_eq!(set.scenarios.len(), 2);
+        assert_eq!(set.scenarios[1].weight, 1);
+        assert_eq!(login.think, Some(Duration::from_millis(250)));
+        assert_eq!(set.scenarios[0].steps[1].method, "GET");
+        assert_eq!(set.scenarios[0].steps[1].label(), "GET /users/{id}");
+    }
+
+    #[test]
+    fn test_weighted_mix() {
+        let set = ScenarioSet::parse(SHOP).unwrap();
+        let picks: Vec<usize> = (0..8).map(|i| set.pick(0, i)).collect();
+
+        assert_eq!(picks, vec![0, 0, 1, 0, 0, 0, 1, 0]);
+        assert_eq!(set.pick(1, 0), 0);
+        assert_eq!(set.pick(2, 0), 1);
+    }
+
+    #[test]
+    fn test_large_weights() {
+        let mix = |a: u32, b: u32| {
+            ScenarioSet::parse(&format!(
+                r#"{{"scenarios": [
+                    {{"name": "a", "weight": {}, "steps": [{{"path": "/"}}]}},
+                    {{"name": "b", "weight": {}, "steps": [{{"path": "/"}}]}}
+                ]}}"#,
+                a, b
+            ))
+            .unwrap()
+        };
+
+        // Reduced by their gcd, these mix exactly like 3 and 1
+        let set = mix(3_000_000, 1_000_000);
+        let picks: Vec<usize> = (0..4).map(|i| set.pick(0, i)).collect();
+        assert_eq!(picks, vec![0, 0, 1, 0]);
+
+        // Too many to lay out: each scenario gets its share of the cycle
+        let set = mix(1_000_000_000, 1);
+        assert_eq!(set.pick(0, 0), 0);
+        assert_eq!(set.pick(0, 999_999_999), 0);
+        assert_eq!(set.pick(0, 1_000_000_000), 1);
+        assert_eq!(set.pick(0, 1_000_000_001), 0);
+    }
+
+    #[test]
+    fn test_request_substitutes_variables() {
+        let set = ScenarioSet::parse(SHOP).unwrap();
+        let mut vars = set.variables.clone();
+        vars.insert("id".to_string(), "42".to_string());
+        vars.insert("token".to_string(), "abc".to_string());
+
+        let login = String::from_utf8(set.scenarios[0].steps[0].request("host", true, &vars).unwrap()).unwrap();
+        assert!(login.starts_with("POST /login HTTP/1.1\r\n"));
+        assert!(login.contains("Content-Length: 27\r\n"));
+        assert!(login.ends_with("\r\n\r\nuser=alice&password=hunter2"));
+
+        let profile = String::from_utf8(set.scenarios[0].steps[1].request("host", false, &vars).unwrap()).unwrap();
+        assert!(profile.starts_with("GET /users/42 HTTP/1.1\r\n"));
+        assert!(profile.contains("Authorization: Bearer abc\r\n"));
+        assert!(profile.contains("Connection: close\r\n"));
+        assert!(!profile.contains("Content-Length"));
+    }
+
+    #[test]
+    fn test_render_leaves_json_braces() {
+        let vars = HashMap::from([("name".to_string(), "alice".to_string())]);
+
+        assert_eq!(
+            render(r#"{"user": "{name}", "tags": {}}"#, &vars).unwrap(),
+            r#"{"user": "alice", "tags": {}}"#
+        );
+        assert!(render("/users/{missing}", &vars).is_err());
+    }
+
+    #[test]
+    fn test_captures() {
+        let set = ScenarioSet::parse(SHOP).unwrap();
+        let login = &set.scenarios[0].steps[0];
+
+        let ok = response(&[("x-token", "abc")], r#"{"user": {"id": 42, "tags": ["a"]}}"#);
+        let mut captured = login.captures(&ok).unwrap();
+        captured.sort();
+        assert_eq!(
+            captured,
+            vec![("id".to_string(), "42".to_string()), ("token".to_string(), "abc".to_string())]
+        );
+
+        let no_header = response(&[], r#"{"user": {"id": 42}}"#);
+        assert!(matches!(login.captures(&no_header), Err(RequestError::Capture(_))));
+        let not_json = response(&[("X-Token", "abc")], "<html>");
+        assert!(matches!(login.captures(&not_json), Err(RequestError::Capture(_))));
+    }
+
+    #[test]
+    fn test_json_paths() {
+        let value: serde_json::Value =
+            serde_json::from_str(r#"{"items": [{"id": "a1"}, {"id": 7}], "ok": true}"#).unwrap();
+
+        assert_eq!(json_field(&value, "items.0.id"), Some("a1".to_string()));
+        assert_eq!(json_field(&value, "items.1.id"), Some("7".to_string()));
+        assert_eq!(json_field(&value, "ok"), Some("true".to_string()));
+        assert_eq!(json_field(&value, "items.5.id"), None);
+        assert_eq!(json_field(&value, "missing"), None);
+    }
+
+    #[test]
+    fn test_invalid_scenarios() {
+        assert!(ScenarioSet::parse(r#"{"scenarios": []}"#).is_err());
+        assert!(ScenarioSet::parse(r#"{"scenarios": [{"name": "a", "steps": []}]}"#).is_err());
+        assert!(ScenarioSet::parse(r#"{"scenarios": [{"name": "a", "weight": 0, "steps": [{"path": "/"}]}]}"#).is_err());
+        assert!(ScenarioSet::parse(r#"{"scenarios": [{"name": "a", "steps": [{"path": "/", "think": "soon"}]}]}"#).is_err());
+
+        let error = ScenarioSet::parse(
+            r#"{"scenarios": [{"name": "a", "steps": [
+                {"path": "/users/{id}"},
+                {"path": "/login", "capture": {"id": {"json": "id"}}}
+            ]}]}"#,
+        )
+        .unwrap_err();
+        assert!(error.contains("\"id\" is never set"), "{}", error);
+    }
+}
@@ -0,0 +1,403 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+// Requests whose headers do not fit are rejected rather than buffered forever
+const MAX_HEADER_BYTES: usize = 64 * 1024;
+// Larger declared bodies are refused with 413 before any of them is buffered
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub version: String,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+impl Request {
+    /// Case-insensitive header lookup
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// HTTP/1.1 connections stay open unless the client asks to close;
+    /// HTTP/1.0 connections close unless the client asks to keep them.
+    pub fn keep_alive(&self) -> bool {
+        let connection = self.header("Connection").map(str::to_ascii_lowercase);
+        match connection.as_deref() {
+            Some("close") => false,
+            Some("keep-alive") => true,
+            _ => self.version == "HTTP/1.1",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Response {
+    pub status_code: u16,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+impl Response {
+    pub fn new(status_code: u16, body: String) -> Self {
+        let mut headers = HashMap::new();
+        headers.insert("Content-Type".to_string(), "text/html".to_string());
+        headers.insert("Server".to_string(), "RustWebServer/1.0".to_string());
+
+        Response {
+            status_code,
+            headers,
+            body,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let status_line = format!("HTTP/1.1 {} OK\r\n", self.status_code);
+        let headers: String = self.headers
+            .iter()
+            .map(|(k, v)| format!("{}: {}\r\n", k, v))
+            .collect();
+        // Keep-alive clients need the length to find the end of the body
+        let content_length = format!("Content-Length: {}\r\n", self.body.len());
+
+        format!("{}{}{}\r\n{}", status_line, headers, content_length, self.body).into_bytes()
+    }
+}
+
+// FIXME: This function has multiple parsing bugs
+pub fn parse_request(data: &[u8]) -> Result<Request, Box<dyn std::error::Error>> {
+    let request_str = String::from_utf8_lossy(data);
+    let lines: Vec<&str> = request_str.lines().collect();
+
+    if lines.is_empty() {
+        return Err("Empty request".into());
+    }
+
+    // TODO: Parse the request line properly
+    let request_line_parts: Vec<&str> = lines[0].split(' ').collect();
+    if request_line_parts.len() != 3 {
+        return Err("Invalid request line".into());
+    }
+
+    let method = request_line_parts[0].to_string();
+    let path = request_line_parts[1].to_string();
+    let version = request_line_parts[2].to_string();
+
+    // FIXME: Header parsing is incomplete
+    let mut headers = HashMap::new();
+    let mut body_start = 0;
+
+    for (i, line) in lines.iter().enumerate().skip(1) {
+        if line.is_empty() {
+            body_start = i + 1;
+            break;
+        }
+
+        // TODO: Handle header parsing edge cases
+        if let Some(colon_pos) = line.find(':') {
+            let key = line[..colon_pos].trim().to_string();
+            let value = line[colon_pos + 1..].trim().to_string();
+            headers.insert(key, value);
+        }
+    }
+
+    // TODO: Extract body properly
+    let body = if body_start < lines.len() {
+        lines[body_start..].join("\n")
+    } else {
+        String::new()
+    };
+
+    Ok(Request {
+        method,
+        path,
+        version,
+        headers,
+        body,
+    })
+}
+
+pub type Handler = Box<dyn Fn(&Request) -> Response + Send + Sync>;
+
+// FIXME: This router is extremely inefficient and broken
+pub struct Router {
+    routes: HashMap<String, Handler>,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Router {
+            routes: HashMap::new(),
+        }
+    }
+
+    pub fn add_route<F>(&mut self, path: &str, handler: F)
+    where
+        F: Fn(&Request) -> Response + Send + Sync + 'static,
+    {
+        self.routes.insert(path.to_string(), Box::new(handler));
+    }
+
+    // TODO: Implement proper routing with path parameters
+    pub fn handle_request(&self, request: &Request) -> Response {
+        // FIXME: This doesn't handle path parameters or wildcards
+        if let Some(handler) = self.routes.get(&request.path) {
+            handler(request)
+        } else {
+            Response::new(404, "Not Found".to_string())
+        }
+    }
+}
+
+impl Default for Router {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Answers a single request and closes the connection. This is what the
+/// server binary runs, so it still carries the exercise bugs.
+pub async fn handle_connection(mut stream: TcpStream, router: Arc<Router>) -> Result<(), Box<dyn std::error::Error>> {
+    // TODO: Fix buffer size issues
+    let mut buffer = [0; 1024];
+    let bytes_read = stream.read(&mut buffer).await?;
+
+    if bytes_read == 0 {
+        return Ok(());
+    }
+
+    // FIXME: This doesn't handle partial reads or large requests
+    let request = parse_request(&buffer[..bytes_read])?;
+    let mut response = router.handle_request(&request);
+    // The accept loop is sequential, so the connection can't be kept open
+    response.headers.insert("Connection".to_string(), "close".to_string());
+
+    // TODO: Handle write failures properly
+    stream.write_all(&response.to_bytes()).await?;
+    Ok(())
+}
+
+/// Serves requests on one connection until the client closes it or asks
+/// for `Connection: close`. Pipelined requests are answered in order, and
+/// all responses to the requests already received go out in one write.
+/// Only safe where every connection gets its own task, as in the benches.
+pub async fn serve_keep_alive(mut stream: TcpStream, router: Arc<Router>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut buffer = Vec::with_capacity(4096);
+    let mut chunk = [0; 4096];
+    let mut output = Vec::new();
+
+    loop {
+        let bytes_read = stream.read(&mut chunk).await?;
+        if bytes_read == 0 {
+            // Client hung up; a partial request left behind is dropped
+            return Ok(());
+        }
+        buffer.extend_from_slice(&chunk[..bytes_read]);
+
+        let mut keep_alive = true;
+        loop {
+            let length = match request_length(&buffer) {
+                Ok(Some(length)) => length,
+                Ok(None) => break,
+                Err(status) => {
+                    // The rest of the stream can't be framed, so answer and close
+                    let reason = match status {
+                        413 => "Payload Too Large",
+                        431 => "Request Header Fields Too Large",
+                        _ => "Bad Request",
+                    };
+                    let mut response = Response::new(status, reason.to_string());
+                    response.headers.insert("Connection".to_string(), "close".to_string());
+                    output.extend_from_slice(&response.to_bytes());
+                    keep_alive = false;
+                    break;
+                }
+            };
+            let request = parse_request(&buffer[..length])?;
+            buffer.drain(..length);
+
+            keep_alive = request.keep_alive();
+            let mut response = router.handle_request(&request);
+            if !keep_alive {
+                response.headers.insert("Connection".to_string(), "close".to_string());
+            }
+            output.extend_from_slice(&response.to_bytes());
+
+            if !keep_alive {
+                break;
+            }
+        }
+
+        // TODO: Handle write failures properly
+        if !output.is_empty() {
+            stream.write_all(&output).await?;
+            output.clear();
+        }
+        if !keep_alive {
+            return Ok(());
+        }
+    }
+}
+
+// Length of the first complete request in `buffer` (headers plus a
+// Content-Length body), or None if more bytes are needed. A request that
+// can't be accepted is the status code to answer it with.
+fn request_length(buffer: &[u8]) -> Result<Option<usize>, u16> {
+    let Some(header_end) = buffer.windows(4).position(|w| w == b"\r\n\r\n") else {
+        if buffer.len() > MAX_HEADER_BYTES {
+            return Err(431);
+        }
+        return Ok(None);
+    };
+
+    let headers = String::from_utf8_lossy(&buffer[..header_end]);
+    let mut content_length = 0;
+    for line in headers.lines().skip(1) {
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse::<usize>().map_err(|_| 400u16)?;
+            }
+        }
+    }
+    if content_length > MAX_BODY_BYTES {
+        return Err(413);
+    }
+
+    let total = (header_end + 4).checked_add(content_length).ok_or(413u16)?;
+    Ok((buffer.len() >= total).then_some(total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_parse_simple_request() {
+        let request_data = b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        let request = parse_request(request_data).unwrap();
+
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, "/");
+        assert_eq!(request.headers.get("Host"), Some(&"localhost".to_string()));
+    }
+
+    #[test]
+    fn test_response_formatting() {
+        let response = Response::new(200, "Hello".to_string());
+        let bytes = response.to_bytes();
+        let response_str = String::from_utf8(bytes).unwrap();
+
+        assert!(response_str.contains("HTTP/1.1 200 OK"));
+        assert!(response_str.contains("Content-Length: 5\r\n"));
+        assert!(response_str.contains("Hello"));
+    }
+
+    #[test]
+    fn test_keep_alive_negotiation() {
+        let keep_alive = |raw: &[u8]| parse_request(raw).unwrap().keep_alive();
+
+        assert!(keep_alive(b"GET / HTTP/1.1\r\nHost: a\r\n\r\n"));
+        assert!(!keep_alive(b"GET / HTTP/1.1\r\nconnection: Close\r\n\r\n"));
+        assert!(!keep_alive(b"GET / HTTP/1.0\r\nHost: a\r\n\r\n"));
+        assert!(keep_alive(b"GET / HTTP/1.0\r\nConnection: keep-alive\r\n\r\n"));
+    }
+
+    #[test]
+    fn test_request_length() {
+        assert_eq!(request_length(b"GET / HTTP/1.1\r\nHost: a").unwrap(), None);
+        assert_eq!(request_length(b"GET / HTTP/1.1\r\n\r\nGET /next").unwrap(), Some(18));
+
+        let post = b"POST /form HTTP/1.1\r\nContent-Length: 5\r\n\r\nab";
+        assert_eq!(request_length(post).unwrap(), None);
+        let post = b"POST /form HTTP/1.1\r\nContent-Length: 5\r\n\r\nabcde";
+        assert_eq!(request_length(post).unwrap(), Some(post.len()));
+
+        assert_eq!(request_length(b"POST / HTTP/1.1\r\nContent-Length: lots\r\n\r\n"), Err(400));
+        assert_eq!(request_length(&vec![b'a'; MAX_HEADER_BYTES + 1]), Err(431));
+
+        let huge = format!("POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n", usize::MAX);
+        assert_eq!(request_length(huge.as_bytes()), Err(413));
+        let too_big = format!("POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n", MAX_BODY_BYTES + 1);
+        assert_eq!(request_length(too_big.as_bytes()), Err(413));
+    }
+
+    #[tokio::test]
+    async fn test_connection_serves_pipelined_requests() {
+        let mut router = Router::new();
+        router.add_route("/", |_req| Response::new(200, "home".to_string()));
+        let router = Arc::new(router);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            serve_keep_alive(stream, router).await.unwrap();
+        });
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client
+            .write_all(b"GET / HTTP/1.1\r\n\r\nGET /missing HTTP/1.1\r\n\r\nGET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut responses = String::new();
+        client.read_to_string(&mut responses).await.unwrap();
+        server.await.unwrap();
+
+        assert_eq!(responses.matches("HTTP/1.1 ").count(), 3);
+        assert_eq!(responses.matches("home").count(), 2);
+        assert!(responses.contains("Not Found"));
+        assert!(responses.ends_with("home"));
+        assert!(responses.contains("Connection: close\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_handle_connection_closes_after_one_response() {
+        let mut router = Router::new();
+        router.add_route("/", |_req| Response::new(200, "home".to_string()));
+        let router = Arc::new(router);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            handle_connection(stream, router).await.unwrap();
+        });
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client.write_all(b"GET / HTTP/1.1\r\nHost: a\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        server.await.unwrap();
+
+        assert!(response.contains("Connection: close\r\n"));
+        assert!(response.ends_with("home"));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_is_refused() {
+        let router = Arc::new(Router::new());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            serve_keep_alive(stream, router).await.unwrap();
+        });
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let request = format!("POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n", usize::MAX);
+        client.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        server.await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 413 "));
+        assert!(response.contains("Connection: close\r\n"));
+    }
+}